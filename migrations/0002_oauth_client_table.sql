@@ -0,0 +1,5 @@
+CREATE TABLE oauth_client (
+    client_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    redirect_uris TEXT[] NOT NULL
+)
\ No newline at end of file
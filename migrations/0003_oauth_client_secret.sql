@@ -0,0 +1,3 @@
+ALTER TABLE oauth_client
+    ADD COLUMN client_secret TEXT,
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'service'
@@ -0,0 +1,2 @@
+ALTER TABLE oauth_client
+    ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT FALSE
@@ -0,0 +1,7 @@
+CREATE TABLE oauth_grant (
+    account_id INTEGER NOT NULL REFERENCES account (id) ON DELETE CASCADE,
+    client_id TEXT NOT NULL REFERENCES oauth_client (client_id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL,
+    granted_at BIGINT NOT NULL,
+    PRIMARY KEY (account_id, client_id)
+)
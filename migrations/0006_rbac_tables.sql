@@ -0,0 +1,36 @@
+CREATE TABLE roles (
+    name TEXT PRIMARY KEY,
+    description TEXT
+);
+
+CREATE TABLE permissions (
+    name TEXT PRIMARY KEY,
+    path_prefix TEXT NOT NULL,
+    description TEXT
+);
+
+CREATE TABLE role_permissions (
+    role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+    permission TEXT NOT NULL REFERENCES permissions (name) ON DELETE CASCADE,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO roles (name, description) VALUES
+    ('admin', 'Administrators'),
+    ('user', 'Registered users'),
+    ('service', 'OAuth clients acting on their own behalf');
+
+INSERT INTO roles (name) SELECT DISTINCT role FROM account ON CONFLICT DO NOTHING;
+INSERT INTO roles (name) SELECT DISTINCT role FROM oauth_client ON CONFLICT DO NOTHING;
+
+INSERT INTO permissions (name, path_prefix, description) VALUES
+    ('admin', '/api/admin', 'Administration API'),
+    ('user', '/api/user', 'User API');
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('admin', 'admin'),
+    ('admin', 'user'),
+    ('user', 'user');
+
+ALTER TABLE account ADD FOREIGN KEY (role) REFERENCES roles (name);
+ALTER TABLE oauth_client ADD FOREIGN KEY (role) REFERENCES roles (name)
@@ -0,0 +1,20 @@
+CREATE TABLE account_roles (
+    account_id INTEGER NOT NULL REFERENCES account (id) ON DELETE CASCADE,
+    role TEXT NOT NULL REFERENCES roles (name),
+    PRIMARY KEY (account_id, role)
+);
+
+INSERT INTO account_roles (account_id, role) SELECT id, role FROM account;
+
+ALTER TABLE account DROP COLUMN role;
+
+CREATE TABLE role_inheritance (
+    role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+    inherits TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+    PRIMARY KEY (role, inherits),
+    CHECK (role <> inherits)
+);
+
+INSERT INTO role_inheritance (role, inherits) VALUES ('admin', 'user');
+
+DELETE FROM role_permissions WHERE role = 'admin' AND permission = 'user'
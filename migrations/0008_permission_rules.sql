@@ -0,0 +1,7 @@
+ALTER TABLE permissions RENAME COLUMN path_prefix TO path_pattern;
+
+ALTER TABLE permissions ADD COLUMN method TEXT;
+
+ALTER TABLE permissions ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+
+UPDATE permissions SET path_pattern = rtrim(path_pattern, '/') || '/*'
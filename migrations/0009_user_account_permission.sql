@@ -0,0 +1,5 @@
+INSERT INTO permissions (name, method, path_pattern, description) VALUES
+    ('users', 'GET', '/api/users/{id}', 'User accounts');
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('user', 'users')
@@ -0,0 +1,13 @@
+CREATE TABLE account_totp (
+    account_id INTEGER PRIMARY KEY REFERENCES account (id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    last_used_step BIGINT
+);
+
+CREATE TABLE account_recovery_codes (
+    account_id INTEGER NOT NULL REFERENCES account_totp (account_id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (account_id, code_hash)
+)
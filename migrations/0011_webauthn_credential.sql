@@ -0,0 +1,11 @@
+CREATE TABLE webauthn_credential (
+    credential_id TEXT PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES account (id) ON DELETE CASCADE,
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name TEXT,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT
+);
+
+CREATE INDEX webauthn_credential_account_id_idx ON webauthn_credential (account_id)
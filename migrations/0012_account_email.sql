@@ -0,0 +1,6 @@
+ALTER TABLE account ADD COLUMN email TEXT, ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Accounts created before email verification existed have nothing left to verify.
+UPDATE account SET email_verified = TRUE;
+
+CREATE UNIQUE INDEX account_email_key ON account (lower(email))
\ No newline at end of file
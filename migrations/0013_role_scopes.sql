@@ -0,0 +1,4 @@
+ALTER TABLE roles ADD COLUMN scopes TEXT[] NOT NULL DEFAULT '{}';
+
+-- Administrators inherit the scopes of users.
+UPDATE roles SET scopes = ARRAY['accounts:read', 'sessions:read', 'sessions:write', 'grants:read', 'grants:write'] WHERE name = 'user'
\ No newline at end of file
@@ -0,0 +1,7 @@
+INSERT INTO permissions (name, method, path_pattern, description) VALUES
+    ('users.update', 'PATCH', '/api/users/{id}', 'Update user accounts');
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('user', 'users.update');
+
+UPDATE roles SET scopes = array_append(scopes, 'accounts:write') WHERE name = 'user'
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE roles SET scopes = scopes || ARRAY['admin:users', 'admin:clients', 'admin:roles', 'admin:permissions', 'admin:keys'] WHERE name = 'admin'
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT INTO account_roles (account_id, role) VALUES ($1, $2) ON CONFLICT DO NOTHING;
\ No newline at end of file
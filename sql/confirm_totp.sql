@@ -0,0 +1 @@
+WITH confirmed AS (UPDATE account_totp SET confirmed = TRUE, last_used_step = $2 WHERE account_id = $1 AND confirmed = FALSE RETURNING account_id) INSERT INTO account_recovery_codes (account_id, code_hash) SELECT confirmed.account_id, code_hash FROM confirmed, unnest($3::text[]) AS code_hash;
\ No newline at end of file
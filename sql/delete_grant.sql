@@ -0,0 +1 @@
+DELETE FROM oauth_grant WHERE account_id = $1 AND client_id = $2;
\ No newline at end of file
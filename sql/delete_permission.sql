@@ -0,0 +1 @@
+DELETE FROM permissions WHERE name = $1;
\ No newline at end of file
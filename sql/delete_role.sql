@@ -0,0 +1 @@
+DELETE FROM roles WHERE name = $1;
\ No newline at end of file
@@ -0,0 +1 @@
+DELETE FROM role_inheritance WHERE role = $1 AND inherits = $2;
\ No newline at end of file
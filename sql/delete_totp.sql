@@ -0,0 +1 @@
+DELETE FROM account_totp WHERE account_id = $1;
\ No newline at end of file
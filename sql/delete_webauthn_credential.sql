@@ -0,0 +1 @@
+DELETE FROM webauthn_credential WHERE credential_id = $1 AND account_id = $2;
\ No newline at end of file
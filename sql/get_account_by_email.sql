@@ -0,0 +1 @@
+SELECT a.id, a.username, NULL AS password, a.email, a.email_verified, COALESCE(array_agg(ar.role ORDER BY ar.role) FILTER (WHERE ar.role IS NOT NULL), '{}') AS roles FROM account a LEFT JOIN account_roles ar ON ar.account_id = a.id WHERE lower(a.email) = lower($1) GROUP BY a.id;
\ No newline at end of file
@@ -1 +1 @@
-SELECT id, username, password, role FROM account WHERE username = $1 LIMIT 1;
\ No newline at end of file
+SELECT a.id, a.username, a.password, a.email, a.email_verified, COALESCE(array_agg(ar.role ORDER BY ar.role) FILTER (WHERE ar.role IS NOT NULL), '{}') AS roles FROM account a LEFT JOIN account_roles ar ON ar.account_id = a.id WHERE a.username = $1 GROUP BY a.id LIMIT 1;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT client_id, name, redirect_uris, client_secret, role, disabled FROM oauth_client WHERE client_id = $1;
\ No newline at end of file
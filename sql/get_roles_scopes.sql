@@ -0,0 +1 @@
+WITH RECURSIVE effective (name) AS (SELECT unnest($1::TEXT[]) UNION SELECT ri.inherits FROM role_inheritance ri JOIN effective e ON ri.role = e.name) SELECT DISTINCT unnest(r.scopes) AS scope FROM roles r JOIN effective e ON e.name = r.name ORDER BY scope;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT credential_id, account_id, public_key, sign_count, name, created_at, last_used_at FROM webauthn_credential WHERE credential_id = $1;
\ No newline at end of file
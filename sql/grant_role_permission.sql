@@ -0,0 +1 @@
+INSERT INTO role_permissions (role, permission) VALUES ($1, $2) ON CONFLICT DO NOTHING;
\ No newline at end of file
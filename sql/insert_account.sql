@@ -1 +1 @@
-INSERT INTO account(username, password, role) VALUES ($1, $2, $3);
\ No newline at end of file
+WITH new_account AS (INSERT INTO account(username, password, email) VALUES ($1, $2, $3) RETURNING id) INSERT INTO account_roles(account_id, role) SELECT id, $4 FROM new_account;
\ No newline at end of file
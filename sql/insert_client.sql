@@ -0,0 +1 @@
+INSERT INTO oauth_client (client_id, name, redirect_uris, client_secret, role) VALUES ($1, $2, $3, $4, $5);
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT INTO permissions (name, method, path_pattern, priority, description) VALUES ($1, $2, $3, $4, $5);
\ No newline at end of file
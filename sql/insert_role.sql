@@ -0,0 +1 @@
+INSERT INTO roles (name, description, scopes) VALUES ($1, $2, $3);
\ No newline at end of file
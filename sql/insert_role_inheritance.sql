@@ -0,0 +1 @@
+INSERT INTO role_inheritance (role, inherits) VALUES ($1, $2) ON CONFLICT DO NOTHING;
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT INTO webauthn_credential (credential_id, account_id, public_key, sign_count, name, created_at) VALUES ($1, $2, $3, $4, $5, $6);
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT client_id, name, redirect_uris, client_secret, role, disabled FROM oauth_client ORDER BY client_id;
\ No newline at end of file
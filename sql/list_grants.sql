@@ -0,0 +1 @@
+SELECT g.client_id, c.name AS client_name, g.scopes, g.granted_at FROM oauth_grant g JOIN oauth_client c ON c.client_id = g.client_id WHERE g.account_id = $1 ORDER BY g.granted_at DESC;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT name, method, path_pattern, priority, description FROM permissions ORDER BY name;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT role, inherits FROM role_inheritance;
\ No newline at end of file
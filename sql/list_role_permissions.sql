@@ -0,0 +1 @@
+SELECT rp.role, p.method, p.path_pattern, p.priority FROM role_permissions rp JOIN permissions p ON p.name = rp.permission;
\ No newline at end of file
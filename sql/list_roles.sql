@@ -0,0 +1 @@
+SELECT r.name, r.description, r.scopes, COALESCE((SELECT array_agg(rp.permission ORDER BY rp.permission) FROM role_permissions rp WHERE rp.role = r.name), '{}') AS permissions, COALESCE((SELECT array_agg(ri.inherits ORDER BY ri.inherits) FROM role_inheritance ri WHERE ri.role = r.name), '{}') AS inherits FROM roles r ORDER BY r.name;
\ No newline at end of file
@@ -0,0 +1 @@
+WITH cleared AS (DELETE FROM account_recovery_codes WHERE account_id = $1) INSERT INTO account_recovery_codes (account_id, code_hash) SELECT account_id, code_hash FROM account_totp, unnest($2::text[]) AS code_hash WHERE account_id = $1 AND confirmed;
\ No newline at end of file
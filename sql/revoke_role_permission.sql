@@ -0,0 +1 @@
+DELETE FROM role_permissions WHERE role = $1 AND permission = $2;
\ No newline at end of file
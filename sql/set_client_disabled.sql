@@ -0,0 +1 @@
+UPDATE oauth_client SET disabled = $2 WHERE client_id = $1;
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE account SET email_verified = TRUE WHERE id = $1 AND email = $2;
\ No newline at end of file
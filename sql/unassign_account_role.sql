@@ -0,0 +1 @@
+DELETE FROM account_roles WHERE account_id = $1 AND role = $2;
\ No newline at end of file
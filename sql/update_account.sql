@@ -0,0 +1 @@
+UPDATE account SET username = COALESCE($2, username), email = COALESCE($3, email), email_verified = email_verified AND ($3::TEXT IS NULL OR lower($3) = lower(email)) WHERE id = $1;
\ No newline at end of file
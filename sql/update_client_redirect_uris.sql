@@ -0,0 +1 @@
+UPDATE oauth_client SET redirect_uris = $2 WHERE client_id = $1;
\ No newline at end of file
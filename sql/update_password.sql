@@ -0,0 +1 @@
+UPDATE account SET password = $2 WHERE id = $1;
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE webauthn_credential SET sign_count = $2, last_used_at = $3 WHERE credential_id = $1 AND (sign_count < $2 OR (sign_count = 0 AND $2 = 0));
\ No newline at end of file
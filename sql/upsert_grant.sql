@@ -0,0 +1 @@
+INSERT INTO oauth_grant (account_id, client_id, scopes, granted_at) VALUES ($1, $2, $3, $4) ON CONFLICT (account_id, client_id) DO UPDATE SET scopes = EXCLUDED.scopes, granted_at = EXCLUDED.granted_at;
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT INTO account_totp (account_id, secret) VALUES ($1, $2) ON CONFLICT (account_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL WHERE account_totp.confirmed = FALSE;
\ No newline at end of file
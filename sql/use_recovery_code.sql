@@ -0,0 +1 @@
+UPDATE account_recovery_codes SET used = TRUE WHERE account_id = $1 AND code_hash = $2 AND NOT used;
\ No newline at end of file
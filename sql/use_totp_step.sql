@@ -0,0 +1 @@
+UPDATE account_totp SET last_used_step = $2 WHERE account_id = $1 AND confirmed AND (last_used_step IS NULL OR last_used_step < $2);
\ No newline at end of file
@@ -0,0 +1,418 @@
+use std::{collections::HashMap, env, fs, path::Path, sync::Arc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use log::{error, info};
+use pkcs1::{der::Decode, RsaPublicKey};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use spki::{der::DecodePem, SubjectPublicKeyInfoOwned};
+
+/// Key material used to sign and verify access tokens.
+pub struct SigningKey {
+    /// Key identifier, written to the `kid` header of every token signed with this key.
+    pub kid: String,
+    /// Signing algorithm (HS256, RS256, ES256 or EdDSA).
+    pub algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    pub decoding_key: DecodingKey,
+    /// Public key published in the JWKS, `None` for the symmetric HS256 key.
+    pub jwk: Option<Jwk>,
+}
+
+/// A key that no longer signs tokens but still verifies the ones it already signed.
+struct RetiredKey {
+    key: Arc<SigningKey>,
+    /// Unix timestamp after which every token signed with the key has expired.
+    retire_at: i64,
+}
+
+/// The set of access token keys: the newest one signs, older ones only verify.
+pub struct KeyRing {
+    current: Arc<SigningKey>,
+    retired: Vec<RetiredKey>,
+    /// Retirement time of every key that stopped signing, including the expired ones,
+    /// persisted so keys left on disk never get a new grace period.
+    retirements: HashMap<String, i64>,
+}
+
+impl KeyRing {
+    /// Returns the key used to sign new tokens.
+    pub fn current(&self) -> Arc<SigningKey> {
+        self.current.clone()
+    }
+
+    /// Finds a key that is still valid for verification by its key id.
+    pub fn find(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        if self.current.kid == kid {
+            return Some(self.current.clone());
+        }
+
+        let now = Utc::now().timestamp();
+        self.retired
+            .iter()
+            .find(|r| r.key.kid == kid && r.retire_at > now)
+            .map(|r| r.key.clone())
+    }
+
+    /// Builds the JWK set of every public key still valid for verification.
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now().timestamp();
+        let keys = std::iter::once(&self.current)
+            .chain(
+                self.retired
+                    .iter()
+                    .filter(|r| r.retire_at > now)
+                    .map(|r| &r.key),
+            )
+            .filter_map(|key| key.jwk.clone())
+            .collect();
+
+        JwkSet { keys }
+    }
+
+    /// Replaces the keys of the ring with freshly loaded ones and promotes the newest to signing key.
+    ///
+    /// A key that stops being the signing key keeps verifying for `grace` seconds,
+    /// long enough for every token it signed to expire. Keys past their grace period are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keys returned by `load_keys`, sorted from oldest to newest.
+    /// * `grace` - The access token lifetime in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * The key id of the signing key after the reload.
+    pub fn reload(&mut self, mut keys: Vec<SigningKey>, grace: i64) -> String {
+        let now = Utc::now().timestamp();
+        let Some(newest) = keys.pop().map(Arc::new) else {
+            return self.current.kid.clone();
+        };
+
+        if newest.kid != self.current.kid {
+            info!("Rotating JWT signing key to {}", newest.kid);
+            let previous = std::mem::replace(&mut self.current, newest);
+            self.retirements.insert(previous.kid.clone(), now + grace);
+            self.retired.push(RetiredKey {
+                key: previous,
+                retire_at: now + grace,
+            });
+        }
+        self.retirements.remove(&self.current.kid);
+
+        // Older keys found on disk only verify, they never extend an existing grace period.
+        for key in keys {
+            if key.kid != self.current.kid && !self.retired.iter().any(|r| r.key.kid == key.kid) {
+                let retire_at = *self
+                    .retirements
+                    .entry(key.kid.clone())
+                    .or_insert(now + grace);
+                self.retired.push(RetiredKey {
+                    key: Arc::new(key),
+                    retire_at,
+                });
+            }
+        }
+
+        self.retired
+            .retain(|r| r.retire_at > now && r.key.kid != self.current.kid);
+        save_retirements(&self.retirements);
+
+        self.current.kid.clone()
+    }
+}
+
+/// Loads the access token key ring from environment variables.
+///
+/// * `JWT_ALGORITHM` - `HS256` (default), `RS256`, `ES256` or `EdDSA`.
+/// * `JWT_KEYS_DIR` - Directory of `{kid}.private.pem` / `{kid}.public.pem` pairs for key rotation.
+///   Key ids are sorted and the greatest one signs, so use sortable ids such as dates.
+/// * `JWT_KEY_ID` - The `kid` of the single key used when `JWT_KEYS_DIR` is not set, defaults to `default`.
+/// * `JWT_ACCESS_SECRET` - The shared secret, used by `HS256` only.
+/// * `JWT_PRIVATE_KEY_PATH` / `JWT_PUBLIC_KEY_PATH` - PEM files of the single key.
+///   EC and Ed25519 private keys must be in PKCS#8 format.
+///
+/// # Arguments
+///
+/// * `grace` - The access token lifetime in seconds, older keys verify for this long.
+pub fn load_key_ring(grace: i64) -> KeyRing {
+    let now = Utc::now().timestamp();
+    let mut keys = load_keys().unwrap_or_else(|e| panic!("{e}"));
+    let current = Arc::new(keys.pop().expect("No JWT signing key found"));
+
+    // Keys retired before a restart keep their original retirement time.
+    let mut retirements = load_retirements();
+    retirements.remove(&current.kid);
+    let retired = keys
+        .into_iter()
+        .map(|key| RetiredKey {
+            retire_at: *retirements.entry(key.kid.clone()).or_insert(now + grace),
+            key: Arc::new(key),
+        })
+        .filter(|r| r.retire_at > now)
+        .collect();
+    save_retirements(&retirements);
+
+    KeyRing {
+        current,
+        retired,
+        retirements,
+    }
+}
+
+/// Path of the file recording when each key of `JWT_KEYS_DIR` was retired.
+/// Single keys are never retired, so nothing is recorded without a keys directory.
+fn retirements_path() -> Option<std::path::PathBuf> {
+    env::var("JWT_KEYS_DIR")
+        .ok()
+        .map(|dir| Path::new(&dir).join("retired.json"))
+}
+
+/// Loads the recorded retirement times, keyed by key id.
+fn load_retirements() -> HashMap<String, i64> {
+    retirements_path()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Records the retirement times, logging instead of failing if the file cannot be written.
+fn save_retirements(retirements: &HashMap<String, i64>) {
+    let Some(path) = retirements_path() else {
+        return;
+    };
+
+    let written = serde_json::to_vec(retirements)
+        .map_err(|e| e.to_string())
+        .and_then(|data| fs::write(&path, data).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        error!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Loads every configured key, sorted from oldest to newest.
+///
+/// # Returns
+///
+/// * `Ok(Vec<SigningKey>)` - The keys, the last one being the newest.
+/// * `Err(String)` - A description of the configuration problem.
+pub fn load_keys() -> Result<Vec<SigningKey>, String> {
+    let algorithm = env::var("JWT_ALGORITHM").unwrap_or_else(|_| String::from("HS256"));
+    let algorithm: Algorithm = algorithm
+        .parse()
+        .map_err(|_| String::from("Unsupported JWT_ALGORITHM"))?;
+
+    if algorithm == Algorithm::HS256 {
+        let kid = env::var("JWT_KEY_ID").unwrap_or_else(|_| String::from("default"));
+        let secret = env::var("JWT_ACCESS_SECRET")
+            .map_err(|_| String::from("JWT_ACCESS_SECRET must be set"))?;
+        return Ok(vec![SigningKey {
+            kid,
+            algorithm,
+            encoding_key: EncodingKey::from_secret(secret.as_ref()),
+            decoding_key: DecodingKey::from_secret(secret.as_ref()),
+            jwk: None,
+        }]);
+    }
+
+    let keys: Vec<SigningKey> = match env::var("JWT_KEYS_DIR") {
+        Ok(dir) => {
+            let mut kids: Vec<String> = fs::read_dir(&dir)
+                .map_err(|e| format!("Failed to read {dir}: {e}"))?
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    name.strip_suffix(".private.pem").map(String::from)
+                })
+                .collect();
+            kids.sort();
+
+            kids.into_iter()
+                .map(|kid| {
+                    let dir = Path::new(&dir);
+                    let private_pem = read_pem(&dir.join(format!("{kid}.private.pem")))?;
+                    let public_pem = read_pem(&dir.join(format!("{kid}.public.pem")))?;
+                    pem_key(kid, algorithm, &private_pem, &public_pem)
+                })
+                .collect::<Result<_, _>>()?
+        }
+        Err(_) => {
+            let kid = env::var("JWT_KEY_ID").unwrap_or_else(|_| String::from("default"));
+            let private_pem = read_pem_var("JWT_PRIVATE_KEY_PATH")?;
+            let public_pem = read_pem_var("JWT_PUBLIC_KEY_PATH")?;
+            vec![pem_key(kid, algorithm, &private_pem, &public_pem)?]
+        }
+    };
+
+    if keys.is_empty() {
+        return Err(String::from("No JWT signing key found"));
+    }
+
+    Ok(keys)
+}
+
+/// Loads the key ID tokens are signed with when access tokens use HS256.
+///
+/// Relying parties cannot verify a token signed with the private HS256 secret,
+/// so ID tokens are then signed with an ES256 key published in the JWKS.
+///
+/// * `ID_TOKEN_PRIVATE_KEY_PATH` / `ID_TOKEN_PUBLIC_KEY_PATH` - PEM files of the ES256 key,
+///   the private key in PKCS#8 format.
+/// * `ID_TOKEN_KEY_ID` - The `kid` of the key, defaults to `id-token`.
+///
+/// Without a configured key a new one is generated at startup, ID tokens issued
+/// before a restart can then no longer be verified.
+pub fn load_id_token_key() -> Result<SigningKey, String> {
+    let kid = env::var("ID_TOKEN_KEY_ID").unwrap_or_else(|_| String::from("id-token"));
+    if env::var("ID_TOKEN_PRIVATE_KEY_PATH").is_ok() {
+        let private_pem = read_pem_var("ID_TOKEN_PRIVATE_KEY_PATH")?;
+        let public_pem = read_pem_var("ID_TOKEN_PUBLIC_KEY_PATH")?;
+        return pem_key(kid, Algorithm::ES256, &private_pem, &public_pem);
+    }
+
+    info!("No ID token key configured, generating one");
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        .map_err(|e| format!("Failed to generate the ID token key: {e}"))?;
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+        .map_err(|e| format!("Failed to generate the ID token key: {e}"))?;
+
+    // Uncompressed SEC1 point: 0x04 || x || y.
+    let point = &key_pair.public_key().as_ref()[1..];
+    let (x, y) = point.split_at(point.len() / 2);
+    let (x, y) = (URL_SAFE_NO_PAD.encode(x), URL_SAFE_NO_PAD.encode(y));
+    let decoding_key = DecodingKey::from_ec_components(&x, &y)
+        .map_err(|e| format!("Invalid ID token key: {e}"))?;
+    let jwk = signature_jwk(
+        &kid,
+        KeyAlgorithm::ES256,
+        AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+            key_type: EllipticCurveKeyType::EC,
+            curve: EllipticCurve::P256,
+            x,
+            y,
+        }),
+    );
+
+    Ok(SigningKey {
+        kid,
+        algorithm: Algorithm::ES256,
+        encoding_key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+        decoding_key,
+        jwk: Some(jwk),
+    })
+}
+
+/// Reads the PEM file whose path is stored in the given environment variable.
+fn read_pem_var(var: &str) -> Result<Vec<u8>, String> {
+    let path = env::var(var).map_err(|_| format!("{var} must be set"))?;
+    read_pem(Path::new(&path))
+}
+
+/// Reads a PEM file from disk.
+fn read_pem(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))
+}
+
+/// Builds an asymmetric signing key from PEM data.
+fn pem_key(
+    kid: String,
+    algorithm: Algorithm,
+    private_pem: &[u8],
+    public_pem: &[u8],
+) -> Result<SigningKey, String> {
+    let keys = match algorithm {
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(private_pem)
+            .and_then(|e| Ok((e, DecodingKey::from_rsa_pem(public_pem)?))),
+        Algorithm::ES256 => EncodingKey::from_ec_pem(private_pem)
+            .and_then(|e| Ok((e, DecodingKey::from_ec_pem(public_pem)?))),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(private_pem)
+            .and_then(|e| Ok((e, DecodingKey::from_ed_pem(public_pem)?))),
+        _ => return Err(String::from("Unsupported JWT_ALGORITHM")),
+    };
+    let (encoding_key, decoding_key) = keys.map_err(|e| format!("Invalid PEM key {kid}: {e}"))?;
+
+    let jwk = public_jwk(&kid, algorithm, public_pem)?;
+
+    Ok(SigningKey {
+        kid,
+        algorithm,
+        encoding_key,
+        decoding_key,
+        jwk: Some(jwk),
+    })
+}
+
+/// Converts a PEM encoded public key into its JWK representation.
+fn public_jwk(kid: &str, algorithm: Algorithm, public_pem: &[u8]) -> Result<Jwk, String> {
+    let invalid = |e: &dyn std::fmt::Display| format!("Invalid public key {kid}: {e}");
+
+    let pem = std::str::from_utf8(public_pem).map_err(|e| invalid(&e))?;
+    let spki = SubjectPublicKeyInfoOwned::from_pem(pem).map_err(|e| invalid(&e))?;
+    let key_bytes = spki.subject_public_key.raw_bytes();
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 => {
+            let rsa = RsaPublicKey::from_der(key_bytes).map_err(|e| invalid(&e))?;
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(rsa.modulus.as_bytes()),
+                    e: URL_SAFE_NO_PAD.encode(rsa.public_exponent.as_bytes()),
+                }),
+            )
+        }
+        Algorithm::ES256 => {
+            // Uncompressed SEC1 point: 0x04 || x || y.
+            let point = key_bytes
+                .strip_prefix(&[0x04])
+                .ok_or_else(|| invalid(&"compressed EC point"))?;
+            let (x, y) = point.split_at(point.len() / 2);
+            (
+                KeyAlgorithm::ES256,
+                AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                }),
+            )
+        }
+        Algorithm::EdDSA => (
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key_bytes),
+            }),
+        ),
+        _ => return Err(String::from("Unsupported JWT_ALGORITHM")),
+    };
+
+    Ok(signature_jwk(kid, key_algorithm, parameters))
+}
+
+/// Builds the JWK of a public signature key.
+fn signature_jwk(kid: &str, key_algorithm: KeyAlgorithm, parameters: AlgorithmParameters) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    }
+}
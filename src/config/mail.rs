@@ -0,0 +1,55 @@
+use std::env;
+
+use lettre::message::Mailbox;
+
+use crate::{
+    config::oidc,
+    mailer::{file_mailer::FileMailer, log_mailer::LogMailer, smtp_mailer::SmtpMailer, AppMailer},
+};
+
+/// Creates the mailer selected by `MAIL_TRANSPORT`:
+/// - `smtp` delivers through the relay at `SMTP_URL`.
+/// - `file` writes `.eml` files into `MAIL_DIR`, `mail` by default.
+/// - `log` logs every mail, the default.
+///
+/// Messages are sent from `MAIL_FROM`.
+///
+/// # Panics
+///
+/// * If the transport is unknown, or its settings are missing or malformed.
+pub fn create_mailer() -> AppMailer {
+    let from: Mailbox = env::var("MAIL_FROM")
+        .unwrap_or_else(|_| String::from("Auth-Service <no-reply@localhost>"))
+        .parse()
+        .expect("Invalid MAIL_FROM");
+
+    match env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("smtp") => {
+            let url = env::var("SMTP_URL").expect("SMTP_URL must be set");
+            AppMailer::Smtp(SmtpMailer::new(from, &url).expect("Invalid SMTP_URL"))
+        }
+        Ok("file") => {
+            let dir = env::var("MAIL_DIR").unwrap_or_else(|_| String::from("mail"));
+            std::fs::create_dir_all(&dir).expect("Cannot create MAIL_DIR");
+            AppMailer::File(FileMailer::new(from, dir))
+        }
+        Ok("log") | Err(_) => AppMailer::Log(LogMailer),
+        Ok(transport) => panic!("Unknown MAIL_TRANSPORT {}", transport),
+    }
+}
+
+/// Loads the link new accounts follow to verify their address from `EMAIL_VERIFICATION_URL`,
+/// the verification token is appended to it.
+/// Defaults to the verification endpoint of the issuer.
+pub fn email_verification_url() -> String {
+    env::var("EMAIL_VERIFICATION_URL")
+        .unwrap_or_else(|_| format!("{}/api/auth/verify-email?token=", oidc::issuer()))
+}
+
+/// Loads the link users follow to reset their password from `PASSWORD_RESET_URL`,
+/// the reset token is appended to it. The page it opens posts the token and the new password
+/// to `/api/auth/password/reset`.
+pub fn password_reset_url() -> String {
+    env::var("PASSWORD_RESET_URL")
+        .unwrap_or_else(|_| format!("{}/reset-password?token=", oidc::issuer()))
+}
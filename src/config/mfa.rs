@@ -0,0 +1,6 @@
+use std::env;
+
+/// Loads the issuer name authenticator apps show next to TOTP codes from `TOTP_ISSUER`.
+pub fn totp_issuer() -> String {
+    env::var("TOTP_ISSUER").unwrap_or_else(|_| String::from("Auth-Service"))
+}
@@ -1,2 +1,10 @@
 pub mod db;
+pub mod jwt;
+pub mod mail;
+pub mod mfa;
+pub mod oauth;
+pub mod oidc;
+pub mod policy;
 pub mod redis;
+pub mod scope;
+pub mod webauthn;
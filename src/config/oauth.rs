@@ -0,0 +1,21 @@
+use std::env;
+
+/// Loads the initial access token required by the dynamic client registration endpoint
+/// from `REGISTRATION_ACCESS_TOKEN` (RFC 7591 section 3).
+/// Dynamic registration is disabled when it is not set.
+pub fn initial_access_token() -> Option<String> {
+    env::var("REGISTRATION_ACCESS_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+/// Loads the roles token exchange never delegates from `EXCHANGE_PRIVILEGED_ROLES`,
+/// a comma separated list defaulting to `admin`. Roles inheriting one of them are dropped too.
+pub fn exchange_privileged_roles() -> Vec<String> {
+    env::var("EXCHANGE_PRIVILEGED_ROLES")
+        .unwrap_or_else(|_| String::from("admin"))
+        .split(',')
+        .map(|role| role.trim().to_string())
+        .filter(|role| !role.is_empty())
+        .collect()
+}
@@ -0,0 +1,8 @@
+use std::env;
+
+/// Loads the issuer identifier of the OpenID provider from `OIDC_ISSUER`.
+/// It is written to the `iss` claim of ID tokens and every endpoint
+/// advertised by the discovery document is built from it.
+pub fn issuer() -> String {
+    env::var("OIDC_ISSUER").unwrap_or_else(|_| String::from("http://localhost:8080"))
+}
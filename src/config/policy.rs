@@ -0,0 +1,62 @@
+use std::env;
+
+use chrono::NaiveTime;
+
+use crate::utils::policy::{Condition, IpNet};
+
+/// Builds the condition requests to the administration API must satisfy, from
+/// `ADMIN_ALLOWED_NETWORKS`, a comma separated list of networks in CIDR notation,
+/// and `ADMIN_ALLOWED_HOURS`, a UTC window such as `08:00-18:00`.
+/// Unset variables place no restriction.
+///
+/// # Panics
+///
+/// * If a network or the hours window is malformed.
+pub fn admin_condition() -> Condition {
+    let mut condition = Condition::All(Vec::new());
+
+    if let Some(networks) = env::var("ADMIN_ALLOWED_NETWORKS")
+        .ok()
+        .filter(|n| !n.trim().is_empty())
+    {
+        let networks = networks
+            .split(',')
+            .map(|network| {
+                IpNet::parse(network.trim())
+                    .map(Condition::IpIn)
+                    .expect("Invalid ADMIN_ALLOWED_NETWORKS")
+            })
+            .collect();
+        condition = condition.and(Condition::Any(networks));
+    }
+
+    if let Some(hours) = env::var("ADMIN_ALLOWED_HOURS")
+        .ok()
+        .filter(|h| !h.trim().is_empty())
+    {
+        let (from, to) = hours
+            .split_once('-')
+            .expect("ADMIN_ALLOWED_HOURS must look like 08:00-18:00");
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M").expect("Invalid ADMIN_ALLOWED_HOURS")
+        };
+        condition = condition.and(Condition::TimeBetween(parse(from), parse(to)));
+    }
+
+    condition
+}
+
+/// Maximum age in seconds of the authentication sensitive routes accept,
+/// from `REAUTH_MAX_AGE`. Defaults to 5 minutes.
+pub fn reauth_max_age() -> i64 {
+    env::var("REAUTH_MAX_AGE")
+        .ok()
+        .and_then(|age| age.parse().ok())
+        .unwrap_or(300)
+}
+
+/// Whether logins of accounts with an unverified email address are rejected,
+/// from `REQUIRE_VERIFIED_EMAIL`. Defaults to false.
+pub fn require_verified_email() -> bool {
+    env::var("REQUIRE_VERIFIED_EMAIL").is_ok_and(|v| v == "true" || v == "1")
+}
@@ -0,0 +1,21 @@
+/// Scopes defined by OpenID Connect, requestable by any user on top of their roles' scopes.
+/// API scopes are stored with each role and inherited along with its permissions.
+pub const OIDC_SCOPES: &[&str] = &["openid", "profile"];
+
+/// Returns the space separated scopes of a direct login, which holds every scope of its roles,
+/// `None` if the roles have none.
+pub fn default_scope(role_scopes: &[String]) -> Option<String> {
+    (!role_scopes.is_empty()).then(|| role_scopes.join(" "))
+}
+
+/// Keeps the requested scopes a user holding `role_scopes` may carry,
+/// `None` if none of them is allowed.
+pub fn allowed_scope(requested: Option<&str>, role_scopes: &[String]) -> Option<String> {
+    let allowed: Vec<&str> = requested
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter(|s| OIDC_SCOPES.contains(s) || role_scopes.iter().any(|r| r == s))
+        .collect();
+
+    (!allowed.is_empty()).then(|| allowed.join(" "))
+}
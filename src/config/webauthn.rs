@@ -0,0 +1,35 @@
+use std::env;
+
+use url::Url;
+
+use super::oidc;
+
+/// Loads the WebAuthn relying party id from `WEBAUTHN_RP_ID`, the domain credentials are scoped to.
+/// Defaults to the host of the issuer.
+pub fn rp_id() -> String {
+    env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| {
+        Url::parse(&oidc::issuer())
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_else(|| String::from("localhost"))
+    })
+}
+
+/// Loads the relying party name authenticators show during registration from `WEBAUTHN_RP_NAME`.
+pub fn rp_name() -> String {
+    env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| String::from("Auth-Service"))
+}
+
+/// Loads the origins ceremonies may come from, a comma separated list in `WEBAUTHN_ORIGINS`.
+/// Defaults to the issuer.
+pub fn origins() -> Vec<String> {
+    env::var("WEBAUTHN_ORIGINS")
+        .map(|origins| {
+            origins
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect()
+        })
+        .unwrap_or_else(|_| vec![oidc::issuer().trim_end_matches('/').to_string()])
+}
@@ -0,0 +1,13 @@
+use derive_more::{Display, Error};
+
+#[derive(Debug, Error, Display)]
+pub enum MailError {
+    #[display("Invalid address: {_0}")]
+    InvalidAddress(lettre::address::AddressError),
+
+    #[display("Cannot build message: {_0}")]
+    MessageError(lettre::error::Error),
+
+    #[display("Transport error: {_0}")]
+    TransportError(#[error(not(source))] String),
+}
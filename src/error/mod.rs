@@ -1,2 +1,4 @@
+pub mod mail_error;
+pub mod oauth_error;
 pub mod redis_error;
 pub mod service_error;
@@ -0,0 +1,114 @@
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+use derive_more::{Display, Error};
+use log::error;
+use serde_json::json;
+
+use super::service_error::ServiceError;
+
+/// Errors returned by the OAuth 2.0 endpoints, serialized as
+/// `{"error": "...", "error_description": "..."}` as defined by RFC 6749 section 5.2.
+#[derive(Debug, Display, Error)]
+pub enum OAuthError {
+    #[display("invalid_request")]
+    InvalidRequest(#[error(not(source))] String),
+
+    #[display("invalid_client")]
+    InvalidClient,
+
+    #[display("invalid_grant")]
+    InvalidGrant(#[error(not(source))] String),
+
+    #[display("unsupported_grant_type")]
+    UnsupportedGrantType,
+
+    /// The user has not yet approved the device authorization (RFC 8628 section 3.5).
+    #[display("authorization_pending")]
+    AuthorizationPending,
+
+    /// The device polls faster than the advertised interval.
+    #[display("slow_down")]
+    SlowDown,
+
+    #[display("access_denied")]
+    AccessDenied,
+
+    #[display("expired_token")]
+    ExpiredToken,
+
+    /// The requested scope exceeds the scope of the subject token.
+    #[display("invalid_scope")]
+    InvalidScope,
+
+    /// The requested audience cannot be granted (RFC 8693 section 2.2.2).
+    #[display("invalid_target")]
+    InvalidTarget,
+
+    /// Missing or invalid bearer token on a protected OAuth endpoint (RFC 6750 section 3.1).
+    #[display("invalid_token")]
+    InvalidToken,
+
+    #[display("invalid_redirect_uri")]
+    InvalidRedirectUri(#[error(not(source))] String),
+
+    #[display("invalid_client_metadata")]
+    InvalidClientMetadata(#[error(not(source))] String),
+
+    #[display("server_error")]
+    ServerError,
+}
+
+impl OAuthError {
+    /// Short description sent alongside the error code.
+    fn description(&self) -> Option<&str> {
+        match self {
+            OAuthError::InvalidRequest(description)
+            | OAuthError::InvalidGrant(description)
+            | OAuthError::InvalidRedirectUri(description)
+            | OAuthError::InvalidClientMetadata(description) => Some(description),
+            _ => None,
+        }
+    }
+}
+
+impl ResponseError for OAuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            OAuthError::InvalidClient | OAuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            OAuthError::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let body = match self.description() {
+            Some(description) => {
+                json!({"error": self.to_string(), "error_description": description})
+            }
+            None => json!({"error": self.to_string()}),
+        };
+
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            OAuthError::InvalidClient => {
+                response.insert_header((header::WWW_AUTHENTICATE, "Basic"));
+            }
+            OAuthError::InvalidToken => {
+                response
+                    .insert_header((header::WWW_AUTHENTICATE, "Bearer error=\"invalid_token\""));
+            }
+            _ => {}
+        }
+
+        response.json(body)
+    }
+}
+
+impl From<ServiceError> for OAuthError {
+    fn from(e: ServiceError) -> Self {
+        error!("{}", e);
+        OAuthError::ServerError
+    }
+}
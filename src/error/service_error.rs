@@ -1,6 +1,8 @@
 use actix_web::{HttpResponse, ResponseError};
 use derive_more::{Display, Error};
 
+use crate::error::mail_error::MailError;
+
 #[derive(Debug, Display, Error)]
 pub enum ServiceError {
     #[display("Invalid account id format")]
@@ -20,6 +22,21 @@ pub enum ServiceError {
 
     #[display("Unauthorized")]
     UnAuthorizedError,
+
+    #[display("Hashing error")]
+    HashError,
+
+    #[display("Bad request: {_0}")]
+    BadRequest(#[error(not(source))] String),
+
+    #[display("Conflict: {_0}")]
+    Conflict(#[error(not(source))] String),
+
+    #[display("Forbidden: {_0}")]
+    Forbidden(#[error(not(source))] String),
+
+    #[display("Mail error: {_0}")]
+    MailError(MailError),
 }
 
 impl ResponseError for ServiceError {
@@ -28,6 +45,9 @@ impl ResponseError for ServiceError {
             ServiceError::NotFound => HttpResponse::NotFound().finish(),
             ServiceError::RedisError => HttpResponse::InternalServerError().finish(),
             ServiceError::UnAuthorizedError => HttpResponse::Unauthorized().finish(),
+            ServiceError::BadRequest(message) => HttpResponse::BadRequest().body(message.clone()),
+            ServiceError::Conflict(message) => HttpResponse::Conflict().body(message.clone()),
+            ServiceError::Forbidden(message) => HttpResponse::Forbidden().body(message.clone()),
             _ => HttpResponse::InternalServerError().finish(),
         }
     }
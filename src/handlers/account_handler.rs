@@ -1,18 +1,39 @@
-use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
-
-use crate::{utils::jwt::Claims, AppAccountService};
+use crate::{
+    handlers::auth_handler::spawn_verification_mail,
+    model::account::AccountUpdate,
+    utils::{
+        jwt::Claims,
+        policy::{Condition, PolicyContext},
+    },
+    AppAccountService, AppRbacService, AppVerificationService,
+};
+use actix_web::{
+    web::{self, Json},
+    HttpMessage, HttpRequest, HttpResponse, Responder,
+};
 
 pub async fn me(account_service: web::Data<AppAccountService>, req: HttpRequest) -> impl Responder {
-    let extensions = req.extensions();
-    let access_token_data: &Claims = match extensions.get::<Claims>() {
-        Some(token_data) => token_data,
+    let account_id = match req.extensions().get::<Claims>() {
+        Some(token_data) => token_data.id.clone(),
         None => return HttpResponse::Unauthorized().body("Some thing wrong"),
     };
 
-    let account = match account_service
-        .get_account_info(&access_token_data.id)
-        .await
-    {
+    let account = match account_service.get_account_info(&account_id).await {
+        Ok(account) => account,
+        Err(e) => {
+            return HttpResponse::from_error(e);
+        }
+    };
+
+    HttpResponse::Ok().json(account)
+}
+
+pub async fn account(
+    account_service: web::Data<AppAccountService>,
+    path: web::Path<String>,
+) -> impl Responder {
+    // Ownership is enforced by the `PolicyMiddleware` wrapping this resource.
+    let account = match account_service.get_account_info(&path.into_inner()).await {
         Ok(account) => account,
         Err(e) => {
             return HttpResponse::from_error(e);
@@ -21,3 +42,41 @@ pub async fn me(account_service: web::Data<AppAccountService>, req: HttpRequest)
 
     HttpResponse::Ok().json(account)
 }
+
+pub async fn update_account(
+    account_service: web::Data<AppAccountService>,
+    verification_service: web::Data<AppVerificationService>,
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<String>,
+    update: Json<AccountUpdate>,
+    req: HttpRequest,
+) -> impl Responder {
+    // Owners and administrators are let through by the `PolicyMiddleware` wrapping this resource.
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+    let roles = match rbac_service.resolve_roles(&claims.roles).await {
+        Ok(roles) => roles,
+        Err(e) => return HttpResponse::from_error(e),
+    };
+
+    // Administrators may rename an account, but only its owner may change
+    // the address password resets are sent to.
+    let ctx = PolicyContext::from_request(&req, claims, roles);
+    if update.email.is_some() && !Condition::Owner("id").evaluate(&ctx) {
+        return HttpResponse::Forbidden().body("Only the owner may change the email address");
+    }
+
+    match account_service
+        .update_account(&path.into_inner(), update.0)
+        .await
+    {
+        Ok(Some(email)) => {
+            spawn_verification_mail(&verification_service, email);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
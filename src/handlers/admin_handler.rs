@@ -0,0 +1,209 @@
+use actix_web::{web, HttpResponse, Responder};
+use log::info;
+
+use crate::{
+    model::{
+        client::RedirectUris,
+        rbac::{NewRole, Permission},
+    },
+    AppAuthService, AppClientService, AppRbacService,
+};
+
+pub async fn user_sessions(
+    auth_service: web::Data<AppAuthService>,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    match auth_service.list_sessions(&user_id, None).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn revoke_user_sessions(
+    auth_service: web::Data<AppAuthService>,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    match auth_service.logout_all(&user_id).await {
+        Ok(()) => {
+            info!("Sessions of user {} revoked by admin", user_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn assign_user_role(
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, role) = path.into_inner();
+
+    match rbac_service.assign_role(&user_id, &role).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn unassign_user_role(
+    rbac_service: web::Data<AppRbacService>,
+    auth_service: web::Data<AppAuthService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, role) = path.into_inner();
+
+    if let Err(e) = rbac_service.unassign_role(&user_id, &role).await {
+        return HttpResponse::from_error(e);
+    }
+
+    // Tokens carry the roles of the account, log the user out so the role stops working now.
+    match auth_service.logout_all(&user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn clients(client_service: web::Data<AppClientService>) -> impl Responder {
+    match client_service.list_clients().await {
+        Ok(clients) => HttpResponse::Ok().json(clients),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn rotate_client_secret(
+    client_service: web::Data<AppClientService>,
+    client_id: web::Path<String>,
+) -> impl Responder {
+    match client_service.rotate_secret(&client_id).await {
+        Ok(secret) => HttpResponse::Ok().json(secret),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn update_client_redirect_uris(
+    client_service: web::Data<AppClientService>,
+    client_id: web::Path<String>,
+    body: web::Json<RedirectUris>,
+) -> impl Responder {
+    match client_service
+        .update_redirect_uris(&client_id, &body.redirect_uris)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn disable_client(
+    client_service: web::Data<AppClientService>,
+    client_id: web::Path<String>,
+) -> impl Responder {
+    match client_service.disable_client(&client_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn roles(rbac_service: web::Data<AppRbacService>) -> impl Responder {
+    match rbac_service.list_roles().await {
+        Ok(roles) => HttpResponse::Ok().json(roles),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn create_role(
+    rbac_service: web::Data<AppRbacService>,
+    role: web::Json<NewRole>,
+) -> impl Responder {
+    match rbac_service.create_role(role.into_inner()).await {
+        Ok(()) => HttpResponse::Created().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn delete_role(
+    rbac_service: web::Data<AppRbacService>,
+    name: web::Path<String>,
+) -> impl Responder {
+    match rbac_service.delete_role(&name).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn grant_role_permission(
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (role, permission) = path.into_inner();
+
+    match rbac_service.grant_permission(&role, &permission).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn revoke_role_permission(
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (role, permission) = path.into_inner();
+
+    match rbac_service.revoke_permission(&role, &permission).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn add_role_inheritance(
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (role, inherits) = path.into_inner();
+
+    match rbac_service.add_inheritance(&role, &inherits).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn remove_role_inheritance(
+    rbac_service: web::Data<AppRbacService>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (role, inherits) = path.into_inner();
+
+    match rbac_service.remove_inheritance(&role, &inherits).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn permissions(rbac_service: web::Data<AppRbacService>) -> impl Responder {
+    match rbac_service.list_permissions().await {
+        Ok(permissions) => HttpResponse::Ok().json(permissions),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn create_permission(
+    rbac_service: web::Data<AppRbacService>,
+    permission: web::Json<Permission>,
+) -> impl Responder {
+    match rbac_service
+        .create_permission(permission.into_inner())
+        .await
+    {
+        Ok(()) => HttpResponse::Created().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn delete_permission(
+    rbac_service: web::Data<AppRbacService>,
+    name: web::Path<String>,
+) -> impl Responder {
+    match rbac_service.delete_permission(&name).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
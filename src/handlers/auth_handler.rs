@@ -3,63 +3,354 @@ use actix_web::{
     web::{self, Json},
     HttpMessage, HttpRequest, HttpResponse, Responder,
 };
-use log::info;
-use serde_json::json;
+use log::{error, info};
 
 use crate::{
-    model::{account::LoginInfo, token::RefreshToken},
-    AppAuthService,
+    model::{
+        account::{
+            EmailAddress, LoginInfo, PasswordReset, Reauthenticate, Registration, VerifyEmail,
+        },
+        mfa::{LoginOutcome, MfaCode, MfaLogin},
+        session::DeviceInfo,
+        token::{RefreshToken, Token},
+    },
+    utils::jwt::Claims,
+    AppAuthService, AppOAuthService, AppPasswordService, AppVerificationService,
 };
 
+/// Sends a verification link in the background. The response does not wait for the mail,
+/// so its timing does not tell whether the address belongs to an account.
+pub fn spawn_verification_mail(
+    verification_service: &web::Data<AppVerificationService>,
+    email: String,
+) {
+    let verification_service = verification_service.clone();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = verification_service.send_verification(&email).await {
+            error!("Verification mail not sent: {}", e);
+        }
+    });
+}
+
 pub async fn register(
     auth_service: web::Data<AppAuthService>,
-    login_info: Json<LoginInfo>,
+    verification_service: web::Data<AppVerificationService>,
+    registration: Json<Registration>,
 ) -> impl Responder {
-    match auth_service.add_account(login_info.0).await {
+    let email = registration.email.clone();
+    match auth_service.add_account(registration.0).await {
         Ok(result) => {
             info!("{} rows inserted", result);
+            // The account exists even if the mail fails, the link can be resent.
+            spawn_verification_mail(&verification_service, email);
             HttpResponse::Ok().body("Success".to_string())
         }
         Err(e) => HttpResponse::from_error(e),
     }
 }
 
+pub async fn verify_email(
+    verification_service: web::Data<AppVerificationService>,
+    query: web::Query<VerifyEmail>,
+) -> impl Responder {
+    match verification_service.verify_email(&query.token).await {
+        Ok(()) => HttpResponse::Ok().body("Email verified"),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn resend_verification(
+    verification_service: web::Data<AppVerificationService>,
+    address: Json<EmailAddress>,
+) -> impl Responder {
+    // Always accepted, so the response does not tell whether the address belongs to an account.
+    spawn_verification_mail(&verification_service, address.0.email);
+    HttpResponse::Accepted().finish()
+}
+
+pub async fn forgot_password(
+    password_service: web::Data<AppPasswordService>,
+    address: Json<EmailAddress>,
+) -> impl Responder {
+    // Always accepted without waiting for the mail, so the response does not tell
+    // whether the address belongs to an account.
+    let password_service = password_service.clone();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = password_service.forgot_password(&address.email).await {
+            error!("Password reset mail not sent: {}", e);
+        }
+    });
+    HttpResponse::Accepted().finish()
+}
+
+pub async fn reset_password(
+    password_service: web::Data<AppPasswordService>,
+    reset: Json<PasswordReset>,
+) -> impl Responder {
+    match password_service.reset_password(reset.0).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
 pub async fn login(
     auth_service: web::Data<AppAuthService>,
     login_info: Json<LoginInfo>,
+    req: HttpRequest,
 ) -> impl Responder {
     let u = login_info.username.clone();
-    match auth_service.verify_account(login_info.0).await {
-        Ok(result) => {
+    match auth_service
+        .verify_account(login_info.0, device_info(&req))
+        .await
+    {
+        Ok(LoginOutcome::Token(result)) => {
             info!("User {u} logged in");
-            HttpResponse::Ok()
-                .insert_header((
-                    header::SET_COOKIE,
-                    format!(
-                        "refresh_token={};Path=/; HttpOnly; Secure; SameSite=Strict",
-                        result.refresh_token
-                    ),
-                ))
-                .json(result)
+            login_response(result)
         }
+        Ok(LoginOutcome::MfaRequired(challenge)) => {
+            info!("User {u} must answer an MFA challenge");
+            HttpResponse::Ok().json(challenge)
+        }
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn login_mfa(
+    auth_service: web::Data<AppAuthService>,
+    mfa_login: Json<MfaLogin>,
+    req: HttpRequest,
+) -> impl Responder {
+    match auth_service
+        .verify_mfa(mfa_login.0, device_info(&req))
+        .await
+    {
+        Ok(result) => login_response(result),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn enroll_totp(
+    auth_service: web::Data<AppAuthService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.enroll_totp(&user_id).await {
+        Ok(enrollment) => HttpResponse::Ok().json(enrollment),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn confirm_totp(
+    auth_service: web::Data<AppAuthService>,
+    code: Json<MfaCode>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.confirm_totp(&user_id, &code.code).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(recovery_codes),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn regenerate_recovery_codes(
+    auth_service: web::Data<AppAuthService>,
+    code: Json<MfaCode>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service
+        .regenerate_recovery_codes(&user_id, &code.code)
+        .await
+    {
+        Ok(recovery_codes) => HttpResponse::Ok().json(recovery_codes),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn disable_totp(
+    auth_service: web::Data<AppAuthService>,
+    code: Json<MfaCode>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.disable_totp(&user_id, &code.code).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn reauthenticate(
+    auth_service: web::Data<AppAuthService>,
+    reauth: Json<Reauthenticate>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.reauthenticate(&claims, reauth.0).await {
+        Ok(result) => login_response(result),
         Err(e) => HttpResponse::from_error(e),
     }
 }
 
 pub async fn refresh(req: HttpRequest) -> impl Responder {
-    let access_token = match req.extensions().get::<String>() {
-        Some(token) => token.clone(),
+    let token = match req.extensions_mut().remove::<Token>() {
+        Some(token) => token,
         None => return HttpResponse::Unauthorized().body("Some thing wrong"),
     };
-    HttpResponse::Ok().json(json!({"access_token": access_token}))
+    HttpResponse::Ok()
+        .insert_header((
+            header::SET_COOKIE,
+            format!(
+                "refresh_token={};Path=/; HttpOnly; Secure; SameSite=Strict",
+                token.refresh_token
+            ),
+        ))
+        .json(token)
 }
 
 pub async fn logout(
     auth_service: web::Data<AppAuthService>,
     refresh_token: Json<RefreshToken>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service
+        .logout(&refresh_token.refresh_token, &claims)
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().body("Logout success"),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn sessions(auth_service: web::Data<AppAuthService>, req: HttpRequest) -> impl Responder {
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service
+        .list_sessions(&claims.id, claims.sid.as_deref())
+        .await
+    {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn revoke_session(
+    auth_service: web::Data<AppAuthService>,
+    session_id: web::Path<String>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.revoke_session(&user_id, &session_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn logout_all(
+    auth_service: web::Data<AppAuthService>,
+    req: HttpRequest,
 ) -> impl Responder {
-    match auth_service.logout(&refresh_token.refresh_token).await {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match auth_service.logout_all(&user_id).await {
         Ok(()) => HttpResponse::Ok().body("Logout success"),
         Err(e) => HttpResponse::from_error(e),
     }
 }
+
+pub async fn grants(oauth_service: web::Data<AppOAuthService>, req: HttpRequest) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match oauth_service.list_grants(&user_id).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn revoke_grant(
+    oauth_service: web::Data<AppOAuthService>,
+    client_id: web::Path<String>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match oauth_service.revoke_grant(&user_id, &client_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+/// Extracts the user agent and client IP address of a request.
+pub fn device_info(req: &HttpRequest) -> DeviceInfo {
+    DeviceInfo {
+        user_agent: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|hv| hv.to_str().ok())
+            .map(String::from),
+        ip: req.connection_info().realip_remote_addr().map(String::from),
+    }
+}
+
+/// Responds to a successful login with its tokens, setting the refresh token cookie and,
+/// for direct logins, the login session cookie the OAuth authorization endpoints rely on.
+pub fn login_response(token: Token) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response.append_header((
+        header::SET_COOKIE,
+        format!(
+            "refresh_token={};Path=/; HttpOnly; Secure; SameSite=Strict",
+            token.refresh_token
+        ),
+    ));
+    if let Some(login_session) = &token.login_session {
+        response.append_header((
+            header::SET_COOKIE,
+            format!(
+                "login_session={};Path=/oauth; HttpOnly; Secure; SameSite=Lax",
+                login_session
+            ),
+        ));
+    }
+    response.json(token)
+}
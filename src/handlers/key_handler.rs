@@ -0,0 +1,22 @@
+use actix_web::{HttpResponse, Responder};
+use log::{error, info};
+use serde_json::json;
+
+use crate::utils::jwt::JwtUtils;
+
+pub async fn jwks() -> impl Responder {
+    HttpResponse::Ok().json(JwtUtils::jwks())
+}
+
+pub async fn rotate() -> impl Responder {
+    match JwtUtils::rotate_signing_key() {
+        Ok(kid) => {
+            info!("Signing key is now {kid}");
+            HttpResponse::Ok().json(json!({"kid": kid}))
+        }
+        Err(e) => {
+            error!("Key rotation failed: {e}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
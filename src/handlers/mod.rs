@@ -1,2 +1,7 @@
 pub mod account_handler;
+pub mod admin_handler;
 pub mod auth_handler;
+pub mod key_handler;
+pub mod oauth_handler;
+pub mod oidc_handler;
+pub mod webauthn_handler;
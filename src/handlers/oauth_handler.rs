@@ -0,0 +1,237 @@
+use actix_web::{
+    error::{JsonPayloadError, UrlencodedError},
+    http::header,
+    web, HttpRequest, HttpResponse, Responder,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    error::oauth_error::OAuthError,
+    handlers::auth_handler::device_info,
+    model::client::ClientRegistrationRequest,
+    model::oauth::{
+        AuthorizeRequest, ClientCredentials, ConsentDecision, ConsentQuery,
+        DeviceAuthorizationRequest, DeviceDecision, IntrospectionRequest, RevocationRequest,
+        TokenRequest, UserCodeQuery,
+    },
+    utils::jwt::Claims,
+    AppClientService, AppOAuthService,
+};
+
+pub async fn authorize(
+    oauth_service: web::Data<AppOAuthService>,
+    request: web::Query<AuthorizeRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    let owner = resource_owner(&oauth_service, &req).await;
+
+    match oauth_service.authorize(request.into_inner(), owner).await {
+        Ok(location) => HttpResponse::Found()
+            .insert_header((header::LOCATION, location))
+            .finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn consent(
+    oauth_service: web::Data<AppOAuthService>,
+    query: web::Query<ConsentQuery>,
+    req: HttpRequest,
+) -> impl Responder {
+    let owner = match resource_owner(&oauth_service, &req).await {
+        Some(owner) => owner,
+        None => return HttpResponse::Unauthorized().finish(),
+    };
+
+    match oauth_service
+        .consent_prompt(&query.consent_id, &owner)
+        .await
+    {
+        Ok(prompt) => HttpResponse::Ok().json(prompt),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn decide_consent(
+    oauth_service: web::Data<AppOAuthService>,
+    decision: web::Form<ConsentDecision>,
+    req: HttpRequest,
+) -> impl Responder {
+    let owner = match resource_owner(&oauth_service, &req).await {
+        Some(owner) => owner,
+        None => return HttpResponse::Unauthorized().finish(),
+    };
+
+    match oauth_service
+        .decide_consent(&decision.consent_id, &owner, decision.approve)
+        .await
+    {
+        Ok(location) => HttpResponse::Found()
+            .insert_header((header::LOCATION, location))
+            .finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn token(
+    oauth_service: web::Data<AppOAuthService>,
+    request: web::Form<TokenRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    match oauth_service
+        .token(
+            request.into_inner(),
+            basic_credentials(&req),
+            device_info(&req),
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .insert_header((header::PRAGMA, "no-cache"))
+            .json(response),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn revoke(
+    oauth_service: web::Data<AppOAuthService>,
+    request: web::Form<RevocationRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    match oauth_service
+        .revoke(request.into_inner(), basic_credentials(&req))
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn introspect(
+    oauth_service: web::Data<AppOAuthService>,
+    request: web::Form<IntrospectionRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    match oauth_service
+        .introspect(request.into_inner(), basic_credentials(&req))
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn device_authorization(
+    oauth_service: web::Data<AppOAuthService>,
+    request: web::Form<DeviceAuthorizationRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    match oauth_service
+        .device_authorization(request.into_inner(), basic_credentials(&req))
+        .await
+    {
+        Ok(response) => HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(response),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn device(
+    oauth_service: web::Data<AppOAuthService>,
+    query: web::Query<UserCodeQuery>,
+    req: HttpRequest,
+) -> impl Responder {
+    if resource_owner(&oauth_service, &req).await.is_none() {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    match oauth_service
+        .pending_device_authorization(&query.user_code)
+        .await
+    {
+        Ok(verification) => HttpResponse::Ok().json(verification),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn decide_device(
+    oauth_service: web::Data<AppOAuthService>,
+    decision: web::Form<DeviceDecision>,
+    req: HttpRequest,
+) -> impl Responder {
+    let owner = match resource_owner(&oauth_service, &req).await {
+        Some(owner) => owner,
+        None => return HttpResponse::Unauthorized().finish(),
+    };
+
+    match oauth_service
+        .decide_device_authorization(&decision.user_code, &owner, decision.approve)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn register(
+    client_service: web::Data<AppClientService>,
+    request: web::Json<ClientRegistrationRequest>,
+    req: HttpRequest,
+) -> impl Responder {
+    match client_service
+        .register(request.into_inner(), bearer_token(&req))
+        .await
+    {
+        Ok(response) => HttpResponse::Created()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(response),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+/// Identifies the logged in user of a browser-facing endpoint, either by its access token
+/// or by the login session cookie set at login.
+async fn resource_owner(oauth_service: &AppOAuthService, req: &HttpRequest) -> Option<Claims> {
+    let login_session = req.cookie("login_session");
+
+    oauth_service
+        .authenticate_resource_owner(bearer_token(req), login_session.as_ref().map(|c| c.value()))
+        .await
+}
+
+/// Extracts the token of an `Authorization: Bearer` header.
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|hv| hv.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+}
+
+/// Extracts client credentials from an `Authorization: Basic` header.
+fn basic_credentials(req: &HttpRequest) -> Option<ClientCredentials> {
+    let encoded = req
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    Some(ClientCredentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+    })
+}
+
+/// Turns malformed registration bodies into an `invalid_client_metadata` error.
+pub fn json_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    OAuthError::InvalidClientMetadata(err.to_string()).into()
+}
+
+/// Turns malformed form bodies into an OAuth `invalid_request` error.
+pub fn form_error(err: UrlencodedError, _req: &HttpRequest) -> actix_web::Error {
+    OAuthError::InvalidRequest(err.to_string()).into()
+}
@@ -0,0 +1,60 @@
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use serde_json::json;
+
+use crate::{
+    config::oidc,
+    utils::jwt::{Claims, JwtUtils},
+    AppAccountService,
+};
+
+/// OpenID Connect discovery document (OpenID Connect Discovery 1.0 section 3).
+pub async fn configuration() -> impl Responder {
+    let issuer = oidc::issuer();
+
+    HttpResponse::Ok().json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+        "token_endpoint": format!("{issuer}/oauth/token"),
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+        "jwks_uri": format!("{issuer}/.well-known/jwks.json"),
+        "revocation_endpoint": format!("{issuer}/oauth/revoke"),
+        "introspection_endpoint": format!("{issuer}/oauth/introspect"),
+        "scopes_supported": ["openid", "profile"],
+        "response_types_supported": ["code"],
+        "grant_types_supported": [
+            "authorization_code",
+            "refresh_token",
+            "client_credentials",
+            "urn:ietf:params:oauth:grant-type:device_code",
+            "urn:ietf:params:oauth:grant-type:token-exchange",
+        ],
+        "registration_endpoint": format!("{issuer}/oauth/register"),
+        "device_authorization_endpoint": format!("{issuer}/oauth/device_authorization"),
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": [JwtUtils::id_token_signing_algorithm()],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "code_challenge_methods_supported": ["S256"],
+        "prompt_values_supported": ["none", "consent"],
+        "claims_supported": ["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "preferred_username", "roles"],
+    }))
+}
+
+/// OpenID Connect UserInfo endpoint, describing the user the access token was issued to.
+pub async fn userinfo(
+    account_service: web::Data<AppAccountService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let account_id = match req.extensions().get::<Claims>() {
+        Some(token_data) => token_data.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match account_service.get_account_info(&account_id).await {
+        Ok(account) => HttpResponse::Ok().json(json!({
+            "sub": account.id.to_string(),
+            "preferred_username": account.username,
+            "roles": account.roles,
+        })),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
@@ -0,0 +1,133 @@
+use actix_web::{
+    web::{self, Json},
+    HttpMessage, HttpRequest, HttpResponse, Responder,
+};
+
+use crate::{
+    handlers::auth_handler::{device_info, login_response},
+    middleware::step_up_middleware::StepUpMiddleware,
+    model::webauthn::{AuthenticationCredential, RegistrationCredential, StartAuthentication},
+    utils::jwt::Claims,
+    AppAuthService, AppWebAuthnService,
+};
+
+/// Asks users with TOTP enabled to authenticate with it before registering a passkey,
+/// so a stolen password is not enough to add one.
+///
+/// # Returns
+///
+/// * `Some(HttpResponse)` - The response to send instead of registering.
+/// * `None` - If the registration may go on.
+async fn registration_step_up(
+    auth_service: &AppAuthService,
+    claims: &Claims,
+) -> Option<HttpResponse> {
+    match auth_service.totp_enabled(&claims.id).await {
+        Ok(true) => StepUpMiddleware::new().require_mfa().check(claims),
+        Ok(false) => None,
+        Err(e) => Some(HttpResponse::from_error(e)),
+    }
+}
+
+pub async fn start_registration(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    auth_service: web::Data<AppAuthService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+    if let Some(response) = registration_step_up(&auth_service, &claims).await {
+        return response;
+    }
+
+    match webauthn_service.start_registration(&claims.id).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn finish_registration(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    auth_service: web::Data<AppAuthService>,
+    credential: Json<RegistrationCredential>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+    if let Some(response) = registration_step_up(&auth_service, &claims).await {
+        return response;
+    }
+
+    match webauthn_service
+        .finish_registration(&claims.id, credential.0)
+        .await
+    {
+        Ok(credential) => HttpResponse::Created().json(credential),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn start_authentication(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    request: Json<StartAuthentication>,
+) -> impl Responder {
+    match webauthn_service
+        .start_authentication(request.username.as_deref())
+        .await
+    {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn finish_authentication(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    credential: Json<AuthenticationCredential>,
+    req: HttpRequest,
+) -> impl Responder {
+    match webauthn_service
+        .finish_authentication(credential.0, device_info(&req))
+        .await
+    {
+        Ok(result) => login_response(result),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn credentials(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match webauthn_service.list_credentials(&user_id).await {
+        Ok(credentials) => HttpResponse::Ok().json(credentials),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
+
+pub async fn delete_credential(
+    webauthn_service: web::Data<AppWebAuthnService>,
+    credential_id: web::Path<String>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id.clone(),
+        None => return HttpResponse::Unauthorized().body("Some thing wrong"),
+    };
+
+    match webauthn_service
+        .delete_credential(&user_id, &credential_id)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
@@ -0,0 +1,33 @@
+use mailer::AppMailer;
+use repository::{
+    account_repo::AccountRepo, client_repo::ClientRepo, grant_repo::GrantRepo, rbac_repo::RbacRepo,
+    token_redis_repo::TokenRedisRepo, webauthn_repo::WebAuthnRepo,
+};
+use service::{
+    account_service::AccountService, auth_service::AuthService, client_service::ClientService,
+    oauth_service::OAuthService, password_service::PasswordService, rbac_service::RbacService,
+    verification_service::VerificationService, webauthn_service::WebAuthnService,
+};
+
+pub mod config;
+pub mod error;
+pub mod handlers;
+pub mod mailer;
+pub mod middleware;
+pub mod model;
+pub mod repository;
+pub mod service;
+// The repository traits are only implemented and awaited within this service.
+#[allow(async_fn_in_trait)]
+pub mod traits;
+pub mod utils;
+
+pub type AppAuthService = AuthService<AccountRepo, TokenRedisRepo>;
+pub type AppAccountService = AccountService<AccountRepo>;
+pub type AppOAuthService =
+    OAuthService<AccountRepo, TokenRedisRepo, ClientRepo, GrantRepo, RbacRepo>;
+pub type AppClientService = ClientService<ClientRepo, TokenRedisRepo>;
+pub type AppRbacService = RbacService<RbacRepo>;
+pub type AppWebAuthnService = WebAuthnService<AccountRepo, TokenRedisRepo, WebAuthnRepo>;
+pub type AppVerificationService = VerificationService<AccountRepo, TokenRedisRepo, AppMailer>;
+pub type AppPasswordService = PasswordService<AccountRepo, TokenRedisRepo, AppMailer>;
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use lettre::{message::Mailbox, AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use log::info;
+
+use crate::{error::mail_error::MailError, model::mail::Mail, traits::mailer_trait::Mailer};
+
+/// `FileMailer` writes every message to an `.eml` file instead of delivering it,
+/// so development setups can read the mail they would have sent.
+pub struct FileMailer {
+    /// Sender of every message.
+    from: Mailbox,
+    /// Transport writing into the mail directory.
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileMailer {
+    /// Creates a new `FileMailer` writing into `dir`, which must exist.
+    pub fn new(from: Mailbox, dir: impl AsRef<Path>) -> Self {
+        Self {
+            from,
+            transport: AsyncFileTransport::new(dir),
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let message = super::message(&self.from, mail)?;
+
+        let id = self
+            .transport
+            .send(message)
+            .await
+            .map_err(|e| MailError::TransportError(e.to_string()))?;
+        info!("Mail to {} written to {}.eml", mail.to, id);
+
+        Ok(())
+    }
+}
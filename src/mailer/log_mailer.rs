@@ -0,0 +1,16 @@
+use log::info;
+
+use crate::{error::mail_error::MailError, model::mail::Mail, traits::mailer_trait::Mailer};
+
+/// `LogMailer` logs every mail instead of delivering it, so the service runs without a mail server.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        info!(
+            "Mail to {}\nSubject: {}\n\n{}",
+            mail.to, mail.subject, mail.body
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use lettre::{message::Mailbox, Message};
+
+use crate::{error::mail_error::MailError, model::mail::Mail, traits::mailer_trait::Mailer};
+
+pub mod file_mailer;
+pub mod log_mailer;
+pub mod smtp_mailer;
+
+/// The mail transport selected by the configuration.
+pub enum AppMailer {
+    Smtp(smtp_mailer::SmtpMailer),
+    File(file_mailer::FileMailer),
+    Log(log_mailer::LogMailer),
+}
+
+impl Mailer for AppMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        match self {
+            AppMailer::Smtp(mailer) => mailer.send(mail).await,
+            AppMailer::File(mailer) => mailer.send(mail).await,
+            AppMailer::Log(mailer) => mailer.send(mail).await,
+        }
+    }
+}
+
+/// Builds the message of a mail.
+///
+/// # Arguments
+///
+/// * `from` - The sender of the message.
+/// * `mail` - The recipient, subject and body.
+///
+/// # Returns
+///
+/// * `Ok(Message)` - The message, ready to be sent.
+/// * `Err(MailError)` - If the recipient is not a valid address.
+fn message(from: &Mailbox, mail: &Mail) -> Result<Message, MailError> {
+    let to: Mailbox = mail.to.parse().map_err(MailError::InvalidAddress)?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(mail.subject.clone())
+        .body(mail.body.clone())
+        .map_err(MailError::MessageError)
+}
@@ -4,40 +4,51 @@ use actix_web::{
     web::{self},
     App, HttpResponse, HttpServer, Responder,
 };
-use config::{db, redis};
+use auth_service::{
+    config::{db, mail, oauth, policy, redis},
+    handlers,
+    middleware::{
+        auth_middleware::{self},
+        first_party_middleware::FirstPartyMiddleware,
+        policy_middleware::PolicyMiddleware,
+        rbac_middleware::RbacMiddleware,
+        scope_middleware::ScopeMiddleware,
+        step_up_middleware::StepUpMiddleware,
+    },
+    repository::{
+        account_repo::AccountRepo, client_repo::ClientRepo, grant_repo::GrantRepo,
+        rbac_repo::RbacRepo, token_redis_repo::TokenRedisRepo, webauthn_repo::WebAuthnRepo,
+    },
+    service::{
+        account_service::AccountService,
+        auth_service::AuthService,
+        client_service::ClientService,
+        oauth_service::OAuthService,
+        password_service::PasswordService,
+        rbac_service::{RbacService, RuleOrdering},
+        token_service::TokenService,
+        verification_service::VerificationService,
+        webauthn_service::WebAuthnService,
+    },
+    utils::{self, policy::Condition},
+};
 use dotenvy::dotenv;
 use env_logger::Env;
 use log::info;
-use middleware::{
-    auth_middleware::{self},
-    rbac_middleware::RbacMiddleware,
-};
-use repository::{account_repo::AccountRepo, token_redis_repo::TokenRedisRepo};
-use service::{account_service::AccountService, auth_service::AuthService};
 use sqlx::migrate;
 
-mod config;
-mod error;
-mod handlers;
-mod middleware;
-mod model;
-mod repository;
-mod service;
-mod traits;
-mod utils;
-
 pub async fn index() -> impl Responder {
     HttpResponse::Ok().body("Welcome!")
 }
 
-type AppAuthService = AuthService<AccountRepo, TokenRedisRepo>;
-type AppAccountService = AccountService<AccountRepo>;
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
+    info!("Loading JWT signing keys");
+    lazy_static::initialize(&utils::jwt::ACCESS_KEYS);
+
     info!("Creating redis pool");
     let redis_pool = redis::create_redis_pool();
 
@@ -52,8 +63,13 @@ async fn main() -> std::io::Result<()> {
 
     info!("Starting server...");
 
-    let account_repo = Arc::new(AccountRepo::new(posgres_pool));
+    let account_repo = Arc::new(AccountRepo::new(posgres_pool.clone()));
+    let client_repo = Arc::new(ClientRepo::new(posgres_pool.clone()));
+    let grant_repo = Arc::new(GrantRepo::new(posgres_pool.clone()));
+    let rbac_repo = Arc::new(RbacRepo::new(posgres_pool.clone()));
+    let webauthn_repo = Arc::new(WebAuthnRepo::new(posgres_pool));
     let token_redis_repo = Arc::new(TokenRedisRepo::new(redis_pool));
+    let mailer = Arc::new(mail::create_mailer());
 
     let auth_service = Arc::new(AuthService::new(
         account_repo.clone(),
@@ -62,44 +78,462 @@ async fn main() -> std::io::Result<()> {
 
     let account_service = Arc::new(AccountService::new(account_repo.clone()));
 
+    let token_service = Arc::new(TokenService::new(
+        account_repo.clone(),
+        token_redis_repo.clone(),
+    ));
+
+    let client_service = Arc::new(ClientService::new(
+        client_repo.clone(),
+        token_redis_repo.clone(),
+        oauth::initial_access_token(),
+    ));
+
+    let rbac_service = Arc::new(RbacService::new(rbac_repo.clone()));
+
+    let oauth_service = Arc::new(OAuthService::new(
+        auth_service.clone(),
+        token_service.clone(),
+        token_redis_repo.clone(),
+        client_repo.clone(),
+        grant_repo.clone(),
+        rbac_service.clone(),
+    ));
+
+    let webauthn_service = Arc::new(WebAuthnService::new(
+        auth_service.clone(),
+        account_repo.clone(),
+        token_redis_repo.clone(),
+        webauthn_repo.clone(),
+    ));
+
+    let verification_service = Arc::new(VerificationService::new(
+        account_repo.clone(),
+        token_redis_repo.clone(),
+        mailer.clone(),
+    ));
+
+    let password_service = Arc::new(PasswordService::new(
+        auth_service.clone(),
+        account_repo.clone(),
+        token_redis_repo.clone(),
+        mailer.clone(),
+    ));
+
+    let admin_condition = policy::admin_condition();
+    let reauth_max_age = policy::reauth_max_age();
+
     let auth_middleware = Arc::new(auth_middleware::AuthMiddleware::new(
+        account_repo.clone(),
         token_redis_repo.clone(),
     ));
+    let user_auth_middleware = Arc::new(
+        auth_middleware::AuthMiddleware::new(account_repo.clone(), token_redis_repo.clone())
+            .users_only(),
+    );
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::from(auth_service.clone()))
             .app_data(web::Data::from(account_service.clone()))
+            .app_data(web::Data::from(oauth_service.clone()))
+            .app_data(web::Data::from(client_service.clone()))
+            .app_data(web::Data::from(rbac_service.clone()))
+            .app_data(web::Data::from(webauthn_service.clone()))
+            .app_data(web::Data::from(verification_service.clone()))
+            .app_data(web::Data::from(password_service.clone()))
             .route("/", web::get().to(index))
+            .route(
+                "/.well-known/jwks.json",
+                web::get().to(handlers::key_handler::jwks),
+            )
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(handlers::oidc_handler::configuration),
+            )
+            .service(
+                web::scope("/oauth")
+                    .app_data(
+                        web::FormConfig::default()
+                            .error_handler(handlers::oauth_handler::form_error),
+                    )
+                    .app_data(
+                        web::JsonConfig::default()
+                            .error_handler(handlers::oauth_handler::json_error),
+                    )
+                    .route(
+                        "/authorize",
+                        web::get().to(handlers::oauth_handler::authorize),
+                    )
+                    .route("/consent", web::get().to(handlers::oauth_handler::consent))
+                    .route(
+                        "/consent",
+                        web::post().to(handlers::oauth_handler::decide_consent),
+                    )
+                    .route("/token", web::post().to(handlers::oauth_handler::token))
+                    .route(
+                        "/register",
+                        web::post().to(handlers::oauth_handler::register),
+                    )
+                    .route(
+                        "/device_authorization",
+                        web::post().to(handlers::oauth_handler::device_authorization),
+                    )
+                    .route("/device", web::get().to(handlers::oauth_handler::device))
+                    .route(
+                        "/device",
+                        web::post().to(handlers::oauth_handler::decide_device),
+                    )
+                    .route("/revoke", web::post().to(handlers::oauth_handler::revoke))
+                    .route(
+                        "/introspect",
+                        web::post().to(handlers::oauth_handler::introspect),
+                    ),
+            )
+            .service(
+                web::scope("/userinfo")
+                    .wrap(user_auth_middleware.clone())
+                    .route("", web::get().to(handlers::oidc_handler::userinfo))
+                    .route("", web::post().to(handlers::oidc_handler::userinfo)),
+            )
             .service(
                 web::scope("/api")
+                    // Revocation and introspection stay served where clients first found them.
+                    .service(
+                        web::scope("/oauth")
+                            .app_data(
+                                web::FormConfig::default()
+                                    .error_handler(handlers::oauth_handler::form_error),
+                            )
+                            .app_data(
+                                web::JsonConfig::default()
+                                    .error_handler(handlers::oauth_handler::json_error),
+                            )
+                            .route("/revoke", web::post().to(handlers::oauth_handler::revoke))
+                            .route(
+                                "/introspect",
+                                web::post().to(handlers::oauth_handler::introspect),
+                            ),
+                    )
                     .service(
                         web::scope("/auth")
                             .route(
                                 "/register",
                                 web::post().to(handlers::auth_handler::register),
                             )
+                            .route(
+                                "/verify-email",
+                                web::get().to(handlers::auth_handler::verify_email),
+                            )
+                            .route(
+                                "/verify-email/resend",
+                                web::post().to(handlers::auth_handler::resend_verification),
+                            )
+                            .route(
+                                "/password/forgot",
+                                web::post().to(handlers::auth_handler::forgot_password),
+                            )
+                            .route(
+                                "/password/reset",
+                                web::post().to(handlers::auth_handler::reset_password),
+                            )
                             .route("/login", web::post().to(handlers::auth_handler::login))
+                            .route(
+                                "/login/mfa",
+                                web::post().to(handlers::auth_handler::login_mfa),
+                            )
+                            .route(
+                                "/webauthn/login/start",
+                                web::post().to(handlers::webauthn_handler::start_authentication),
+                            )
+                            .route(
+                                "/webauthn/login/finish",
+                                web::post().to(handlers::webauthn_handler::finish_authentication),
+                            )
                             .service(
                                 web::scope("")
-                                    .wrap(auth_middleware.clone())
+                                    .wrap(user_auth_middleware.clone())
                                     .route(
                                         "/refresh",
                                         web::post().to(handlers::auth_handler::refresh),
                                     )
                                     .route("/ping", web::get().to(index))
-                                    .route("/me", web::get().to(handlers::account_handler::me))
+                                    // Credentials and sessions are managed by the user, never
+                                    // by tokens issued to a client or minted by token exchange.
+                                    // Changing the second factor needs a recent login.
+                                    .route(
+                                        "/mfa/totp",
+                                        web::post()
+                                            .to(handlers::auth_handler::enroll_totp)
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .route(
+                                        "/mfa/totp/confirm",
+                                        web::post()
+                                            .to(handlers::auth_handler::confirm_totp)
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .route(
+                                        "/mfa/totp/disable",
+                                        web::post()
+                                            .to(handlers::auth_handler::disable_totp)
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .service(
+                                        // Adding a passkey needs a recent login, and the second
+                                        // factor of accounts having one, checked by the handlers.
+                                        web::scope("/webauthn/register")
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(FirstPartyMiddleware::new())
+                                            .route(
+                                                "/start",
+                                                web::post().to(
+                                                    handlers::webauthn_handler::start_registration,
+                                                ),
+                                            )
+                                            .route(
+                                                "/finish",
+                                                web::post().to(
+                                                    handlers::webauthn_handler::finish_registration,
+                                                ),
+                                            ),
+                                    )
+                                    .route(
+                                        "/webauthn/credentials",
+                                        web::get().to(handlers::webauthn_handler::credentials),
+                                    )
+                                    .service(
+                                        web::resource("/webauthn/credentials/{id}")
+                                            // Removing a passkey needs a recent multi-factor login.
+                                            .wrap(
+                                                StepUpMiddleware::new()
+                                                    .max_age(reauth_max_age)
+                                                    .require_mfa(),
+                                            )
+                                            .wrap(FirstPartyMiddleware::new())
+                                            .route(
+                                                web::delete().to(
+                                                    handlers::webauthn_handler::delete_credential,
+                                                ),
+                                            ),
+                                    )
+                                    .route(
+                                        "/reauthenticate",
+                                        web::post()
+                                            .to(handlers::auth_handler::reauthenticate)
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .route(
+                                        "/mfa/recovery-codes",
+                                        web::post()
+                                            .to(handlers::auth_handler::regenerate_recovery_codes)
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .service(
+                                        web::resource("/me")
+                                            .wrap(ScopeMiddleware::new(&["accounts:read"]))
+                                            .route(web::get().to(handlers::account_handler::me)),
+                                    )
+                                    .service(
+                                        web::resource("/sessions")
+                                            .wrap(ScopeMiddleware::new(&["sessions:read"]))
+                                            .route(web::get().to(handlers::auth_handler::sessions)),
+                                    )
+                                    .service(
+                                        web::resource("/sessions/{id}")
+                                            .wrap(ScopeMiddleware::new(&["sessions:write"]))
+                                            .route(
+                                                web::delete()
+                                                    .to(handlers::auth_handler::revoke_session),
+                                            ),
+                                    )
+                                    .service(
+                                        web::resource("/grants")
+                                            .wrap(ScopeMiddleware::new(&["grants:read"]))
+                                            .route(web::get().to(handlers::auth_handler::grants)),
+                                    )
+                                    .service(
+                                        web::resource("/grants/{client_id}")
+                                            .wrap(ScopeMiddleware::new(&["grants:write"]))
+                                            .route(
+                                                web::delete()
+                                                    .to(handlers::auth_handler::revoke_grant),
+                                            ),
+                                    )
                                     .route(
                                         "/logout",
-                                        web::post().to(handlers::auth_handler::logout),
+                                        web::post()
+                                            .to(handlers::auth_handler::logout)
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    )
+                                    .route(
+                                        "/logout-all",
+                                        web::post()
+                                            .to(handlers::auth_handler::logout_all)
+                                            .wrap(FirstPartyMiddleware::new()),
+                                    ),
+                            ),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::LongestMatch,
+                            ))
+                            .wrap(user_auth_middleware.clone())
+                            .service(
+                                web::resource("/{id}")
+                                    // Users may only read and update their own account unless they are administrators.
+                                    .wrap(PolicyMiddleware::new(
+                                        rbac_service.clone(),
+                                        Condition::Owner("id").or(Condition::Role("admin")),
+                                    ))
+                                    .route(
+                                        web::get()
+                                            .to(handlers::account_handler::account)
+                                            .wrap(ScopeMiddleware::new(&["accounts:read"])),
+                                    )
+                                    .route(
+                                        web::patch()
+                                            .to(handlers::account_handler::update_account)
+                                            // Changing an account needs a recent login.
+                                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                                            .wrap(ScopeMiddleware::new(&["accounts:write"])),
                                     ),
                             ),
                     )
                     .service(
                         web::scope("/admin/users")
-                            .wrap(RbacMiddleware)
+                            .wrap(StepUpMiddleware::new().max_age(reauth_max_age))
+                            .wrap(PolicyMiddleware::new(
+                                rbac_service.clone(),
+                                admin_condition.clone(),
+                            ))
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::LongestMatch,
+                            ))
+                            .wrap(ScopeMiddleware::new(&["admin:users"]))
+                            .wrap(auth_middleware.clone())
+                            .route("/", web::get().to(index))
+                            .route(
+                                "/{id}/sessions",
+                                web::get().to(handlers::admin_handler::user_sessions),
+                            )
+                            .route(
+                                "/{id}/sessions",
+                                web::delete().to(handlers::admin_handler::revoke_user_sessions),
+                            )
+                            .route(
+                                "/{id}/roles/{role}",
+                                web::put().to(handlers::admin_handler::assign_user_role),
+                            )
+                            .route(
+                                "/{id}/roles/{role}",
+                                web::delete().to(handlers::admin_handler::unassign_user_role),
+                            ),
+                    )
+                    .service(
+                        web::scope("/admin/clients")
+                            .wrap(PolicyMiddleware::new(
+                                rbac_service.clone(),
+                                admin_condition.clone(),
+                            ))
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::LongestMatch,
+                            ))
+                            .wrap(ScopeMiddleware::new(&["admin:clients"]))
+                            .wrap(auth_middleware.clone())
+                            .route("", web::get().to(handlers::admin_handler::clients))
+                            .route(
+                                "/{id}/secret",
+                                web::post().to(handlers::admin_handler::rotate_client_secret),
+                            )
+                            .route(
+                                "/{id}/redirect_uris",
+                                web::put().to(handlers::admin_handler::update_client_redirect_uris),
+                            )
+                            .route(
+                                "/{id}/disable",
+                                web::post().to(handlers::admin_handler::disable_client),
+                            ),
+                    )
+                    .service(
+                        web::scope("/admin/roles")
+                            // Policy administration honours permission priorities over pattern specificity.
+                            .wrap(PolicyMiddleware::new(
+                                rbac_service.clone(),
+                                admin_condition.clone(),
+                            ))
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::Priority,
+                            ))
+                            .wrap(ScopeMiddleware::new(&["admin:roles"]))
+                            .wrap(auth_middleware.clone())
+                            .route("", web::get().to(handlers::admin_handler::roles))
+                            .route("", web::post().to(handlers::admin_handler::create_role))
+                            .route(
+                                "/{name}",
+                                web::delete().to(handlers::admin_handler::delete_role),
+                            )
+                            .route(
+                                "/{name}/permissions/{permission}",
+                                web::put().to(handlers::admin_handler::grant_role_permission),
+                            )
+                            .route(
+                                "/{name}/permissions/{permission}",
+                                web::delete().to(handlers::admin_handler::revoke_role_permission),
+                            )
+                            .route(
+                                "/{name}/inherits/{parent}",
+                                web::put().to(handlers::admin_handler::add_role_inheritance),
+                            )
+                            .route(
+                                "/{name}/inherits/{parent}",
+                                web::delete().to(handlers::admin_handler::remove_role_inheritance),
+                            ),
+                    )
+                    .service(
+                        web::scope("/admin/permissions")
+                            .wrap(PolicyMiddleware::new(
+                                rbac_service.clone(),
+                                admin_condition.clone(),
+                            ))
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::Priority,
+                            ))
+                            .wrap(ScopeMiddleware::new(&["admin:permissions"]))
+                            .wrap(auth_middleware.clone())
+                            .route("", web::get().to(handlers::admin_handler::permissions))
+                            .route(
+                                "",
+                                web::post().to(handlers::admin_handler::create_permission),
+                            )
+                            .route(
+                                "/{name}",
+                                web::delete().to(handlers::admin_handler::delete_permission),
+                            ),
+                    )
+                    .service(
+                        web::scope("/admin/keys")
+                            .wrap(PolicyMiddleware::new(
+                                rbac_service.clone(),
+                                admin_condition.clone(),
+                            ))
+                            .wrap(RbacMiddleware::new(
+                                rbac_service.clone(),
+                                RuleOrdering::LongestMatch,
+                            ))
+                            .wrap(ScopeMiddleware::new(&["admin:keys"]))
                             .wrap(auth_middleware.clone())
-                            .route("/", web::get().to(index)),
+                            .route("/rotate", web::post().to(handlers::key_handler::rotate)),
                     ),
             )
     })
@@ -8,43 +8,61 @@ use actix_web::{
 use futures_util::future::{ok, Ready};
 use log::{error, info};
 
-use crate::{service::token_service::TokenService, traits::redis_traits::TokenRedisRepository};
+use crate::{
+    config::oidc,
+    service::token_service::TokenService,
+    traits::{account_trait::AccountRepository, redis_traits::TokenRedisRepository},
+};
 
 /// `AuthMiddleware` is a struct representing authentication middleware.
 /// This middleware is responsible for verifying access and refresh tokens
 /// from incoming requests and injecting verified token claims into the request
 /// extensions for downstream handlers to use.
+///
+/// Both user tokens and client tokens (from the `client_credentials` grant) are accepted,
+/// unless the middleware is restricted with `users_only`.
 #[derive(Clone)]
-pub struct AuthMiddleware<T: TokenRedisRepository> {
+pub struct AuthMiddleware<R: AccountRepository, T: TokenRedisRepository> {
     // Shared instance of the `TokenService`, responsible for token verification.
-    token_service: Arc<TokenService<T>>,
+    token_service: Arc<TokenService<R, T>>,
+    // Whether tokens issued to clients are accepted.
+    allow_clients: bool,
 }
 
-impl<T: TokenRedisRepository> AuthMiddleware<T> {
-    /// Creates a new `AuthMiddleware` with the given token repository.
+impl<R: AccountRepository, T: TokenRedisRepository> AuthMiddleware<R, T> {
+    /// Creates a new `AuthMiddleware` with the given repositories.
     ///
     /// # Arguments
     ///
+    /// * `account_repo` - An `Arc` wrapped account repository, used to reload roles on refresh.
     /// * `token_redis_repo` - An `Arc` wrapped repository for token storage and retrieval.
-    pub fn new(token_redis_repo: Arc<T>) -> Self {
+    pub fn new(account_repo: Arc<R>, token_redis_repo: Arc<T>) -> Self {
         Self {
-            token_service: Arc::new(TokenService::new(token_redis_repo)),
+            token_service: Arc::new(TokenService::new(account_repo, token_redis_repo)),
+            allow_clients: true,
         }
     }
+
+    /// Rejects client tokens with `403 Forbidden`, for routes that act on a user account.
+    pub fn users_only(mut self) -> Self {
+        self.allow_clients = false;
+        self
+    }
 }
 
 /// Actix Web `Transform` implementation for `AuthMiddleware`.
 ///
 /// This allows the middleware to be used in the Actix Web middleware chain.
-impl<S, B, T> Transform<S, ServiceRequest> for AuthMiddleware<T>
+impl<S, B, R, T> Transform<S, ServiceRequest> for AuthMiddleware<R, T>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: MessageBody + 'static,
+    R: AccountRepository + 'static,
     T: TokenRedisRepository + 'static,
 {
     type Response = ServiceResponse<BoxBody>;
     type Error = Error;
-    type Transform = AuthMiddlewareService<S, T>;
+    type Transform = AuthMiddlewareService<S, R, T>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
@@ -53,6 +71,7 @@ where
         ok(AuthMiddlewareService {
             service: Rc::new(service),
             token_service: self.token_service.clone(),
+            allow_clients: self.allow_clients,
         })
     }
 }
@@ -60,18 +79,21 @@ where
 /// `AuthMiddlewareService` is the actual service handling request processing.
 /// It wraps the underlying service and performs token validation before
 /// delegating the request to the next service in the chain.
-pub struct AuthMiddlewareService<S, T: TokenRedisRepository> {
+pub struct AuthMiddlewareService<S, R: AccountRepository, T: TokenRedisRepository> {
     /// The next service in the chain.
     service: Rc<S>,
     /// Shared `TokenService` for token verification.
-    token_service: Arc<TokenService<T>>,
+    token_service: Arc<TokenService<R, T>>,
+    /// Whether tokens issued to clients are accepted.
+    allow_clients: bool,
 }
 
 /// Actix Web `Service` implementation for `AuthMiddlewareService`.
-impl<S, B, T> Service<ServiceRequest> for AuthMiddlewareService<S, T>
+impl<S, B, R, T> Service<ServiceRequest> for AuthMiddlewareService<S, R, T>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: MessageBody + 'static,
+    R: AccountRepository + 'static,
     T: TokenRedisRepository + 'static,
 {
     type Response = ServiceResponse<BoxBody>;
@@ -86,15 +108,19 @@ where
     /// Handles the incoming request.
     ///
     /// If the request path is `/api/auth/refresh`, the middleware expects a `refresh_token` cookie.
-    /// It will verify the refresh token and insert the new access token into request extensions.
+    /// It will rotate the refresh token and insert the new token pair into request extensions.
     ///
     /// For other requests, the middleware expects a Bearer token in the `Authorization` header.
-    /// It will verify the access token and insert token claims into request extensions.
+    /// It will verify the access token, reject it if its `jti` is on the Redis denylist,
+    /// and insert token claims into request extensions.
+    /// Tokens restricted to another audience get `401 Unauthorized`,
+    /// client tokens get `403 Forbidden` when the middleware only allows users.
     ///
     /// If any token is invalid or missing, the middleware responds with `401 Unauthorized`.
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let srv = self.service.clone();
         let token_service = self.token_service.clone();
+        let allow_clients = self.allow_clients;
 
         info!("AuthMiddleware called");
 
@@ -105,12 +131,15 @@ where
             if path.ends_with("/api/auth/refresh") {
                 if let Some(cookie) = req.cookie("refresh_token") {
                     let refresh_token = cookie.value().to_string();
-                    match token_service.verify_refresh_token(&refresh_token).await {
-                        Ok(new_access_token) => {
-                            info!("Refresh token verified");
+                    match token_service
+                        .rotate_refresh_token(&refresh_token, None)
+                        .await
+                    {
+                        Ok(new_token) => {
+                            info!("Refresh token rotated");
 
-                            // Insert new access token into request extensions
-                            req.extensions_mut().insert(new_access_token);
+                            // Insert new token pair into request extensions
+                            req.extensions_mut().insert(new_token);
 
                             // Forward request to next service
                             let res = srv.call(req).await?;
@@ -130,7 +159,20 @@ where
                     .map(|s| s.trim_start_matches("Bearer "));
 
                 if let Some(token) = access_token {
-                    return match token_service.verify_access_token(token) {
+                    return match token_service.verify_access_token(token).await {
+                        Ok(claims)
+                            if claims
+                                .aud
+                                .as_ref()
+                                .is_some_and(|aud| *aud != oidc::issuer()) =>
+                        {
+                            info!("Access token issued for another audience");
+                            Ok(req.into_response(HttpResponse::Unauthorized().finish()))
+                        }
+                        Ok(claims) if claims.is_client() && !allow_clients => {
+                            info!("Client token rejected on a user route");
+                            Ok(req.into_response(HttpResponse::Forbidden().finish()))
+                        }
                         Ok(claims) => {
                             info!("Access token verified");
 
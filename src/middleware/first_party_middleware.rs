@@ -0,0 +1,113 @@
+use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::Error,
+    http::header,
+    HttpMessage, HttpResponse,
+};
+use futures_util::future::{ok, Ready};
+use log::info;
+
+use crate::utils::jwt::Claims;
+
+/// `FirstPartyMiddleware` reserves routes to tokens the user obtained by logging in directly.
+/// Tokens issued to an OAuth client (`client_id` claim) or minted by token exchange (`act` claim)
+/// carry the user's authentication too, but must not manage the user's credentials or sessions.
+/// It checks the claims verified by `AuthMiddleware`, so it must be wrapped inside it.
+/// Other tokens receive a `403 Forbidden` response with a
+/// `WWW-Authenticate: Bearer error="insufficient_scope"` header (RFC 6750 section 3.1).
+#[derive(Default, Clone, Copy)]
+pub struct FirstPartyMiddleware;
+
+impl FirstPartyMiddleware {
+    /// Creates a new `FirstPartyMiddleware`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Actix Web `Transform` implementation for `FirstPartyMiddleware`.
+///
+/// This allows the middleware to be used in the Actix Web middleware chain.
+impl<S, B> Transform<S, ServiceRequest> for FirstPartyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = FirstPartyMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    /// Initializes the middleware with the downstream service.
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(FirstPartyMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+/// `FirstPartyMiddlewareService` is the actual service that checks who the token was issued to.
+pub struct FirstPartyMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+/// Actix Web `Service` implementation for `FirstPartyMiddlewareService`.
+impl<S, B> Service<ServiceRequest> for FirstPartyMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        _ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Processes incoming requests, rejecting tokens issued to a client or an actor.
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        info!("FirstPartyMiddleware called");
+
+        let srv = self.service.clone();
+
+        // Retrieve user claims from request extensions
+        let user_info = req.extensions().get::<Claims>().cloned();
+
+        Box::pin(async move {
+            match user_info {
+                Some(user_info) if !is_first_party(&user_info) => {
+                    info!("Token issued to a client or an actor used on a first-party route");
+                    return Ok(req.into_response(
+                        HttpResponse::Forbidden()
+                            .insert_header((
+                                header::WWW_AUTHENTICATE,
+                                "Bearer error=\"insufficient_scope\"",
+                            ))
+                            .finish(),
+                    ));
+                }
+                Some(_) => {}
+                // If claims are missing, return 500 Internal Server Error.
+                None => {
+                    return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                }
+            }
+
+            // Forward the request to the inner service if the user logged in directly.
+            Ok(srv.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
+
+/// Checks whether a token was issued to the user itself rather than a client or an actor.
+fn is_first_party(user_info: &Claims) -> bool {
+    !user_info.is_client() && user_info.client_id.is_none() && user_info.act.is_none()
+}
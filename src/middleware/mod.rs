@@ -1,2 +1,6 @@
 pub mod auth_middleware;
+pub mod first_party_middleware;
+pub mod policy_middleware;
 pub mod rbac_middleware;
+pub mod scope_middleware;
+pub mod step_up_middleware;
@@ -0,0 +1,138 @@
+use std::{future::Future, pin::Pin, rc::Rc, sync::Arc, task::Poll};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::Error,
+    HttpMessage, HttpResponse,
+};
+use futures_util::future::{ok, Ready};
+use log::{error, info};
+
+use crate::{
+    service::rbac_service::RbacService,
+    traits::rbac_trait::RbacRepository,
+    utils::{
+        jwt::Claims,
+        policy::{Condition, PolicyContext},
+    },
+};
+
+/// `PolicyMiddleware` evaluates an attribute-based `Condition` over the token claims,
+/// the path parameters and the request attributes, next to the role checks of `RbacMiddleware`.
+/// It must be wrapped inside `AuthMiddleware`, and on a resource rather than a scope
+/// so the path parameters are matched when it runs.
+/// Requests failing the condition receive a `403 Forbidden` response.
+pub struct PolicyMiddleware<P: RbacRepository> {
+    /// Shared `RbacService` resolving inherited roles.
+    rbac_service: Arc<RbacService<P>>,
+    /// Condition the requests must satisfy.
+    condition: Arc<Condition>,
+}
+
+impl<P: RbacRepository> PolicyMiddleware<P> {
+    /// Creates a new `PolicyMiddleware` enforcing a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `rbac_service` - An `Arc` wrapped service resolving inherited roles.
+    /// * `condition` - The condition the requests must satisfy.
+    pub fn new(rbac_service: Arc<RbacService<P>>, condition: Condition) -> Self {
+        Self {
+            rbac_service,
+            condition: Arc::new(condition),
+        }
+    }
+}
+
+/// Actix Web `Transform` implementation for `PolicyMiddleware`.
+///
+/// This allows the middleware to be used in the Actix Web middleware chain.
+impl<S, B, P> Transform<S, ServiceRequest> for PolicyMiddleware<P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+    P: RbacRepository + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = PolicyMiddlewareService<S, P>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    /// Initializes the middleware with the downstream service.
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PolicyMiddlewareService {
+            service: Rc::new(service),
+            rbac_service: self.rbac_service.clone(),
+            condition: self.condition.clone(),
+        })
+    }
+}
+
+/// `PolicyMiddlewareService` is the actual service that evaluates the condition.
+pub struct PolicyMiddlewareService<S, P: RbacRepository> {
+    service: Rc<S>,
+    /// Shared `RbacService` resolving inherited roles.
+    rbac_service: Arc<RbacService<P>>,
+    /// Condition the requests must satisfy.
+    condition: Arc<Condition>,
+}
+
+/// Actix Web `Service` implementation for `PolicyMiddlewareService`.
+impl<S, B, P> Service<ServiceRequest> for PolicyMiddlewareService<S, P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+    P: RbacRepository + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        _ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Processes incoming requests, evaluates the condition, and either forwards the request or rejects it.
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        info!("PolicyMiddleware called");
+
+        let srv = self.service.clone();
+        let rbac_service = self.rbac_service.clone();
+        let condition = self.condition.clone();
+
+        // Retrieve user or client claims from request extensions
+        let user_info = req.extensions().get::<Claims>().cloned();
+
+        Box::pin(async move {
+            let user_info = match user_info {
+                Some(user_info) => user_info,
+                // If claims are missing, return 500 Internal Server Error.
+                None => {
+                    return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                }
+            };
+
+            let roles = match rbac_service.resolve_roles(&user_info.roles).await {
+                Ok(roles) => roles,
+                Err(e) => {
+                    error!("Cannot resolve roles: {}", e);
+                    return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                }
+            };
+
+            let ctx = PolicyContext::from_request(req.request(), user_info, roles);
+            if !condition.evaluate(&ctx) {
+                info!("Request denied by policy");
+                return Ok(req.into_response(HttpResponse::Forbidden().finish()));
+            }
+
+            // Forward the request to the inner service if the condition holds.
+            Ok(srv.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
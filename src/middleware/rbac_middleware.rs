@@ -1,4 +1,4 @@
-use std::{collections::HashMap, future::Future, pin::Pin, rc::Rc, task::Poll};
+use std::{future::Future, pin::Pin, rc::Rc, sync::Arc, task::Poll};
 
 use actix_web::{
     body::{BoxBody, MessageBody},
@@ -7,26 +7,55 @@ use actix_web::{
     HttpMessage, HttpResponse,
 };
 use futures_util::future::{ok, Ready};
-use log::info;
+use log::{error, info};
 
-use crate::utils::jwt::Claims;
+use crate::{
+    service::rbac_service::{RbacService, RuleOrdering},
+    traits::rbac_trait::RbacRepository,
+    utils::jwt::Claims,
+};
 
 /// `RbacMiddleware` is a struct representing a Role-Based Access Control middleware.
-/// It checks the user's role (from JWT claims) against the required roles for specific API paths.
+/// It checks the user's roles (from JWT claims) against the permissions of the roles
+/// and of the roles they inherit, managed in the database through `RbacService`.
+/// Permissions match the request method and path, each scope picks how overlapping
+/// permissions are ordered.
+/// Client tokens carry the role registered for the client.
 /// Unauthorized requests will receive a `403 Forbidden` response
-pub struct RbacMiddleware;
+pub struct RbacMiddleware<P: RbacRepository> {
+    /// Shared `RbacService` holding the cached role permissions.
+    rbac_service: Arc<RbacService<P>>,
+    /// How the permission deciding a request is picked.
+    ordering: RuleOrdering,
+}
+
+impl<P: RbacRepository> RbacMiddleware<P> {
+    /// Creates a new `RbacMiddleware` checking the permissions of the given service.
+    ///
+    /// # Arguments
+    ///
+    /// * `rbac_service` - An `Arc` wrapped service resolving role permissions.
+    /// * `ordering` - How the permission deciding a request is picked among the matching ones.
+    pub fn new(rbac_service: Arc<RbacService<P>>, ordering: RuleOrdering) -> Self {
+        Self {
+            rbac_service,
+            ordering,
+        }
+    }
+}
 
 /// Actix Web `Transform` implementation for `RbacMiddleware`.
 ///
 /// This allows the middleware to be used in the Actix Web middleware chain.
-impl<S, B> Transform<S, ServiceRequest> for RbacMiddleware
+impl<S, B, P> Transform<S, ServiceRequest> for RbacMiddleware<P>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: MessageBody + 'static,
+    P: RbacRepository + 'static,
 {
     type Response = ServiceResponse<BoxBody>;
     type Error = Error;
-    type Transform = RbacMiddlewareServie<S>;
+    type Transform = RbacMiddlewareServie<S, P>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
@@ -34,20 +63,27 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(RbacMiddlewareServie {
             service: Rc::new(service),
+            rbac_service: self.rbac_service.clone(),
+            ordering: self.ordering,
         })
     }
 }
 
 /// `RbacMiddlewareService` is the actual service that performs role-based checks.
-pub struct RbacMiddlewareServie<S> {
+pub struct RbacMiddlewareServie<S, P: RbacRepository> {
     service: Rc<S>,
+    /// Shared `RbacService` holding the cached role permissions.
+    rbac_service: Arc<RbacService<P>>,
+    /// How the permission deciding a request is picked.
+    ordering: RuleOrdering,
 }
 
 /// Actix Web `Service` implementation for `AuthMiddlewareService`.
-impl<S, B> Service<ServiceRequest> for RbacMiddlewareServie<S>
+impl<S, B, P> Service<ServiceRequest> for RbacMiddlewareServie<S, P>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: MessageBody + 'static,
+    P: RbacRepository + 'static,
 {
     type Response = ServiceResponse<BoxBody>;
     type Error = Error;
@@ -65,16 +101,29 @@ where
         info!("RbacMiddleware called");
 
         let srv = self.service.clone();
+        let rbac_service = self.rbac_service.clone();
+        let ordering = self.ordering;
+        let method = req.method().to_string();
         let path = req.path().to_string();
 
-        // Retrieve user claims from request extensions
+        // Retrieve user or client claims from request extensions
         let user_info = req.extensions().get::<Claims>().cloned();
 
         Box::pin(async move {
             if let Some(user_info) = user_info {
                 // Check if the user has permission to access the requested path.
-                if !has_permission(user_info, &path) {
-                    return Ok(req.into_response(HttpResponse::Forbidden().finish()));
+                match rbac_service
+                    .is_allowed(&user_info.roles, &method, &path, ordering)
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return Ok(req.into_response(HttpResponse::Forbidden().finish()));
+                    }
+                    Err(e) => {
+                        error!("Cannot load role permissions: {}", e);
+                        return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                    }
                 }
             } else {
                 // If claims are missing, return 500 Internal Server Error.
@@ -86,19 +135,3 @@ where
         })
     }
 }
-
-/// Checks whether the given user has permission to access a specific path.
-/// Permissions are defined using path prefixes and allowed roles.
-fn has_permission(user_info: Claims, path: &str) -> bool {
-    let mut permissions = HashMap::new();
-    permissions.insert("/api/admin", vec!["admin"]);
-    permissions.insert("/api/user", vec!["user", "admin"]);
-
-    for (prefix, roles) in &permissions {
-        if path.starts_with(prefix) {
-            return roles.contains(&user_info.role.as_str());
-        }
-    }
-
-    false
-}
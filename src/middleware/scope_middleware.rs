@@ -0,0 +1,129 @@
+use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::Error,
+    http::header,
+    HttpMessage, HttpResponse,
+};
+use futures_util::future::{ok, Ready};
+use log::info;
+
+use crate::utils::jwt::Claims;
+
+/// `ScopeMiddleware` declares the scopes a route requires.
+/// It checks the `scope` claim of the access token verified by `AuthMiddleware`,
+/// so it must be wrapped inside it. Tokens missing any of the scopes receive a `403 Forbidden`
+/// response with a `WWW-Authenticate: Bearer error="insufficient_scope"` header (RFC 6750 section 3.1).
+pub struct ScopeMiddleware {
+    /// Scopes the token must all carry.
+    scopes: Rc<Vec<&'static str>>,
+}
+
+impl ScopeMiddleware {
+    /// Creates a new `ScopeMiddleware` requiring every given scope.
+    ///
+    /// # Arguments
+    ///
+    /// * `scopes` - The scopes required by the wrapped routes.
+    pub fn new(scopes: &[&'static str]) -> Self {
+        Self {
+            scopes: Rc::new(scopes.to_vec()),
+        }
+    }
+}
+
+/// Actix Web `Transform` implementation for `ScopeMiddleware`.
+///
+/// This allows the middleware to be used in the Actix Web middleware chain.
+impl<S, B> Transform<S, ServiceRequest> for ScopeMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ScopeMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    /// Initializes the middleware with the downstream service.
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ScopeMiddlewareService {
+            service: Rc::new(service),
+            scopes: self.scopes.clone(),
+        })
+    }
+}
+
+/// `ScopeMiddlewareService` is the actual service that performs scope checks.
+pub struct ScopeMiddlewareService<S> {
+    service: Rc<S>,
+    scopes: Rc<Vec<&'static str>>,
+}
+
+/// Actix Web `Service` implementation for `ScopeMiddlewareService`.
+impl<S, B> Service<ServiceRequest> for ScopeMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        _ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Processes incoming requests, checks the token scopes, and either forwards the request or rejects it.
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        info!("ScopeMiddleware called");
+
+        let srv = self.service.clone();
+        let scopes = self.scopes.clone();
+
+        // Retrieve user or client claims from request extensions
+        let user_info = req.extensions().get::<Claims>().cloned();
+
+        Box::pin(async move {
+            match user_info {
+                Some(user_info) if !has_scopes(&user_info, &scopes) => {
+                    info!("Access token lacks the required scopes");
+                    let challenge = format!(
+                        "Bearer error=\"insufficient_scope\", scope=\"{}\"",
+                        scopes.join(" ")
+                    );
+                    return Ok(req.into_response(
+                        HttpResponse::Forbidden()
+                            .insert_header((header::WWW_AUTHENTICATE, challenge))
+                            .finish(),
+                    ));
+                }
+                Some(_) => {}
+                // If claims are missing, return 500 Internal Server Error.
+                None => {
+                    return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                }
+            }
+
+            // Forward the request to the inner service if the token carries every scope.
+            Ok(srv.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
+
+/// Checks whether the `scope` claim of a token contains every required scope.
+fn has_scopes(user_info: &Claims, required: &[&str]) -> bool {
+    let granted: Vec<&str> = user_info
+        .scope
+        .as_deref()
+        .map(|scope| scope.split_whitespace().collect())
+        .unwrap_or_default();
+
+    required.iter().all(|s| granted.contains(s))
+}
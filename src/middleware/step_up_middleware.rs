@@ -0,0 +1,199 @@
+use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::Error,
+    http::header,
+    HttpMessage, HttpResponse,
+};
+use chrono::Utc;
+use futures_util::future::{ok, Ready};
+use log::info;
+use serde_json::json;
+
+use crate::utils::jwt::{Claims, ACR_MFA};
+
+/// `StepUpMiddleware` declares how recently and how strongly the user must have authenticated
+/// to use a route. It checks the `auth_time` and `acr` claims of the access token verified by
+/// `AuthMiddleware`, so it must be wrapped inside it.
+///
+/// Tokens that fall short receive a `401 Unauthorized` response with a
+/// `WWW-Authenticate: Bearer error="insufficient_user_authentication"` header (RFC 9470)
+/// and a JSON body telling the client to re-authenticate through `/api/auth/reauthenticate`.
+/// Client tokens have no user behind them and are let through.
+#[derive(Default, Clone, Copy)]
+pub struct StepUpMiddleware {
+    policy: StepUpPolicy,
+}
+
+/// Requirements of a route on the user's last authentication.
+#[derive(Default, Clone, Copy)]
+struct StepUpPolicy {
+    /// Maximum age in seconds of the authentication.
+    max_age: Option<i64>,
+    /// Whether the authentication must have used a second factor or a passkey.
+    require_mfa: bool,
+}
+
+impl StepUpMiddleware {
+    /// Creates a new `StepUpMiddleware` with no requirement.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the user to have authenticated in the last `seconds`.
+    pub fn max_age(mut self, seconds: i64) -> Self {
+        self.policy.max_age = Some(seconds);
+        self
+    }
+
+    /// Requires the user to have authenticated with a second factor or a passkey.
+    pub fn require_mfa(mut self) -> Self {
+        self.policy.require_mfa = true;
+        self
+    }
+
+    /// Checks claims against the requirements from a handler, for requirements that depend
+    /// on the account and cannot be declared on the route.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(HttpResponse)` - The response asking the client to re-authenticate.
+    /// * `None` - If the claims meet the requirements or belong to a client.
+    pub fn check(&self, user_info: &Claims) -> Option<HttpResponse> {
+        if user_info.is_client() {
+            return None;
+        }
+
+        self.policy.unmet(user_info).map(|reason| {
+            info!("Re-authentication required: {}", reason);
+            self.policy.challenge(reason)
+        })
+    }
+}
+
+/// Actix Web `Transform` implementation for `StepUpMiddleware`.
+///
+/// This allows the middleware to be used in the Actix Web middleware chain.
+impl<S, B> Transform<S, ServiceRequest> for StepUpMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = StepUpMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    /// Initializes the middleware with the downstream service.
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(StepUpMiddlewareService {
+            service: Rc::new(service),
+            step_up: *self,
+        })
+    }
+}
+
+/// `StepUpMiddlewareService` is the actual service that performs the authentication checks.
+pub struct StepUpMiddlewareService<S> {
+    service: Rc<S>,
+    step_up: StepUpMiddleware,
+}
+
+/// Actix Web `Service` implementation for `StepUpMiddlewareService`.
+impl<S, B> Service<ServiceRequest> for StepUpMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        _ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Processes incoming requests, checks the user's last authentication, and either forwards the request or rejects it.
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        info!("StepUpMiddleware called");
+
+        let srv = self.service.clone();
+        let step_up = self.step_up;
+
+        // Retrieve user or client claims from request extensions
+        let user_info = req.extensions().get::<Claims>().cloned();
+
+        Box::pin(async move {
+            match user_info {
+                Some(user_info) => {
+                    if let Some(challenge) = step_up.check(&user_info) {
+                        return Ok(req.into_response(challenge));
+                    }
+                }
+                // If claims are missing, return 500 Internal Server Error.
+                None => {
+                    return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+                }
+            }
+
+            // Forward the request to the inner service if the authentication is recent and strong enough.
+            Ok(srv.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
+
+impl StepUpPolicy {
+    /// Returns why a token does not meet the policy, if it does not.
+    fn unmet(&self, user_info: &Claims) -> Option<&'static str> {
+        if let Some(max_age) = self.max_age {
+            let fresh = user_info
+                .auth_time
+                .is_some_and(|t| Utc::now().timestamp() - t as i64 <= max_age);
+            if !fresh {
+                return Some("A more recent authentication is required");
+            }
+        }
+
+        if self.require_mfa && user_info.acr.as_deref() != Some(ACR_MFA) {
+            return Some("A multi-factor authentication is required");
+        }
+
+        None
+    }
+
+    /// Builds the response asking the client to re-authenticate.
+    fn challenge(&self, reason: &str) -> HttpResponse {
+        let mut challenge = format!(
+            "Bearer error=\"insufficient_user_authentication\", error_description=\"{}\"",
+            reason
+        );
+        if let Some(max_age) = self.max_age {
+            challenge.push_str(&format!(", max_age={}", max_age));
+        }
+        if self.require_mfa {
+            challenge.push_str(&format!(", acr_values=\"{}\"", ACR_MFA));
+        }
+
+        let mut body = json!({
+            "error": "insufficient_user_authentication",
+            "error_description": reason,
+            "reauthenticate": "/api/auth/reauthenticate",
+        });
+        if let Some(max_age) = self.max_age {
+            body["max_age"] = json!(max_age);
+        }
+        if self.require_mfa {
+            body["acr_values"] = json!(ACR_MFA);
+        }
+
+        HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, challenge))
+            .json(body)
+    }
+}
@@ -6,7 +6,18 @@ pub struct Account {
     pub id: i32,
     pub username: String,
     pub password: Option<String>,
-    pub role: String,
+    pub email: Option<String>,
+    /// Whether the owner of the account proved they receive mail sent to `email`.
+    pub email_verified: bool,
+    /// Roles assigned to the account, without the roles they inherit.
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Registration {
+    pub username: String,
+    pub password: String,
+    pub email: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -14,3 +25,38 @@ pub struct LoginInfo {
     pub username: String,
     pub password: String,
 }
+
+/// Credentials re-entered by a logged in user to refresh their authentication.
+#[derive(Debug, Deserialize)]
+pub struct Reauthenticate {
+    pub password: String,
+    /// TOTP code, raising the session to multi-factor.
+    pub code: Option<String>,
+}
+
+/// Body of an account update, fields left out are kept.
+#[derive(Debug, Deserialize)]
+pub struct AccountUpdate {
+    pub username: Option<String>,
+    /// New email address, unverified until the link sent to it is followed.
+    pub email: Option<String>,
+}
+
+/// Verification token sent by mail, proving the owner of an account receives mail at its address.
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+/// Address a mail should be sent to.
+#[derive(Debug, Deserialize)]
+pub struct EmailAddress {
+    pub email: String,
+}
+
+/// New password of an account, authorized by the token of a reset mail.
+#[derive(Debug, Deserialize)]
+pub struct PasswordReset {
+    pub token: String,
+    pub password: String,
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// An OAuth client application registered with the authorization server.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Client {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    /// Argon2 hash of the client secret, `None` for public clients.
+    #[serde(skip_serializing)]
+    pub client_secret: Option<String>,
+    /// Role carried by the tokens the client obtains for itself.
+    pub role: String,
+    /// Disabled clients can no longer authenticate or obtain tokens.
+    pub disabled: bool,
+}
+
+impl Client {
+    /// Returns true if the client must authenticate with a secret.
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret.is_some()
+    }
+}
+
+/// Client metadata sent to the dynamic registration endpoint (RFC 7591 section 2).
+#[derive(Debug, Deserialize)]
+pub struct ClientRegistrationRequest {
+    pub client_name: String,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// `none` registers a public client, anything else a confidential one.
+    pub token_endpoint_auth_method: Option<String>,
+}
+
+/// Response of the dynamic registration endpoint (RFC 7591 section 3.2.1).
+/// The client secret is only ever returned here, it is stored hashed.
+#[derive(Debug, Serialize)]
+pub struct ClientRegistrationResponse {
+    pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Always 0, secrets do not expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<i64>,
+    pub client_name: String,
+    pub redirect_uris: Vec<String>,
+    pub token_endpoint_auth_method: String,
+}
+
+/// Body of the admin endpoint replacing the redirect URIs of a client.
+#[derive(Debug, Deserialize)]
+pub struct RedirectUris {
+    pub redirect_uris: Vec<String>,
+}
+
+/// A newly generated client secret, returned once by the admin rotation endpoint.
+#[derive(Debug, Serialize)]
+pub struct ClientSecret {
+    pub client_id: String,
+    pub client_secret: String,
+}
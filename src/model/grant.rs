@@ -0,0 +1,12 @@
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+
+/// Scopes a user granted to an OAuth client on the consent screen.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Grant {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    /// Unix timestamp of the last consent.
+    pub granted_at: i64,
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::token::Token;
+
+/// TOTP secret of an account, stored in the `account_totp` table.
+#[derive(Debug, FromRow)]
+pub struct TotpSecret {
+    /// Base32 encoded secret shared with the authenticator app.
+    pub secret: String,
+    /// Whether the user proved the authenticator works. Unconfirmed secrets are not enforced at login.
+    pub confirmed: bool,
+}
+
+/// Returned when TOTP enrollment starts.
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Single-use recovery codes, shown to the user once.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// A TOTP code, or a recovery code where accepted.
+#[derive(Debug, Deserialize)]
+pub struct MfaCode {
+    pub code: String,
+}
+
+/// Second step of a login, answering an MFA challenge.
+#[derive(Debug, Deserialize)]
+pub struct MfaLogin {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+/// Value stored in Redis under `mfa_challenge:{token}` between the two login steps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallenge {
+    pub user_id: String,
+    pub roles: Vec<String>,
+}
+
+/// Returned by the first login step when the account has a second factor.
+#[derive(Debug, Serialize)]
+pub struct MfaRequired {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
+/// Outcome of checking a username and password.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    /// The account has no second factor, the session is started.
+    Token(Token),
+    /// The account has a second factor that must be answered first.
+    MfaRequired(MfaRequired),
+}
@@ -1,2 +1,10 @@
 pub mod account;
+pub mod client;
+pub mod grant;
+pub mod mail;
+pub mod mfa;
+pub mod oauth;
+pub mod rbac;
+pub mod session;
 pub mod token;
+pub mod webauthn;
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::jwt::{Actor, Authentication};
+
+/// Credentials a client authenticates with.
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// Form body of the token revocation endpoint (RFC 7009).
+#[derive(Debug, Deserialize)]
+pub struct RevocationRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Form body of the token introspection endpoint (RFC 7662).
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Response of the token introspection endpoint (RFC 7662).
+/// Only `active` is set for tokens that are invalid, expired or revoked.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /// Set when the token was issued to a client acting on its own behalf.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+}
+
+/// Query parameters of the authorization endpoint (RFC 6749 section 4.1.1, RFC 7636).
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    /// Space separated scopes, `openid` requests an ID token.
+    pub scope: Option<String>,
+    /// OpenID Connect nonce, copied into the ID token.
+    pub nonce: Option<String>,
+    /// `consent` always shows the consent screen, `none` fails instead of showing it.
+    pub prompt: Option<String>,
+}
+
+/// Form body of the token endpoint. Which fields are required depends on `grant_type`.
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub device_code: Option<String>,
+    /// Token exchange parameters (RFC 8693 section 2.1).
+    pub subject_token: Option<String>,
+    pub subject_token_type: Option<String>,
+    pub requested_token_type: Option<String>,
+    pub audience: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// Successful response of the token endpoint (RFC 6749 section 5.1).
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// OpenID Connect ID token, issued when the `openid` scope was granted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    /// Type of the issued token, required in token exchange responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// Value stored in Redis under `authorization_code:{code}` until the code is redeemed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizationCode {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub user_id: String,
+    pub roles: Vec<String>,
+    /// PKCE S256 challenge the `code_verifier` must match.
+    pub code_challenge: String,
+    /// Scopes requested by the client.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// OpenID Connect nonce of the authorization request.
+    #[serde(default)]
+    pub nonce: Option<String>,
+    /// Unix timestamp of the user's login, reported as `auth_time` in the ID token.
+    #[serde(default)]
+    pub auth_time: i64,
+    /// Authentication methods of the user's login, carried over to the issued tokens.
+    #[serde(default)]
+    pub amr: Vec<String>,
+}
+
+/// Form body of the device authorization endpoint (RFC 8628 section 3.1).
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// Response of the device authorization endpoint (RFC 8628 section 3.2).
+#[derive(Debug, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    /// Minimum number of seconds the device must wait between polls.
+    pub interval: i64,
+}
+
+/// Progress of a device authorization, decided by the user on the verification page.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceAuthorizationStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+/// Value stored in Redis under `device_code:{device_code}` until the device redeems it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorization {
+    pub client_id: String,
+    /// Normalized user code, without separator.
+    pub user_code: String,
+    pub scope: Option<String>,
+    pub status: DeviceAuthorizationStatus,
+    /// The user who approved the request.
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// How the approving user authenticated, carried over to the issued tokens.
+    #[serde(default)]
+    pub authentication: Option<Authentication>,
+}
+
+/// Query parameters of the device verification page.
+#[derive(Debug, Deserialize)]
+pub struct UserCodeQuery {
+    pub user_code: String,
+}
+
+/// Decision of the user on the device verification page.
+#[derive(Debug, Deserialize)]
+pub struct DeviceDecision {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+/// Pending device authorization shown to the user before they approve it.
+#[derive(Debug, Serialize)]
+pub struct DeviceVerification {
+    pub user_code: String,
+    pub client_id: String,
+    pub client_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// Authorization request waiting for the user's consent, stored in Redis under
+/// `consent_request:{consent_id}` until the user decides.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentRequest {
+    /// The code to issue once the user consents.
+    pub authorization: AuthorizationCode,
+    /// The client's `state`, returned with the redirect.
+    pub state: Option<String>,
+}
+
+/// Query parameters of the consent screen.
+#[derive(Debug, Deserialize)]
+pub struct ConsentQuery {
+    pub consent_id: String,
+}
+
+/// Decision of the user on the consent screen.
+#[derive(Debug, Deserialize)]
+pub struct ConsentDecision {
+    pub consent_id: String,
+    pub approve: bool,
+}
+
+/// What the consent screen shows the user.
+#[derive(Debug, Serialize)]
+pub struct ConsentPrompt {
+    pub consent_id: String,
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// A role assigned to accounts and clients, with the permissions it holds.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Role {
+    pub name: String,
+    pub description: Option<String>,
+    /// API scopes tokens of the role may carry, on top of the inherited roles' scopes.
+    pub scopes: Vec<String>,
+    /// Names of the permissions granted to the role.
+    pub permissions: Vec<String>,
+    /// Roles whose permissions the role inherits.
+    pub inherits: Vec<String>,
+}
+
+/// Body of a role creation request.
+#[derive(Debug, Deserialize)]
+pub struct NewRole {
+    pub name: String,
+    pub description: Option<String>,
+    /// API scopes tokens of the role may carry.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// A permission gives access to the requests matching a method and a path pattern,
+/// such as `GET /api/admin/users/{id}` or `/api/admin/*` for every method.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Permission {
+    pub name: String,
+    /// HTTP method the permission applies to, `None` for every method.
+    #[serde(default)]
+    pub method: Option<String>,
+    pub path_pattern: String,
+    /// Rank of the permission when rules are ordered by priority, higher wins.
+    #[serde(default)]
+    pub priority: i32,
+    pub description: Option<String>,
+}
+
+/// A role inheriting the permissions of another, as loaded into the RBAC cache.
+#[derive(Debug, FromRow)]
+pub struct RoleInheritance {
+    pub role: String,
+    pub inherits: String,
+}
+
+/// A rule a role is allowed by, as loaded into the RBAC cache.
+#[derive(Debug, FromRow)]
+pub struct RolePermission {
+    pub role: String,
+    pub method: Option<String>,
+    pub path_pattern: String,
+    pub priority: i32,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::jwt::Authentication;
+
+/// A login session, one per device. Its id is also the id of the refresh token family.
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub user_id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: i64,
+    pub last_used_at: i64,
+    /// OAuth client the session was started for, `None` for direct logins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Whether this is the session the request was made from.
+    pub current: bool,
+}
+
+/// Browser login of a direct session, stored in Redis under `login_session:{token}`.
+/// The token is sent as the `login_session` cookie so the authorization endpoints
+/// can recognise the user on a redirect from another site.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginSession {
+    pub user_id: String,
+    pub session_id: String,
+    pub authentication: Authentication,
+}
+
+/// Information about the device a login comes from.
+#[derive(Debug, Default)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
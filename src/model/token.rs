@@ -4,9 +4,29 @@ use serde::{Deserialize, Serialize};
 pub struct Token {
     pub access_token: String,
     pub refresh_token: String,
+    /// Space separated scopes the tokens are restricted to, reported by the token endpoint.
+    #[serde(skip)]
+    pub scope: Option<String>,
+    /// Browser login session token, set for direct logins and sent as a cookie only.
+    #[serde(skip)]
+    pub login_session: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RefreshToken {
     pub refresh_token: String,
 }
+
+/// Value stored in Redis under `refresh_token:{token}`.
+///
+/// Every refresh token belongs to a family: the chain of tokens produced by
+/// rotating the one issued at login. Reusing a rotated token revokes the whole family.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenRecord {
+    pub user_id: String,
+    pub family_id: String,
+    /// OAuth client the family was issued to, `None` for direct logins.
+    /// Only that client may rotate the family's tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// A passkey or security key registered to an account.
+#[derive(Debug, Serialize, FromRow)]
+pub struct WebAuthnCredential {
+    /// Base64url encoded credential id chosen by the authenticator.
+    #[serde(rename = "id")]
+    pub credential_id: String,
+    #[serde(skip_serializing)]
+    pub account_id: i32,
+    /// COSE encoded public key.
+    #[serde(skip_serializing)]
+    pub public_key: Vec<u8>,
+    /// Signature counter reported by the authenticator, 0 if it keeps none.
+    pub sign_count: i64,
+    pub name: Option<String>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+/// Value stored in Redis under `webauthn_challenge:{challenge}` until the ceremony finishes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebAuthnChallenge {
+    /// The `type` the client data of the ceremony must carry, `webauthn.create` or `webauthn.get`.
+    pub ceremony: String,
+    /// The user registering a credential, or the user expected to log in if a username was given.
+    pub user_id: Option<String>,
+}
+
+/// Options passed to `navigator.credentials.create()`.
+#[derive(Debug, Serialize)]
+pub struct CreationOptions {
+    #[serde(rename = "publicKey")]
+    pub public_key: PublicKeyCredentialCreationOptions,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredentialCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: i64,
+    pub attestation: &'static str,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// Base64url encoded user handle, the account id.
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: &'static str,
+    pub user_verification: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+}
+
+/// Options passed to `navigator.credentials.get()`.
+#[derive(Debug, Serialize)]
+pub struct RequestOptions {
+    #[serde(rename = "publicKey")]
+    pub public_key: PublicKeyCredentialRequestOptions,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredentialRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: i64,
+    pub user_verification: &'static str,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+/// Starts a passkey login. Without a username any discoverable credential may answer.
+#[derive(Debug, Deserialize)]
+pub struct StartAuthentication {
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.create()`,
+/// binary fields base64url encoded.
+#[derive(Debug, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+    /// Name the user gives the credential.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.get()`,
+/// binary fields base64url encoded.
+#[derive(Debug, Deserialize)]
+pub struct AuthenticationCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle", default)]
+    pub user_handle: Option<String>,
+}
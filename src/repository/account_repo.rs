@@ -1,6 +1,9 @@
 use sqlx::{Pool, Postgres};
 
-use crate::{model::account::Account, traits::account_trait::AccountRepository};
+use crate::{
+    model::{account::Account, mfa::TotpSecret},
+    traits::account_trait::AccountRepository,
+};
 
 /// `AccountRepo` provides an implementation of `AccountRepository` for PostgreSQL.
 /// It handles basic account-related operations such as inserting a new account,
@@ -32,19 +35,26 @@ impl AccountRepository for AccountRepo {
     ///
     /// * `username` - The username for the new account.
     /// * `password` - The hashed password for the new account.
+    /// * `email` - The email address of the new account, unverified.
     ///
     /// # Returns
     ///
     /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
     /// * `Err(sqlx::Error)` - If an error occurs during database operation.
-    async fn insert_account(&self, username: String, password: String) -> Result<u64, sqlx::Error> {
+    async fn insert_account(
+        &self,
+        username: String,
+        password: String,
+        email: String,
+    ) -> Result<u64, sqlx::Error> {
         // Load SQL query from external file.
         let insert_stmt = include_str!("../../sql/insert_account.sql");
 
-        // Execute the query, binding the username, password, and default "user" role.
-        let x = sqlx::query(&insert_stmt)
+        // Execute the query, binding the username, password, email, and default "user" role.
+        let x = sqlx::query(insert_stmt)
             .bind(username)
             .bind(password)
+            .bind(email)
             .bind(String::from("user")) // Default role assigned to new accounts.
             .execute(&self.pool)
             .await?;
@@ -68,7 +78,7 @@ impl AccountRepository for AccountRepo {
         let select_stmt = include_str!("../../sql/get_auth_info.sql");
 
         // Perform the query and try to fetch the account as `Account` model.
-        let result: Option<Account> = sqlx::query_as(&select_stmt)
+        let result: Option<Account> = sqlx::query_as(select_stmt)
             .bind(username)
             .fetch_optional(&self.pool)
             .await?;
@@ -80,6 +90,126 @@ impl AccountRepository for AccountRepo {
         }
     }
 
+    /// Retrieves account information by email address, ignoring case.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The email address to search for.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Account)` - The account information if found, without its password.
+    /// * `Err(sqlx::Error)` - Returns `RowNotFound` if no account has this address, or other SQLx errors.
+    async fn get_account_by_email(&self, email: &str) -> Result<Account, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_account_by_email.sql");
+
+        let account: Option<Account> = sqlx::query_as(stmt)
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match account {
+            Some(account) => Ok(account),
+            None => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Retrieves the API scopes of roles, including the scopes of every role they inherit.
+    ///
+    /// # Arguments
+    ///
+    /// * `roles` - The roles assigned to an account or client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - The distinct scopes, sorted.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn get_roles_scopes(&self, roles: &[String]) -> Result<Vec<String>, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_roles_scopes.sql");
+
+        sqlx::query_scalar(stmt)
+            .bind(roles)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Marks the email address of an account as verified, if it is still the account's address.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the account.
+    /// * `email` - The address that was verified.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account or its address changed.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn set_email_verified(&self, id: i32, email: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/set_email_verified.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(id)
+            .bind(email)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Replaces the password hash of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the account.
+    /// * `password` - The new hashed password.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account does not exist.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn update_password(&self, id: i32, password: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/update_password.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(id)
+            .bind(password)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Changes the username and email address of an account.
+    /// A changed address is no longer verified.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the account.
+    /// * `username` - The new username, `None` to keep it.
+    /// * `email` - The new email address, `None` to keep it.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account does not exist.
+    /// * `Err(sqlx::Error)` - A unique violation if the address belongs to another account, or other SQLx errors.
+    async fn update_account(
+        &self,
+        id: i32,
+        username: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/update_account.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(id)
+            .bind(username)
+            .bind(email)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Checks if an account with the given username exists.
     ///
     /// # Arguments
@@ -95,7 +225,7 @@ impl AccountRepository for AccountRepo {
         let select_stmt = include_str!("../../sql/get_auth_info.sql");
 
         // Check if the account exists by trying to fetch it.
-        let account: Option<Account> = sqlx::query_as(&select_stmt)
+        let account: Option<Account> = sqlx::query_as(select_stmt)
             .bind(username)
             .fetch_optional(&self.pool)
             .await?;
@@ -130,4 +260,179 @@ impl AccountRepository for AccountRepo {
             None => Err(sqlx::Error::RowNotFound),
         }
     }
+
+    /// Retrieves the TOTP secret of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account whose secret to fetch.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TotpSecret)` - The secret, confirmed or not.
+    /// * `Err(sqlx::Error)` - Returns `RowNotFound` if the account never started enrollment, or other SQLx errors.
+    async fn get_totp(&self, account_id: i32) -> Result<TotpSecret, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_totp.sql");
+
+        let totp: Option<TotpSecret> = sqlx::query_as(stmt)
+            .bind(account_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match totp {
+            Some(totp) => Ok(totp),
+            None => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Stores a new unconfirmed TOTP secret, replacing an earlier unconfirmed one.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account enrolling.
+    /// * `secret` - The base32 encoded secret.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account already has a confirmed secret.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn upsert_totp(&self, account_id: i32, secret: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/upsert_totp.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(secret)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Confirms the TOTP secret of an account and stores its recovery codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account enrolling.
+    /// * `step` - The time step of the code that confirmed the secret.
+    /// * `recovery_code_hashes` - The hashes of the new recovery codes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of recovery codes stored, 0 if there is no unconfirmed secret.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn confirm_totp(
+        &self,
+        account_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/confirm_totp.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(step)
+            .bind(recovery_code_hashes)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Marks a time step as used, so its code cannot be replayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account the code was entered for.
+    /// * `step` - The time step of the code.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if a code of this or a later step was already used.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn use_totp_step(&self, account_id: i32, step: i64) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/use_totp_step.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(step)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Marks a recovery code as used.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account the code was entered for.
+    /// * `code_hash` - The hash of the recovery code.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the code is unknown or already used.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn use_recovery_code(
+        &self,
+        account_id: i32,
+        code_hash: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/use_recovery_code.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(code_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Replaces every recovery code of an account with a confirmed TOTP secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account whose codes to replace.
+    /// * `recovery_code_hashes` - The hashes of the new recovery codes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of recovery codes stored, 0 if the account has no confirmed secret.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn replace_recovery_codes(
+        &self,
+        account_id: i32,
+        recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/replace_recovery_codes.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(recovery_code_hashes)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes the TOTP secret of an account together with its recovery codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account whose second factor to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account has no secret.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn delete_totp(&self, account_id: i32) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_totp.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(account_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
 }
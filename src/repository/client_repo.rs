@@ -0,0 +1,169 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{model::client::Client, traits::client_trait::ClientRepository};
+
+/// `ClientRepo` provides an implementation of `ClientRepository` for PostgreSQL.
+/// It reads and manages the OAuth clients registered in the `oauth_client` table.
+pub struct ClientRepo {
+    /// Connection pool for interacting with the PostgreSQL database.
+    pool: Pool<Postgres>,
+}
+
+impl ClientRepo {
+    /// Creates a new `ClientRepo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The SQLx connection pool for PostgreSQL.
+    ///
+    /// # Returns
+    ///
+    /// * A new instance of `ClientRepo`.
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+impl ClientRepository for ClientRepo {
+    /// Retrieves a registered client by its client id.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client id to search for.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Client)` - The client if found.
+    /// * `Err(sqlx::Error)` - Returns `RowNotFound` if no client has this id, or other SQLx errors.
+    async fn get_client_by_id(&self, client_id: &str) -> Result<Client, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_client_by_id.sql");
+
+        let client: Option<Client> = sqlx::query_as(stmt)
+            .bind(client_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match client {
+            Some(client) => Ok(client),
+            None => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Retrieves every registered client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Client>)` - The clients, ordered by client id.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_clients(&self) -> Result<Vec<Client>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_clients.sql");
+
+        sqlx::query_as(stmt).fetch_all(&self.pool).await
+    }
+
+    /// Inserts a new client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to register, with its secret already hashed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn insert_client(&self, client: &Client) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/insert_client.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(&client.client_id)
+            .bind(&client.name)
+            .bind(&client.redirect_uris)
+            .bind(&client.client_secret)
+            .bind(&client.role)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Replaces the secret hash of a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client to update.
+    /// * `client_secret` - The hash of the new secret.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the client does not exist.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn update_client_secret(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/update_client_secret.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(client_id)
+            .bind(client_secret)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Replaces the registered redirect URIs of a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client to update.
+    /// * `redirect_uris` - The new redirect URIs.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the client does not exist.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn update_redirect_uris(
+        &self,
+        client_id: &str,
+        redirect_uris: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/update_client_redirect_uris.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(client_id)
+            .bind(redirect_uris)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Disables or re-enables a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client to update.
+    /// * `disabled` - Whether the client is disabled.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the client does not exist.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn set_client_disabled(
+        &self,
+        client_id: &str,
+        disabled: bool,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/set_client_disabled.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(client_id)
+            .bind(disabled)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+}
@@ -0,0 +1,128 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{model::grant::Grant, traits::grant_trait::GrantRepository};
+
+/// `GrantRepo` provides an implementation of `GrantRepository` for PostgreSQL.
+/// It stores the scopes each account granted to each OAuth client in the `oauth_grant` table.
+pub struct GrantRepo {
+    /// Connection pool for interacting with the PostgreSQL database.
+    pool: Pool<Postgres>,
+}
+
+impl GrantRepo {
+    /// Creates a new `GrantRepo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The SQLx connection pool for PostgreSQL.
+    ///
+    /// # Returns
+    ///
+    /// * A new instance of `GrantRepo`.
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+impl GrantRepository for GrantRepo {
+    /// Retrieves the grant of an account to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account that gave consent.
+    /// * `client_id` - The client the consent was given to.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Grant)` - The grant if found.
+    /// * `Err(sqlx::Error)` - Returns `RowNotFound` if the account never consented, or other SQLx errors.
+    async fn get_grant(&self, account_id: i32, client_id: &str) -> Result<Grant, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_grant.sql");
+
+        let grant: Option<Grant> = sqlx::query_as(stmt)
+            .bind(account_id)
+            .bind(client_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match grant {
+            Some(grant) => Ok(grant),
+            None => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Retrieves every grant of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account whose grants to list.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Grant>)` - The grants, most recent first.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_grants(&self, account_id: i32) -> Result<Vec<Grant>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_grants.sql");
+
+        sqlx::query_as(stmt)
+            .bind(account_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Creates or replaces the grant of an account to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account that gave consent.
+    /// * `client_id` - The client the consent was given to.
+    /// * `scopes` - Every scope the client is now allowed.
+    /// * `granted_at` - Unix timestamp of the consent.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn upsert_grant(
+        &self,
+        account_id: i32,
+        client_id: &str,
+        scopes: &[String],
+        granted_at: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/upsert_grant.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(client_id)
+            .bind(scopes)
+            .bind(granted_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Deletes the grant of an account to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account that gave consent.
+    /// * `client_id` - The client to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if there was no such grant.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn delete_grant(&self, account_id: i32, client_id: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_grant.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(client_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+}
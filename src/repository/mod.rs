@@ -1,2 +1,6 @@
 pub mod account_repo;
+pub mod client_repo;
+pub mod grant_repo;
+pub mod rbac_repo;
 pub mod token_redis_repo;
+pub mod webauthn_repo;
@@ -0,0 +1,309 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    model::rbac::{NewRole, Permission, Role, RoleInheritance, RolePermission},
+    traits::rbac_trait::RbacRepository,
+};
+
+/// `RbacRepo` provides an implementation of `RbacRepository` for PostgreSQL.
+/// It manages the `roles`, `permissions`, `role_permissions`, `role_inheritance`
+/// and `account_roles` tables.
+pub struct RbacRepo {
+    /// Connection pool for interacting with the PostgreSQL database.
+    pool: Pool<Postgres>,
+}
+
+impl RbacRepo {
+    /// Creates a new `RbacRepo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The SQLx connection pool for PostgreSQL.
+    ///
+    /// # Returns
+    ///
+    /// * A new instance of `RbacRepo`.
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+impl RbacRepository for RbacRepo {
+    /// Retrieves every role with the names of its permissions.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Role>)` - The roles, ordered by name.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_roles(&self) -> Result<Vec<Role>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_roles.sql");
+
+        sqlx::query_as(stmt).fetch_all(&self.pool).await
+    }
+
+    /// Inserts a new role without permissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role to create.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
+    /// * `Err(sqlx::Error)` - A unique violation if the role exists, or other SQLx errors.
+    async fn insert_role(&self, role: &NewRole) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/insert_role.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(&role.name)
+            .bind(&role.description)
+            .bind(&role.scopes)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Deletes a role and its permission assignments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The role to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the role does not exist.
+    /// * `Err(sqlx::Error)` - A foreign key violation if accounts or clients still have the role.
+    async fn delete_role(&self, name: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_role.sql");
+
+        let x = sqlx::query(stmt).bind(name).execute(&self.pool).await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Retrieves every permission.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Permission>)` - The permissions, ordered by name.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_permissions(&self) -> Result<Vec<Permission>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_permissions.sql");
+
+        sqlx::query_as(stmt).fetch_all(&self.pool).await
+    }
+
+    /// Inserts a new permission.
+    ///
+    /// # Arguments
+    ///
+    /// * `permission` - The permission to create.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
+    /// * `Err(sqlx::Error)` - A unique violation if the permission exists, or other SQLx errors.
+    async fn insert_permission(&self, permission: &Permission) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/insert_permission.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(&permission.name)
+            .bind(&permission.method)
+            .bind(&permission.path_pattern)
+            .bind(permission.priority)
+            .bind(&permission.description)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Deletes a permission and removes it from every role.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The permission to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the permission does not exist.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn delete_permission(&self, name: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_permission.sql");
+
+        let x = sqlx::query(stmt).bind(name).execute(&self.pool).await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Grants a permission to a role. Granting it twice has no effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role receiving the permission.
+    /// * `permission` - The permission to grant.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the role already held the permission.
+    /// * `Err(sqlx::Error)` - A foreign key violation if the role or permission does not exist.
+    async fn grant_permission(&self, role: &str, permission: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/grant_role_permission.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(role)
+            .bind(permission)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Removes a permission from a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role losing the permission.
+    /// * `permission` - The permission to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the role did not hold the permission.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn revoke_permission(&self, role: &str, permission: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/revoke_role_permission.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(role)
+            .bind(permission)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Retrieves the rule of every permission held by every role.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<RolePermission>)` - One entry per role and permission.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_role_permissions(&self) -> Result<Vec<RolePermission>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_role_permissions.sql");
+
+        sqlx::query_as(stmt).fetch_all(&self.pool).await
+    }
+
+    /// Retrieves every inheritance link between roles.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<RoleInheritance>)` - One entry per role and inherited role.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_role_inheritance(&self) -> Result<Vec<RoleInheritance>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_role_inheritance.sql");
+
+        sqlx::query_as(stmt).fetch_all(&self.pool).await
+    }
+
+    /// Makes a role inherit the permissions of another. Adding it twice has no effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The inheriting role.
+    /// * `inherits` - The role whose permissions are inherited.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the role already inherited it.
+    /// * `Err(sqlx::Error)` - A foreign key violation if either role does not exist.
+    async fn insert_role_inheritance(
+        &self,
+        role: &str,
+        inherits: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/insert_role_inheritance.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(role)
+            .bind(inherits)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Stops a role from inheriting the permissions of another.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The inheriting role.
+    /// * `inherits` - The role whose permissions were inherited.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the role did not inherit it.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn delete_role_inheritance(
+        &self,
+        role: &str,
+        inherits: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_role_inheritance.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(role)
+            .bind(inherits)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Assigns a role to an account. Assigning it twice has no effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account receiving the role.
+    /// * `role` - The role to assign.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account already had the role.
+    /// * `Err(sqlx::Error)` - A foreign key violation if the account or role does not exist.
+    async fn assign_account_role(&self, account_id: i32, role: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/assign_account_role.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(role)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+
+    /// Removes a role from an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account losing the role.
+    /// * `role` - The role to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account did not have the role.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn unassign_account_role(&self, account_id: i32, role: &str) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/unassign_account_role.sql");
+
+        let x = sqlx::query(stmt)
+            .bind(account_id)
+            .bind(role)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(x.rows_affected())
+    }
+}
@@ -1,13 +1,123 @@
-use deadpool_redis::{redis::cmd, Pool};
+use deadpool_redis::{
+    redis::{cmd, pipe},
+    Pool,
+};
 use log::{error, info};
 
-use crate::{error::redis_error::RedisError, traits::redis_traits::TokenRedisRepository};
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::{
+    error::redis_error::RedisError,
+    model::{
+        mfa::MfaChallenge,
+        oauth::{AuthorizationCode, ConsentRequest, DeviceAuthorization},
+        session::{LoginSession, Session},
+        token::RefreshTokenRecord,
+        webauthn::WebAuthnChallenge,
+    },
+    traits::redis_traits::TokenRedisRepository,
+};
+
+/// Rotates a refresh token: deletes `KEYS[1]` and removes `ARGV[2]` from its family. Unless the
+/// session was revoked, marks the token used in `KEYS[2]` for `ARGV[1]` seconds, stores the new token
+/// `ARGV[3]` in the family for `ARGV[4]` seconds, records the session's access token `ARGV[5]`
+/// expiring at `ARGV[6]` and its use at `ARGV[7]`. Returns the token record, or nil if nothing was stored.
+const ROTATE_REFRESH_TOKEN_SCRIPT: &str = r#"
+local value = redis.call('GETDEL', KEYS[1])
+if not value then
+    return false
+end
+local record = cjson.decode(value)
+local family_key = 'token_family:' .. record.family_id
+local session_key = 'session:' .. record.family_id
+redis.call('SREM', family_key, ARGV[2])
+if redis.call('EXISTS', session_key) == 0 then
+    return false
+end
+redis.call('SETEX', KEYS[2], ARGV[1], record.family_id)
+redis.call('SETEX', 'refresh_token:' .. ARGV[3], ARGV[4], value)
+redis.call('SADD', family_key, ARGV[3])
+redis.call('EXPIRE', family_key, ARGV[4])
+redis.call('HSET', session_key, 'access_jti', ARGV[5], 'access_exp', ARGV[6], 'last_used_at', ARGV[7])
+redis.call('EXPIRE', session_key, ARGV[4])
+redis.call('EXPIRE', 'user_sessions:' .. record.user_id, ARGV[4])
+local client_id = redis.call('HGET', session_key, 'client_id')
+if client_id then
+    redis.call('EXPIRE', 'client_sessions:' .. client_id, ARGV[4])
+end
+return value
+"#;
+
+/// Deletes every live refresh token of the family `KEYS[1]` and the family set itself,
+/// keeping the session. Returns the number of tokens deleted.
+const DELETE_FAMILY_TOKENS_SCRIPT: &str = r#"
+local tokens = redis.call('SMEMBERS', KEYS[1])
+for _, token in ipairs(tokens) do
+    redis.call('DEL', 'refresh_token:' .. token)
+end
+redis.call('DEL', KEYS[1])
+return #tokens
+"#;
+
+/// Deletes the keys indexed by the set `KEYS[1]`: for every member and every prefix in `ARGV`,
+/// deletes `{prefix}{member}`, then deletes the set.
+const DELETE_INDEXED_KEYS_SCRIPT: &str = r#"
+local members = redis.call('SMEMBERS', KEYS[1])
+for _, member in ipairs(members) do
+    for _, prefix in ipairs(ARGV) do
+        redis.call('DEL', prefix .. member)
+    end
+end
+redis.call('DEL', KEYS[1])
+return #members
+"#;
+
+/// The `user_id`, `client_id` and `access_exp` fields of a session hash.
+type SessionOwnerFields = (Option<String>, Option<String>, Option<i64>);
 
 /// `TokenRedisRepo` is an implementation of `TokenRedisRepository`.
-/// This repository handles storing, validating, and deleting refresh tokens in Redis.
+/// This repository handles storing, rotating, and deleting refresh tokens in Redis.
 ///
 /// Each refresh token is stored with a key prefix `refresh_token:` followed by the token itself.
-/// The value is a JSON-like string containing the `user_id` and expiration time (`exp`).
+/// The value is a JSON-serialized `RefreshTokenRecord` holding the `user_id`, `family_id`
+/// and, for OAuth clients, the `client_id`.
+///
+/// Token families are tracked with two more key types:
+/// * `token_family:{family_id}` - A set of the live tokens belonging to the family.
+/// * `used_refresh_token:{token}` - Marks a token that has already been rotated,
+///   the value is the `family_id` it belonged to.
+///
+/// A token family is a login session, sessions are indexed per user with:
+/// * `session:{family_id}` - A hash with the device information and timestamps of the session.
+/// * `user_sessions:{user_id}` - A set of the session ids of the user.
+/// * `client_sessions:{client_id}` - A set of the session ids started for an OAuth client.
+///
+/// After revoking every session of a user, `tokens_valid_after:{user_id}` holds the revocation time
+/// in milliseconds so access tokens issued earlier are rejected until they expire.
+/// `session_tokens_valid_after:{family_id}` does the same for the access tokens of a revoked session.
+///
+/// Individually revoked access tokens are denied by `denied_access_token:{jti}` keys,
+/// which expire together with the token.
+///
+/// OAuth authorization codes are stored as JSON under `authorization_code:{code}` until redeemed,
+/// authorization requests waiting for the user's consent under `consent_request:{consent_id}`.
+///
+/// Logins waiting for a second factor are stored as JSON under `mfa_challenge:{token}`,
+/// the failed attempts at answering them are counted in `mfa_failures:{token}`,
+/// and `user_mfa_challenges:{user_id}` is a set of the challenge tokens of a user.
+/// Wrong second factors entered for an account, by any route, are counted in
+/// `account_mfa_failures:{user_id}` to lock the second factor of the account.
+/// WebAuthn ceremonies in progress are stored as JSON under `webauthn_challenge:{challenge}`.
+/// Password reset tokens map to the id of their account under `password_reset:{token}`,
+/// `user_password_resets:{user_id}` is a set of the reset tokens of a user.
+/// `verification_mail:{email}` is present while no other verification mail may be sent to an address.
+///
+/// Device authorizations (RFC 8628) use three key types:
+/// * `device_code:{device_code}` - The JSON-serialized `DeviceAuthorization`.
+/// * `user_code:{user_code}` - The device code a user code refers to, until the user decides.
+/// * `device_poll:{device_code}` - Present while the device must wait before polling again.
 pub struct TokenRedisRepo {
     /// Redis connection pool.
     pool: Pool,
@@ -27,11 +137,14 @@ impl TokenRedisRepo {
 /// Implements the `TokenRedisRepository` trait for `TokenRedisRepo`.
 /// This trait defines methods for managing refresh tokens in Redis.
 impl TokenRedisRepository for TokenRedisRepo {
-    /// Stores a refresh token in Redis with a specified TTL (time-to-live).
+    /// Stores a refresh token in Redis with a specified TTL (time-to-live)
+    /// and adds it to its token family.
     ///
     /// # Arguments
     ///
     /// * `user_id` - The user identifier associated with the token.
+    /// * `family_id` - The family the token belongs to.
+    /// * `client_id` - The OAuth client the family was issued to, `None` for direct logins.
     /// * `token` - The refresh token string.
     /// * `ttl` - Time-to-live in seconds.
     ///
@@ -42,6 +155,8 @@ impl TokenRedisRepository for TokenRedisRepo {
     async fn store_refresh_token(
         &self,
         user_id: &str,
+        family_id: &str,
+        client_id: Option<&str>,
         token: &str,
         ttl: i64,
     ) -> Result<(), RedisError> {
@@ -49,39 +164,182 @@ impl TokenRedisRepository for TokenRedisRepo {
         let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
         // Create the key with prefix "refresh_token:".
         let key = format!("refresh_token:{}", token);
-        // Create the value as a JSON-like string.
-        let value = format!(r#"{{"user_id": {}, "exp": {}}}"#, user_id, ttl);
+        let family_key = format!("token_family:{}", family_id);
+        // Serialize the token record.
+        let value = serde_json::to_string(&RefreshTokenRecord {
+            user_id: user_id.to_string(),
+            family_id: family_id.to_string(),
+            client_id: client_id.map(String::from),
+        })
+        .map_err(|_| RedisError::RedisError)?;
 
-        // Use the SETEX command to store the key with expiration.
-        cmd("SETEX")
-            .arg(&[key, ttl.to_string(), value])
+        // Store the token and register it in its family, the family lives as long as its newest token.
+        pipe()
+            .atomic()
+            .cmd("SETEX")
+            .arg(&key)
+            .arg(ttl)
+            .arg(value)
+            .ignore()
+            .cmd("SADD")
+            .arg(&family_key)
+            .arg(token)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&family_key)
+            .arg(ttl)
+            .ignore()
             .query_async::<()>(&mut conn)
             .await
-            .map_err(|_| RedisError::RedisError)?;
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
 
         Ok(())
     }
 
-    /// Checks if a refresh token exists (is still valid) in Redis.
+    /// Fetches the record of a live refresh token.
     ///
     /// # Arguments
     ///
-    /// * `token` - The refresh token to check.
+    /// * `token` - The refresh token to look up.
     ///
     /// # Returns
     ///
-    /// * `Ok(true)` if the token exists.
-    /// * `Ok(false)` if the token does not exist.
+    /// * `Ok(Some(RefreshTokenRecord))` if the token exists.
+    /// * `Ok(None)` if the token does not exist (expired, revoked or already used).
     /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
-    async fn is_refresh_token_valid(&self, token: &str) -> Result<bool, RedisError> {
+    async fn get_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError> {
         // Get a connection from the pool.
         let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
-        // Create the key with prefix "refresh_token:".
         let key = format!("refresh_token:{}", token);
 
-        // Check if the key exists using the EXISTS command.
-        let existed: bool = cmd("EXISTS")
-            .arg(&[key])
+        let value: Option<String> =
+            cmd("GET")
+                .arg(&key)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    RedisError::RedisError
+                })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Atomically replaces a refresh token by a new one in the same family, marking it as used,
+    /// and records the new access token and the use of the session.
+    /// Nothing is stored if the session was revoked, the consumed token is deleted all the same.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to consume.
+    /// * `used_ttl` - How long, in seconds, to remember that the token was used.
+    /// * `new_token` - The refresh token replacing it.
+    /// * `ttl` - Time-to-live of the new token and the session in seconds.
+    /// * `access_jti` - The unique identifier of the access token issued with the new token.
+    /// * `access_exp` - Unix timestamp at which that access token expires.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(RefreshTokenRecord))` if the token existed and has now been replaced.
+    /// * `Ok(None)` if the token does not exist (expired, revoked or already used) or its session was revoked.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn rotate_refresh_token(
+        &self,
+        token: &str,
+        used_ttl: i64,
+        new_token: &str,
+        ttl: i64,
+        access_jti: &str,
+        access_exp: i64,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        // Consuming the token and storing its successor happen in one script, so a replay racing
+        // the rotation always finds the used marker and a revocation racing it is never undone.
+        let value: Option<String> = cmd("EVAL")
+            .arg(ROTATE_REFRESH_TOKEN_SCRIPT)
+            .arg(2)
+            .arg(format!("refresh_token:{}", token))
+            .arg(format!("used_refresh_token:{}", token))
+            .arg(used_ttl.max(1))
+            .arg(token)
+            .arg(new_token)
+            .arg(ttl)
+            .arg(access_jti)
+            .arg(access_exp)
+            .arg(Utc::now().timestamp())
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Looks up the family of a refresh token that has already been rotated.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to check.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(String))` - The family id if the token was already used.
+    /// * `Ok(None)` if the token was never used.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_used_token_family(&self, token: &str) -> Result<Option<String>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let key = format!("used_refresh_token:{}", token);
+
+        cmd("GET")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Revokes every live refresh token of a token family and removes its session,
+    /// rejecting the access tokens issued for it until they expire.
+    ///
+    /// # Arguments
+    ///
+    /// * `family_id` - The family (session) to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the family was revoked successfully.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn revoke_token_family(&self, family_id: &str) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let family_key = format!("token_family:{}", family_id);
+        let session_key = format!("session:{}", family_id);
+
+        // Collect the tokens still alive in this family, the owner of the session
+        // and the expiry of the last access token issued for it.
+        let (tokens, (user_id, client_id, access_exp)): (Vec<String>, SessionOwnerFields) = pipe()
+            .cmd("SMEMBERS")
+            .arg(&family_key)
+            .cmd("HMGET")
+            .arg(&session_key)
+            .arg(&["user_id", "client_id", "access_exp"])
             .query_async(&mut conn)
             .await
             .map_err(|e| {
@@ -89,7 +347,51 @@ impl TokenRedisRepository for TokenRedisRepo {
                 RedisError::RedisError
             })?;
 
-        Ok(existed)
+        // Delete the tokens together with the family set and the session.
+        let mut keys: Vec<String> = tokens
+            .iter()
+            .map(|token| format!("refresh_token:{}", token))
+            .collect();
+        keys.push(family_key);
+        keys.push(session_key);
+
+        let mut pipeline = pipe();
+        pipeline.atomic().cmd("DEL").arg(&keys).ignore();
+        if let Some(user_id) = user_id {
+            pipeline
+                .cmd("SREM")
+                .arg(format!("user_sessions:{}", user_id))
+                .arg(family_id)
+                .ignore();
+        }
+        if let Some(client_id) = client_id {
+            pipeline
+                .cmd("SREM")
+                .arg(format!("client_sessions:{}", client_id))
+                .arg(family_id)
+                .ignore();
+        }
+        // Reject every access token issued for the session until the last one expires,
+        // older tokens expire before it.
+        if let Some(exp) = access_exp {
+            let remaining = exp - Utc::now().timestamp();
+            if remaining > 0 {
+                pipeline
+                    .cmd("SETEX")
+                    .arg(format!("session_tokens_valid_after:{}", family_id))
+                    .arg(remaining)
+                    .arg(Utc::now().timestamp_millis())
+                    .ignore();
+            }
+        }
+
+        pipeline
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|_| RedisError::RedisError)?;
+
+        info!("Token family revoked");
+        Ok(())
     }
 
     /// Deletes a refresh token from Redis.
@@ -108,14 +410,1427 @@ impl TokenRedisRepository for TokenRedisRepo {
         // Create the key with prefix "refresh_token:".
         let key = format!("refresh_token:{}", token);
 
-        // Use DEL command to remove the key from Redis.
-        cmd("DEL")
+        // Use GETDEL command to remove the key from Redis and learn its family.
+        let value: Option<String> = cmd("GETDEL")
             .arg(&key)
-            .query_async::<()>(&mut conn)
+            .query_async(&mut conn)
             .await
             .map_err(|_| RedisError::RedisError)?;
 
+        // Drop the token from its family set as well.
+        if let Some(record) =
+            value.and_then(|v| serde_json::from_str::<RefreshTokenRecord>(&v).ok())
+        {
+            cmd("SREM")
+                .arg(format!("token_family:{}", record.family_id))
+                .arg(token)
+                .query_async::<()>(&mut conn)
+                .await
+                .map_err(|_| RedisError::RedisError)?;
+        }
+
         info!("Refresh token deleted");
         Ok(())
     }
+
+    /// Deletes every live refresh token of a token family, keeping its session,
+    /// so new tokens can be issued in the session without leaving the old ones usable.
+    ///
+    /// # Arguments
+    ///
+    /// * `family_id` - The family (session) whose tokens to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the tokens were deleted or the family had none.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn delete_family_tokens(&self, family_id: &str) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("EVAL")
+            .arg(DELETE_FAMILY_TOKENS_SCRIPT)
+            .arg(1)
+            .arg(format!("token_family:{}", family_id))
+            .query_async::<i64>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Creates a session and adds it to the session index of its user.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session to store, its id is the id of the token family.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn create_session(&self, session: &Session, ttl: i64) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let session_key = format!("session:{}", session.id);
+        let index_key = format!("user_sessions:{}", session.user_id);
+
+        let mut fields = vec![
+            ("user_id", session.user_id.clone()),
+            ("created_at", session.created_at.to_string()),
+            ("last_used_at", session.last_used_at.to_string()),
+        ];
+        if let Some(user_agent) = &session.user_agent {
+            fields.push(("user_agent", user_agent.clone()));
+        }
+        if let Some(ip) = &session.ip {
+            fields.push(("ip", ip.clone()));
+        }
+        if let Some(client_id) = &session.client_id {
+            fields.push(("client_id", client_id.clone()));
+        }
+
+        // Store the session hash and index it under its user.
+        pipe()
+            .atomic()
+            .cmd("HSET")
+            .arg(&session_key)
+            .arg(&fields)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&session_key)
+            .arg(ttl)
+            .ignore()
+            .cmd("SADD")
+            .arg(&index_key)
+            .arg(&session.id)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        // Sessions of OAuth clients are also indexed under the client, so disabling it can revoke them.
+        if let Some(client_id) = &session.client_id {
+            let client_key = format!("client_sessions:{}", client_id);
+            pipe()
+                .atomic()
+                .cmd("SADD")
+                .arg(&client_key)
+                .arg(&session.id)
+                .ignore()
+                .cmd("EXPIRE")
+                .arg(&client_key)
+                .arg(ttl)
+                .ignore()
+                .query_async::<()>(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    RedisError::RedisError
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Records a use of a session and extends its lifetime.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The owner of the session.
+    /// * `session_id` - The session that was used.
+    /// * `last_used_at` - Unix timestamp of the use.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn touch_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        last_used_at: i64,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let session_key = format!("session:{}", session_id);
+        let index_key = format!("user_sessions:{}", user_id);
+
+        let client_id: Option<String> = cmd("HGET")
+            .arg(&session_key)
+            .arg("client_id")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        let mut pipeline = pipe();
+        pipeline
+            .atomic()
+            .cmd("HSET")
+            .arg(&session_key)
+            .arg("last_used_at")
+            .arg(last_used_at)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&session_key)
+            .arg(ttl)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl)
+            .ignore();
+        // The client's session index lives as long as its most recently used session.
+        if let Some(client_id) = client_id {
+            pipeline
+                .cmd("EXPIRE")
+                .arg(format!("client_sessions:{}", client_id))
+                .arg(ttl)
+                .ignore();
+        }
+
+        pipeline.query_async::<()>(&mut conn).await.map_err(|e| {
+            error!("{}", e);
+            RedisError::RedisError
+        })?;
+
+        Ok(())
+    }
+
+    /// Lists the live sessions of a user, pruning expired ones from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose sessions to list.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Session>)` - The sessions of the user.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let index_key = format!("user_sessions:{}", user_id);
+
+        let session_ids: Vec<String> = cmd("SMEMBERS")
+            .arg(&index_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            let fields: HashMap<String, String> = cmd("HGETALL")
+                .arg(format!("session:{}", session_id))
+                .query_async(&mut conn)
+                .await
+                .map_err(|_| RedisError::RedisError)?;
+
+            // The session hash expired, drop it from the index.
+            if fields.is_empty() {
+                cmd("SREM")
+                    .arg(&index_key)
+                    .arg(&session_id)
+                    .query_async::<()>(&mut conn)
+                    .await
+                    .map_err(|_| RedisError::RedisError)?;
+                continue;
+            }
+
+            let timestamp = |name: &str| {
+                fields
+                    .get(name)
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .unwrap_or_default()
+            };
+
+            sessions.push(Session {
+                user_id: user_id.to_string(),
+                user_agent: fields.get("user_agent").cloned(),
+                ip: fields.get("ip").cloned(),
+                created_at: timestamp("created_at"),
+                last_used_at: timestamp("last_used_at"),
+                client_id: fields.get("client_id").cloned(),
+                current: false,
+                id: session_id,
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    /// Revokes every session, and so every refresh token, of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose sessions to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the sessions were revoked successfully.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn revoke_user_sessions(&self, user_id: &str) -> Result<(), RedisError> {
+        let session_ids: Vec<String> = {
+            // Get a connection from the pool.
+            let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+            cmd("SMEMBERS")
+                .arg(format!("user_sessions:{}", user_id))
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    RedisError::RedisError
+                })?
+        };
+
+        for session_id in session_ids {
+            self.revoke_token_family(&session_id).await?;
+        }
+
+        info!("All sessions of user {} revoked", user_id);
+        Ok(())
+    }
+
+    /// Revokes every session started for an OAuth client, with their refresh token families.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client whose sessions to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn revoke_client_sessions(&self, client_id: &str) -> Result<(), RedisError> {
+        let session_ids: Vec<String> = {
+            // Get a connection from the pool.
+            let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+            cmd("SMEMBERS")
+                .arg(format!("client_sessions:{}", client_id))
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    RedisError::RedisError
+                })?
+        };
+
+        for session_id in session_ids {
+            self.revoke_token_family(&session_id).await?;
+        }
+
+        info!("All sessions of client {} revoked", client_id);
+        Ok(())
+    }
+
+    /// Sets the time before which access tokens of a user are no longer accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose tokens to invalidate.
+    /// * `timestamp` - Unix timestamp in milliseconds, tokens issued before it are rejected.
+    /// * `ttl` - Time-to-live in seconds, the access token lifetime is enough.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn set_tokens_valid_after(
+        &self,
+        user_id: &str,
+        timestamp: i64,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("SETEX")
+            .arg(format!("tokens_valid_after:{}", user_id))
+            .arg(ttl)
+            .arg(timestamp)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|_| RedisError::RedisError)?;
+
+        Ok(())
+    }
+
+    /// Gets the time before which access tokens of a user are no longer accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user to check.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(i64))` - The revocation timestamp in milliseconds if the user's sessions were recently revoked.
+    /// * `Ok(None)` if there is no active revocation.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_tokens_valid_after(&self, user_id: &str) -> Result<Option<i64>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("GET")
+            .arg(format!("tokens_valid_after:{}", user_id))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Gets the time before which access tokens of a session are no longer accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session to check.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(i64))` - The revocation timestamp in milliseconds if the session was recently revoked.
+    /// * `Ok(None)` if there is no active revocation.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_session_tokens_valid_after(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<i64>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("GET")
+            .arg(format!("session_tokens_valid_after:{}", session_id))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Records the latest access token issued for a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session the token was issued for.
+    /// * `jti` - The unique identifier of the access token.
+    /// * `exp` - Unix timestamp at which the access token expires.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn set_session_access_token(
+        &self,
+        session_id: &str,
+        jti: &str,
+        exp: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("HSET")
+            .arg(format!("session:{}", session_id))
+            .arg(&[
+                ("access_jti", jti.to_string()),
+                ("access_exp", exp.to_string()),
+            ])
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Adds an access token to the denylist.
+    ///
+    /// # Arguments
+    ///
+    /// * `jti` - The unique identifier of the access token.
+    /// * `ttl` - The remaining lifetime of the token in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success, an already expired token is not stored.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn deny_access_token(&self, jti: &str, ttl: i64) -> Result<(), RedisError> {
+        // An expired token is rejected anyway.
+        if ttl <= 0 {
+            return Ok(());
+        }
+
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("SETEX")
+            .arg(format!("denied_access_token:{}", jti))
+            .arg(ttl)
+            .arg(1)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|_| RedisError::RedisError)?;
+
+        info!("Access token denied");
+        Ok(())
+    }
+
+    /// Checks if an access token is on the denylist.
+    ///
+    /// # Arguments
+    ///
+    /// * `jti` - The unique identifier of the access token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the token was revoked.
+    /// * `Ok(false)` otherwise.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn is_access_token_denied(&self, jti: &str) -> Result<bool, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("EXISTS")
+            .arg(format!("denied_access_token:{}", jti))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Stores an OAuth authorization code until it is redeemed or expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The authorization code handed to the client.
+    /// * `authorization` - What the code grants.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_authorization_code(
+        &self,
+        code: &str,
+        authorization: &AuthorizationCode,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(authorization).map_err(|_| RedisError::RedisError)?;
+
+        cmd("SETEX")
+            .arg(format!("authorization_code:{}", code))
+            .arg(ttl)
+            .arg(value)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Atomically removes an authorization code, so it can only be redeemed once.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The authorization code to redeem.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(AuthorizationCode))` if the code existed.
+    /// * `Ok(None)` if the code is unknown, expired or already redeemed.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GETDEL")
+            .arg(format!("authorization_code:{}", code))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Stores a new device authorization and indexes it by its user code, both expiring after `ttl`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_code` - The device code polled by the device.
+    /// * `authorization` - The pending authorization.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_device_authorization(
+        &self,
+        device_code: &str,
+        authorization: &DeviceAuthorization,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(authorization).map_err(|_| RedisError::RedisError)?;
+
+        pipe()
+            .atomic()
+            .cmd("SETEX")
+            .arg(format!("device_code:{}", device_code))
+            .arg(ttl)
+            .arg(value)
+            .ignore()
+            .cmd("SETEX")
+            .arg(format!("user_code:{}", authorization.user_code))
+            .arg(ttl)
+            .arg(device_code)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Looks up the device code a user code was issued with.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_code` - The normalized user code entered by the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(String))` - The device code, while the authorization is undecided.
+    /// * `Ok(None)` if the user code is unknown, expired or already used.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn find_device_code(&self, user_code: &str) -> Result<Option<String>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("GET")
+            .arg(format!("user_code:{}", user_code))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Reads a device authorization.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_code` - The device code of the authorization.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DeviceAuthorization))` if the authorization exists.
+    /// * `Ok(None)` if the device code is unknown, expired or already redeemed.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_device_authorization(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GET")
+            .arg(format!("device_code:{}", device_code))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Records the user's decision on a device authorization, keeping its expiry,
+    /// and retires its user code so it cannot be entered again.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_code` - The device code of the authorization.
+    /// * `authorization` - The decided authorization.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the authorization was updated.
+    /// * `Ok(false)` if it expired in the meantime.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn update_device_authorization(
+        &self,
+        device_code: &str,
+        authorization: &DeviceAuthorization,
+    ) -> Result<bool, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(authorization).map_err(|_| RedisError::RedisError)?;
+
+        let (updated,): (Option<String>,) = pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(format!("device_code:{}", device_code))
+            .arg(value)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .cmd("DEL")
+            .arg(format!("user_code:{}", authorization.user_code))
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(updated.is_some())
+    }
+
+    /// Atomically removes a device authorization, so its tokens are only issued once.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_code` - The device code to redeem.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DeviceAuthorization))` if the authorization existed.
+    /// * `Ok(None)` if the device code is unknown, expired or already redeemed.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_device_authorization(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GETDEL")
+            .arg(format!("device_code:{}", device_code))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Records a poll of the token endpoint for a device code.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_code` - The device code being polled.
+    /// * `interval` - Minimum number of seconds between two polls.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the device waited long enough since its last poll.
+    /// * `Ok(false)` if it polls too fast.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn throttle_device_poll(
+        &self,
+        device_code: &str,
+        interval: i64,
+    ) -> Result<bool, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let set: Option<String> = cmd("SET")
+            .arg(format!("device_poll:{}", device_code))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(interval)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(set.is_some())
+    }
+
+    /// Stores an authorization request until the user consents to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `consent_id` - The id of the consent screen.
+    /// * `request` - The authorization waiting for consent.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_consent_request(
+        &self,
+        consent_id: &str,
+        request: &ConsentRequest,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(request).map_err(|_| RedisError::RedisError)?;
+
+        cmd("SETEX")
+            .arg(format!("consent_request:{}", consent_id))
+            .arg(ttl)
+            .arg(value)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Reads an authorization request waiting for consent.
+    ///
+    /// # Arguments
+    ///
+    /// * `consent_id` - The id of the consent screen.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(ConsentRequest))` if the request exists.
+    /// * `Ok(None)` if it is unknown, expired or already decided.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_consent_request(
+        &self,
+        consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GET")
+            .arg(format!("consent_request:{}", consent_id))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Atomically removes an authorization request waiting for consent, so it is decided once.
+    ///
+    /// # Arguments
+    ///
+    /// * `consent_id` - The id of the consent screen.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(ConsentRequest))` if the request existed.
+    /// * `Ok(None)` if it is unknown, expired or already decided.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_consent_request(
+        &self,
+        consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GETDEL")
+            .arg(format!("consent_request:{}", consent_id))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Stores a login waiting for its second factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The MFA challenge token handed to the client.
+    /// * `challenge` - The user who passed the first factor.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_mfa_challenge(
+        &self,
+        token: &str,
+        challenge: &MfaChallenge,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(challenge).map_err(|_| RedisError::RedisError)?;
+        let index_key = format!("user_mfa_challenges:{}", challenge.user_id);
+
+        // Index the challenge under its user, so resetting the password can revoke it.
+        pipe()
+            .atomic()
+            .cmd("SETEX")
+            .arg(format!("mfa_challenge:{}", token))
+            .arg(ttl)
+            .arg(value)
+            .ignore()
+            .cmd("SADD")
+            .arg(&index_key)
+            .arg(token)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Reads a login waiting for its second factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The MFA challenge token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(MfaChallenge))` if the challenge exists.
+    /// * `Ok(None)` if it is unknown, expired or already answered.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_mfa_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GET")
+            .arg(format!("mfa_challenge:{}", token))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Atomically removes a login waiting for its second factor, so it is answered once.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The MFA challenge token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(MfaChallenge))` if the challenge existed.
+    /// * `Ok(None)` if it is unknown, expired or already answered.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_mfa_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GETDEL")
+            .arg(format!("mfa_challenge:{}", token))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Revokes every pending MFA challenge of a user, with their failure counters.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose challenges to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the challenges were revoked or there were none.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn revoke_mfa_challenges(&self, user_id: &str) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("EVAL")
+            .arg(DELETE_INDEXED_KEYS_SCRIPT)
+            .arg(1)
+            .arg(format!("user_mfa_challenges:{}", user_id))
+            .arg(&["mfa_challenge:", "mfa_failures:"])
+            .query_async::<i64>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Counts a failed attempt at answering an MFA challenge.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The MFA challenge token.
+    /// * `ttl` - Time-to-live of the counter in seconds, at least the lifetime of the challenge.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The number of failed attempts so far.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn record_mfa_failure(&self, token: &str, ttl: i64) -> Result<i64, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let key = format!("mfa_failures:{}", token);
+
+        let (failures,): (i64,) = pipe()
+            .atomic()
+            .cmd("INCR")
+            .arg(&key)
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(ttl)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(failures)
+    }
+
+    /// Counts a wrong second factor entered for an account.
+    /// Every failure restarts the window, the counter expires `ttl` seconds after the last one.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The account the code was entered for.
+    /// * `ttl` - Time-to-live of the counter in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The number of failures within the window.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn record_account_mfa_failure(&self, user_id: &str, ttl: i64) -> Result<i64, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let key = format!("account_mfa_failures:{}", user_id);
+
+        let (failures,): (i64,) = pipe()
+            .atomic()
+            .cmd("INCR")
+            .arg(&key)
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(ttl)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(failures)
+    }
+
+    /// Returns the number of wrong second factors recently entered for an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The account to look up.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The number of failures within the window, 0 if there are none.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_account_mfa_failures(&self, user_id: &str) -> Result<i64, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let failures: Option<i64> = cmd("GET")
+            .arg(format!("account_mfa_failures:{}", user_id))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(failures.unwrap_or(0))
+    }
+
+    /// Resets the count of wrong second factors of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The account to reset.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the counter was removed or did not exist.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn clear_account_mfa_failures(&self, user_id: &str) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("DEL")
+            .arg(format!("account_mfa_failures:{}", user_id))
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Stores the challenge of a WebAuthn ceremony until the ceremony finishes.
+    ///
+    /// # Arguments
+    ///
+    /// * `challenge` - The base64url encoded challenge handed to the authenticator.
+    /// * `record` - The ceremony the challenge belongs to.
+    /// * `ttl` - Time-to-live in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_webauthn_challenge(
+        &self,
+        challenge: &str,
+        record: &WebAuthnChallenge,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(record).map_err(|_| RedisError::RedisError)?;
+
+        cmd("SETEX")
+            .arg(format!("webauthn_challenge:{}", challenge))
+            .arg(ttl)
+            .arg(value)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Atomically removes the challenge of a WebAuthn ceremony, so it is answered once.
+    ///
+    /// # Arguments
+    ///
+    /// * `challenge` - The base64url encoded challenge.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(WebAuthnChallenge))` if the challenge existed.
+    /// * `Ok(None)` if it is unknown, expired or already answered.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_webauthn_challenge(
+        &self,
+        challenge: &str,
+    ) -> Result<Option<WebAuthnChallenge>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GETDEL")
+            .arg(format!("webauthn_challenge:{}", challenge))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    /// Stores a password reset token with an expiration time,
+    /// replacing the tokens sent to the user before.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The reset token sent to the user.
+    /// * `user_id` - The account whose password the token resets.
+    /// * `ttl` - Time-to-live of the token in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_password_reset(
+        &self,
+        token: &str,
+        user_id: &str,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let index_key = format!("user_password_resets:{}", user_id);
+
+        // Only the newest link works, older mails may have been read by someone else.
+        pipe()
+            .atomic()
+            .cmd("EVAL")
+            .arg(DELETE_INDEXED_KEYS_SCRIPT)
+            .arg(1)
+            .arg(&index_key)
+            .arg("password_reset:")
+            .ignore()
+            .cmd("SETEX")
+            .arg(format!("password_reset:{}", token))
+            .arg(ttl)
+            .arg(user_id)
+            .ignore()
+            .cmd("SADD")
+            .arg(&index_key)
+            .arg(token)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Records a verification mail sent to an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The address, compared case-insensitively.
+    /// * `interval` - Minimum number of seconds between two mails to the address.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if no mail was sent to the address within the interval.
+    /// * `Ok(false)` if one was.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn throttle_verification_mail(
+        &self,
+        email: &str,
+        interval: i64,
+    ) -> Result<bool, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let set: Option<String> = cmd("SET")
+            .arg(format!("verification_mail:{}", email.to_lowercase()))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(interval)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(set.is_some())
+    }
+
+    /// Atomically removes a password reset token, so it is used once.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The reset token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(String))` - The account id, if the token existed.
+    /// * `Ok(None)` if it is unknown, expired or already used.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn consume_password_reset(&self, token: &str) -> Result<Option<String>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("GETDEL")
+            .arg(format!("password_reset:{}", token))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })
+    }
+
+    /// Deletes every password reset token of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose reset tokens to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the tokens were deleted or there were none.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn revoke_password_resets(&self, user_id: &str) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        cmd("EVAL")
+            .arg(DELETE_INDEXED_KEYS_SCRIPT)
+            .arg(1)
+            .arg(format!("user_password_resets:{}", user_id))
+            .arg("password_reset:")
+            .query_async::<i64>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Stores the browser login of a direct session.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The login session token sent as a cookie.
+    /// * `login` - The session and authentication the token stands for.
+    /// * `ttl` - Time-to-live in seconds, the lifetime of the session.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the login session was stored successfully.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn store_login_session(
+        &self,
+        token: &str,
+        login: &LoginSession,
+        ttl: i64,
+    ) -> Result<(), RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+        let value = serde_json::to_string(login).map_err(|_| RedisError::RedisError)?;
+
+        cmd("SETEX")
+            .arg(format!("login_session:{}", token))
+            .arg(ttl)
+            .arg(value)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        Ok(())
+    }
+
+    /// Reads the browser login a login session token stands for.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The login session token sent as a cookie.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(LoginSession))` - The login, if the token exists.
+    /// * `Ok(None)` if it is unknown or expired.
+    /// * `Err(RedisError)` if there's an error with the connection pool or Redis command.
+    async fn get_login_session(&self, token: &str) -> Result<Option<LoginSession>, RedisError> {
+        // Get a connection from the pool.
+        let mut conn = self.pool.get().await.map_err(|_| RedisError::PoolError)?;
+
+        let value: Option<String> = cmd("GET")
+            .arg(format!("login_session:{}", token))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                RedisError::RedisError
+            })?;
+
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
 }
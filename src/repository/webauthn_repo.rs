@@ -0,0 +1,156 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{model::webauthn::WebAuthnCredential, traits::webauthn_trait::WebAuthnRepository};
+
+/// `WebAuthnRepo` provides an implementation of `WebAuthnRepository` for PostgreSQL.
+/// It stores the passkeys and security keys of each account in the `webauthn_credential` table.
+pub struct WebAuthnRepo {
+    /// Connection pool for interacting with the PostgreSQL database.
+    pool: Pool<Postgres>,
+}
+
+impl WebAuthnRepo {
+    /// Creates a new `WebAuthnRepo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The SQLx connection pool for PostgreSQL.
+    ///
+    /// # Returns
+    ///
+    /// * A new instance of `WebAuthnRepo`.
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+impl WebAuthnRepository for WebAuthnRepo {
+    /// Stores a newly registered credential.
+    ///
+    /// # Arguments
+    ///
+    /// * `credential` - The credential to store.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected (should be 1 on success).
+    /// * `Err(sqlx::Error)` - A unique violation if the credential is already registered, or other SQLx errors.
+    async fn insert_credential(&self, credential: &WebAuthnCredential) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/insert_webauthn_credential.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(&credential.credential_id)
+            .bind(credential.account_id)
+            .bind(&credential.public_key)
+            .bind(credential.sign_count)
+            .bind(&credential.name)
+            .bind(credential.created_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Retrieves a credential by its id.
+    ///
+    /// # Arguments
+    ///
+    /// * `credential_id` - The base64url encoded credential id.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(WebAuthnCredential)` - The credential if found.
+    /// * `Err(sqlx::Error)` - Returns `RowNotFound` if the credential is unknown, or other SQLx errors.
+    async fn get_credential(&self, credential_id: &str) -> Result<WebAuthnCredential, sqlx::Error> {
+        let stmt = include_str!("../../sql/get_webauthn_credential.sql");
+
+        let credential: Option<WebAuthnCredential> = sqlx::query_as(stmt)
+            .bind(credential_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match credential {
+            Some(credential) => Ok(credential),
+            None => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Retrieves every credential of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account whose credentials to list.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<WebAuthnCredential>)` - The credentials, most recent first.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn list_credentials(
+        &self,
+        account_id: i32,
+    ) -> Result<Vec<WebAuthnCredential>, sqlx::Error> {
+        let stmt = include_str!("../../sql/list_webauthn_credentials.sql");
+
+        sqlx::query_as(stmt)
+            .bind(account_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Records a successful assertion and the signature counter it reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `credential_id` - The credential used.
+    /// * `sign_count` - The signature counter of the assertion.
+    /// * `used_at` - Unix timestamp of the assertion.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the counter did not increase.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn update_sign_count(
+        &self,
+        credential_id: &str,
+        sign_count: i64,
+        used_at: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/update_webauthn_sign_count.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(credential_id)
+            .bind(sign_count)
+            .bind(used_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes a credential of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `credential_id` - The credential to remove.
+    /// * `account_id` - The account owning the credential.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of rows affected, 0 if the account has no such credential.
+    /// * `Err(sqlx::Error)` - If an error occurs during database operation.
+    async fn delete_credential(
+        &self,
+        credential_id: &str,
+        account_id: i32,
+    ) -> Result<u64, sqlx::Error> {
+        let stmt = include_str!("../../sql/delete_webauthn_credential.sql");
+
+        let result = sqlx::query(stmt)
+            .bind(credential_id)
+            .bind(account_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
-use log::error;
+use log::{error, info};
 
 use crate::{
-    error::service_error::ServiceError, model::account::Account,
+    error::service_error::ServiceError,
+    model::account::{Account, AccountUpdate},
     traits::account_trait::AccountRepository,
 };
 
@@ -41,9 +42,7 @@ impl<T: AccountRepository> AccountService<T> {
     ///   the account is not found, or a database error occurs.
     pub async fn get_account_info(&self, id: &str) -> Result<Account, ServiceError> {
         // Parse the provided string ID into an i32.
-        let id = id
-            .parse::<i32>()
-            .map_err(|e| ServiceError::InvalidIdFormat(e))?;
+        let id = id.parse::<i32>().map_err(ServiceError::InvalidIdFormat)?;
 
         // Query the repository for the account information.
         let account = self.account_repo.get_account_by_id(id).await;
@@ -63,4 +62,90 @@ impl<T: AccountRepository> AccountService<T> {
             }
         }
     }
+
+    /// Changes the username and email address of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A string slice representing the account ID.
+    /// * `update` - The fields to change.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Option<String>)` - The new email address if it changed and must be verified.
+    /// * `Err(ServiceError)` - `BadRequest` if a field is invalid, `Conflict` if the username
+    ///   or address belongs to another account, `NotFound` if the account does not exist.
+    pub async fn update_account(
+        &self,
+        id: &str,
+        update: AccountUpdate,
+    ) -> Result<Option<String>, ServiceError> {
+        let id = id.parse::<i32>().map_err(ServiceError::InvalidIdFormat)?;
+
+        if update
+            .username
+            .as_deref()
+            .is_some_and(|u| u.trim().is_empty())
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "username must not be empty",
+            )));
+        }
+        if update
+            .email
+            .as_deref()
+            .is_some_and(|e| e.parse::<lettre::Address>().is_err())
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "Invalid email address",
+            )));
+        }
+
+        let current = self.get_account_info(&id.to_string()).await?;
+
+        // Usernames and addresses may not be taken from another account.
+        if let Some(username) = &update.username {
+            match self.account_repo.get_account_by_username(username).await {
+                Ok(account) if account.id != id => {
+                    return Err(ServiceError::Conflict(String::from("Username existed")))
+                }
+                Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+                Err(e) => return Err(ServiceError::DatabaseError(e)),
+            }
+        }
+        if let Some(email) = &update.email {
+            match self.account_repo.get_account_by_email(email).await {
+                Ok(account) if account.id != id => {
+                    return Err(ServiceError::Conflict(String::from("Email existed")))
+                }
+                Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+                Err(e) => return Err(ServiceError::DatabaseError(e)),
+            }
+        }
+
+        let updated = self
+            .account_repo
+            .update_account(id, update.username.as_deref(), update.email.as_deref())
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    ServiceError::Conflict(String::from("Email existed"))
+                }
+                _ => {
+                    error!("{}", e);
+                    ServiceError::DatabaseError(e)
+                }
+            })?;
+        if updated == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        info!("Account {} updated", id);
+        Ok(update.email.filter(|email| {
+            current
+                .email
+                .as_deref()
+                .is_none_or(|current| !current.eq_ignore_ascii_case(email))
+        }))
+    }
 }
@@ -1,17 +1,42 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use log::error;
+use uuid::Uuid;
 
 use crate::{
+    config::{mfa, policy, scope},
     error::service_error::ServiceError,
     model::{
-        account::{Account, LoginInfo},
+        account::{Account, LoginInfo, Reauthenticate, Registration},
+        mfa::{LoginOutcome, MfaChallenge, MfaLogin, MfaRequired, RecoveryCodes, TotpEnrollment},
+        session::{DeviceInfo, LoginSession, Session},
         token::Token,
     },
     traits::{account_trait::AccountRepository, redis_traits::TokenRedisRepository},
-    utils,
+    utils::{
+        self,
+        jwt::{Authentication, Claims},
+        totp,
+    },
 };
 
+/// Lifetime of an MFA challenge token in seconds.
+const MFA_CHALLENGE_TTL: i64 = 300;
+
+/// Wrong codes accepted for one MFA challenge before it is revoked.
+const MAX_MFA_ATTEMPTS: i64 = 5;
+
+/// Wrong second factors accepted for one account, across challenges and routes,
+/// before its second factor is locked.
+const MAX_ACCOUNT_MFA_FAILURES: i64 = 10;
+
+/// Seconds the second factor of an account stays locked after its last wrong code.
+const MFA_LOCKOUT_TTL: i64 = 900;
+
+/// Number of recovery codes handed out at once.
+const RECOVERY_CODE_COUNT: usize = 10;
+
 /// Service responsible for handling user authentication and account management.
 /// It interacts with both the PostgreSQL repository (for account data) and the Redis repository (for refresh token storage).
 pub struct AuthService<R: AccountRepository, T: TokenRedisRepository> {
@@ -39,21 +64,30 @@ impl<R: AccountRepository, T: TokenRedisRepository> AuthService<R, T> {
         }
     }
 
-    /// Adds a new account to the system after checking that the username does not already exist.
+    /// Adds a new account to the system after checking that the username and the email address
+    /// are not already taken. The account starts with an unverified email address.
     ///
     /// # Arguments
     ///
-    /// * `login_info` - The username and password provided by the user during registration.
+    /// * `registration` - The username, password and email address provided by the user during registration.
     ///
     /// # Returns
     ///
     /// * `Ok(u64)` - Number of rows affected (should be 1 if successful).
-    /// * `Err(actix_web::error::Error)` - Actix error if username already exists or other issues occur.
-    pub async fn add_account(&self, login_info: LoginInfo) -> Result<u64, actix_web::error::Error> {
+    /// * `Err(actix_web::error::Error)` - Actix error if username or email already exists or other issues occur.
+    pub async fn add_account(
+        &self,
+        registration: Registration,
+    ) -> Result<u64, actix_web::error::Error> {
+        // Reject addresses mail cannot be sent to.
+        if registration.email.parse::<lettre::Address>().is_err() {
+            return Err(actix_web::error::ErrorBadRequest("Invalid email address"));
+        }
+
         // Check if the username already exists in the database.
         if self
             .pg_repo
-            .is_account_exist(&login_info.username)
+            .is_account_exist(&registration.username)
             .await
             .is_ok()
         {
@@ -61,16 +95,27 @@ impl<R: AccountRepository, T: TokenRedisRepository> AuthService<R, T> {
             return Err(actix_web::error::ErrorConflict("Username existed"));
         }
 
+        // Check if the email address already belongs to an account.
+        if self
+            .pg_repo
+            .get_account_by_email(&registration.email)
+            .await
+            .is_ok()
+        {
+            error!("Email existed");
+            return Err(actix_web::error::ErrorConflict("Email existed"));
+        }
+
         // Hash the provided password.
-        let password_hash =
-            utils::password::Hasher::hash_password(&login_info.password).map_err(|e| {
+        let password_hash = utils::password::Hasher::hash_password(&registration.password)
+            .map_err(|e| {
                 error!("Hashing error: {}", e);
                 actix_web::error::ErrorInternalServerError(e)
             })?;
 
         // Insert the new account into the database.
         self.pg_repo
-            .insert_account(login_info.username, password_hash)
+            .insert_account(registration.username, password_hash, registration.email)
             .await
             .map_err(|e| {
                 error!("Create account error: {}", e);
@@ -79,17 +124,24 @@ impl<R: AccountRepository, T: TokenRedisRepository> AuthService<R, T> {
     }
 
     /// Verifies the provided username and password, and if successful, generates access and refresh tokens.
-    /// The refresh token is stored in Redis.
+    /// A new session is started for the device and the refresh token is stored in Redis.
+    /// Accounts with a confirmed TOTP secret receive an MFA challenge token instead,
+    /// the session starts once `verify_mfa` accepts a code.
     ///
     /// # Arguments
     ///
     /// * `login_info` - The username and password provided by the user during login.
+    /// * `device` - The user agent and IP address the login comes from.
     ///
     /// # Returns
     ///
-    /// * `Ok(Token)` - Struct containing the generated access and refresh tokens.
+    /// * `Ok(LoginOutcome)` - The generated tokens, or the MFA challenge to answer.
     /// * `Err(ServiceError)` - Actix error if the account is not found or the credentials are invalid.
-    pub async fn verify_account(&self, login_info: LoginInfo) -> Result<Token, ServiceError> {
+    pub async fn verify_account(
+        &self,
+        login_info: LoginInfo,
+        device: DeviceInfo,
+    ) -> Result<LoginOutcome, ServiceError> {
         // Fetch authentication information from the database.
         let auth_info: Account = match self
             .pg_repo
@@ -107,61 +159,956 @@ impl<R: AccountRepository, T: TokenRedisRepository> AuthService<R, T> {
         // Verify the provided password against the stored hash.
         if utils::password::Hasher::verify_password(
             &login_info.password,
-            &auth_info.password.unwrap(), // Assume password is always Some.
+            auth_info.password.as_deref().unwrap(), // Assume password is always Some.
         )
         .is_ok()
         {
-            // Generate new access and refresh tokens upon successful verification.
-            let access_token = utils::jwt::JwtUtils::generate_access_token(
-                &auth_info.id.to_string(),
-                &auth_info.role,
+            check_login_policy(&auth_info)?;
+
+            // Enrolled accounts must answer a second factor before the session starts.
+            match self.pg_repo.get_totp(auth_info.id).await {
+                Ok(totp) if totp.confirmed => {
+                    return self
+                        .mfa_challenge(auth_info.id.to_string(), auth_info.roles)
+                        .await
+                        .map(LoginOutcome::MfaRequired);
+                }
+                Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+                Err(e) => return Err(ServiceError::DatabaseError(e)),
+            }
+
+            // Direct logins hold every scope of their roles.
+            let scope = scope::default_scope(&self.roles_scopes(&auth_info.roles).await?);
+            return self
+                .start_session(
+                    &auth_info.id.to_string(),
+                    &auth_info.roles,
+                    device,
+                    None,
+                    scope.as_deref(),
+                    &Authentication::now(&["pwd"]),
+                )
+                .await
+                .map(LoginOutcome::Token);
+        }
+
+        // Return an error if password verification fails.
+        Err(ServiceError::UnAuthorizedError)
+    }
+
+    /// Stores a login that passed its first factor and hands out the token answering it.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The id of the account.
+    /// * `roles` - The roles of the account.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(MfaRequired)` - The MFA challenge token and its lifetime.
+    /// * `Err(ServiceError)` - If Redis storage fails.
+    async fn mfa_challenge(
+        &self,
+        user_id: String,
+        roles: Vec<String>,
+    ) -> Result<MfaRequired, ServiceError> {
+        let mfa_token = utils::random::random_token(32);
+
+        self.redis_repo
+            .store_mfa_challenge(
+                &mfa_token,
+                &MfaChallenge { user_id, roles },
+                MFA_CHALLENGE_TTL,
             )
-            .map_err(|e| ServiceError::JwtError(e))?;
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        Ok(MfaRequired {
+            mfa_required: true,
+            mfa_token,
+            expires_in: MFA_CHALLENGE_TTL,
+        })
+    }
+
+    /// Completes a login by answering its MFA challenge with a TOTP code or a recovery code.
+    /// The challenge is revoked after too many wrong codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `mfa_login` - The MFA challenge token and the code.
+    /// * `device` - The user agent and IP address the login comes from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Token)` - Struct containing the generated access and refresh tokens.
+    /// * `Err(ServiceError)` - `UnAuthorizedError` if the challenge is unknown or the code is wrong,
+    ///   `Forbidden` if the second factor of the account is locked.
+    pub async fn verify_mfa(
+        &self,
+        mfa_login: MfaLogin,
+        device: DeviceInfo,
+    ) -> Result<Token, ServiceError> {
+        let challenge = self
+            .redis_repo
+            .get_mfa_challenge(&mfa_login.mfa_token)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .ok_or(ServiceError::UnAuthorizedError)?;
+
+        let account_id = parse_account_id(&challenge.user_id)?;
+        if !self
+            .check_second_factor(account_id, &mfa_login.code, true)
+            .await?
+        {
+            let failures = self
+                .redis_repo
+                .record_mfa_failure(&mfa_login.mfa_token, MFA_CHALLENGE_TTL)
+                .await
+                .map_err(|e| {
+                    error!("Redis error: {}", e);
+                    ServiceError::RedisError
+                })?;
+            if failures >= MAX_MFA_ATTEMPTS {
+                // Too many wrong codes, the password must be entered again.
+                self.redis_repo
+                    .consume_mfa_challenge(&mfa_login.mfa_token)
+                    .await
+                    .map_err(|e| {
+                        error!("Redis error: {}", e);
+                        ServiceError::RedisError
+                    })?;
+            }
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        // Consume the challenge so a second request with the same token cannot start another session.
+        let challenge = self
+            .redis_repo
+            .consume_mfa_challenge(&mfa_login.mfa_token)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .ok_or(ServiceError::UnAuthorizedError)?;
+
+        let scope = scope::default_scope(&self.roles_scopes(&challenge.roles).await?);
+        self.start_session(
+            &challenge.user_id,
+            &challenge.roles,
+            device,
+            None,
+            scope.as_deref(),
+            &Authentication::now(&["pwd", "otp"]),
+        )
+        .await
+    }
+
+    /// Starts TOTP enrollment by generating a new secret.
+    /// The secret is only enforced at login once `confirm_totp` accepted a code.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user enrolling.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TotpEnrollment)` - The secret and the `otpauth://` URI to enroll an authenticator with.
+    /// * `Err(ServiceError)` - `Conflict` if TOTP is already enabled, or a database error.
+    pub async fn enroll_totp(&self, user_id: &str) -> Result<TotpEnrollment, ServiceError> {
+        let account_id = parse_account_id(user_id)?;
+        let account = self
+            .pg_repo
+            .get_account_by_id(account_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => ServiceError::NotFound,
+                e => ServiceError::DatabaseError(e),
+            })?;
 
-            let refresh_token = utils::jwt::JwtUtils::generate_refresh_token(
-                &auth_info.id.to_string(),
-                &auth_info.role,
+        let secret = totp::generate_secret();
+        let updated = self
+            .pg_repo
+            .upsert_totp(account_id, &secret)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if updated == 0 {
+            return Err(ServiceError::Conflict(String::from(
+                "TOTP is already enabled",
+            )));
+        }
+
+        let otpauth_uri = totp::otpauth_uri(&mfa::totp_issuer(), &account.username, &secret);
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri,
+        })
+    }
+
+    /// Confirms TOTP enrollment with a code from the authenticator and hands out recovery codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user enrolling.
+    /// * `code` - A code generated by the authenticator.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RecoveryCodes)` - The recovery codes, shown to the user once.
+    /// * `Err(ServiceError)` - `BadRequest` if enrollment was not started or the code is wrong,
+    ///   `Conflict` if TOTP is already enabled, or a database error.
+    pub async fn confirm_totp(
+        &self,
+        user_id: &str,
+        code: &str,
+    ) -> Result<RecoveryCodes, ServiceError> {
+        let account_id = parse_account_id(user_id)?;
+        let secret = match self.pg_repo.get_totp(account_id).await {
+            Ok(secret) => secret,
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(ServiceError::BadRequest(String::from(
+                    "TOTP enrollment was not started",
+                )));
+            }
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+        if secret.confirmed {
+            return Err(ServiceError::Conflict(String::from(
+                "TOTP is already enabled",
+            )));
+        }
+
+        let step = totp::verify(&secret.secret, code, Utc::now().timestamp())
+            .ok_or_else(|| ServiceError::BadRequest(String::from("Invalid code")))?;
+
+        let (recovery_codes, hashes) = new_recovery_codes();
+        let stored = self
+            .pg_repo
+            .confirm_totp(account_id, step as i64, &hashes)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if stored == 0 {
+            // Confirmed concurrently.
+            return Err(ServiceError::Conflict(String::from(
+                "TOTP is already enabled",
+            )));
+        }
+
+        Ok(RecoveryCodes { recovery_codes })
+    }
+
+    /// Replaces the recovery codes of a user, invalidating the unused ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose codes to replace.
+    /// * `code` - A current TOTP code.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RecoveryCodes)` - The new recovery codes, shown to the user once.
+    /// * `Err(ServiceError)` - `UnAuthorizedError` if the code is wrong, `NotFound` if TOTP is not enabled,
+    ///   `Forbidden` if the second factor of the account is locked.
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: &str,
+        code: &str,
+    ) -> Result<RecoveryCodes, ServiceError> {
+        let account_id = parse_account_id(user_id)?;
+        if !self.check_second_factor(account_id, code, false).await? {
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        let (recovery_codes, hashes) = new_recovery_codes();
+        let stored = self
+            .pg_repo
+            .replace_recovery_codes(account_id, &hashes)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if stored == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        Ok(RecoveryCodes { recovery_codes })
+    }
+
+    /// Disables TOTP for a user, removing the secret and the recovery codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user disabling TOTP.
+    /// * `code` - A current TOTP code or an unused recovery code.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If TOTP was disabled.
+    /// * `Err(ServiceError)` - `UnAuthorizedError` if the code is wrong, `NotFound` if TOTP is not enabled,
+    ///   `Forbidden` if the second factor of the account is locked.
+    pub async fn disable_totp(&self, user_id: &str, code: &str) -> Result<(), ServiceError> {
+        let account_id = parse_account_id(user_id)?;
+        if !self.check_second_factor(account_id, code, true).await? {
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        match self.pg_repo.delete_totp(account_id).await {
+            Ok(0) => Err(ServiceError::NotFound),
+            Ok(_) => Ok(()),
+            Err(e) => Err(ServiceError::DatabaseError(e)),
+        }
+    }
+
+    /// Tells whether a user has a confirmed TOTP secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user to look up.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether TOTP codes are required at login.
+    /// * `Err(ServiceError)` - If a database error occurs.
+    pub async fn totp_enabled(&self, user_id: &str) -> Result<bool, ServiceError> {
+        match self.pg_repo.get_totp(parse_account_id(user_id)?).await {
+            Ok(secret) => Ok(secret.confirmed),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(e) => Err(ServiceError::DatabaseError(e)),
+        }
+    }
+
+    /// Re-authenticates the user of a session, so routes requiring a recent or multi-factor
+    /// authentication accept it again. New tokens are issued in the same session,
+    /// recording a password authentication, plus `otp` when a TOTP code is given.
+    /// They replace the session's refresh token and the access token presented.
+    ///
+    /// # Arguments
+    ///
+    /// * `claims` - The claims of the access token of the session.
+    /// * `reauth` - The password of the account and an optional TOTP code.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Token)` - Struct containing the new access and refresh tokens of the session.
+    /// * `Err(ServiceError)` - `UnAuthorizedError` if the password or the code is wrong.
+    pub async fn reauthenticate(
+        &self,
+        claims: &Claims,
+        reauth: Reauthenticate,
+    ) -> Result<Token, ServiceError> {
+        let account_id = parse_account_id(&claims.id)?;
+        let session_id = claims
+            .sid
+            .as_deref()
+            .ok_or(ServiceError::UnAuthorizedError)?;
+
+        // Accounts fetched by id carry no password hash, fetch it by username.
+        let auth_info = match self.pg_repo.get_account_by_id(account_id).await {
+            Ok(account) => {
+                self.pg_repo
+                    .get_account_by_username(&account.username)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let auth_info = match auth_info {
+            Ok(auth_info) => auth_info,
+            Err(sqlx::Error::RowNotFound) => return Err(ServiceError::NotFound),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+
+        let password = auth_info.password.as_deref().unwrap_or_default();
+        if utils::password::Hasher::verify_password(&reauth.password, password).is_err() {
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        // The session must still be live, new tokens stay bound to its client.
+        let session = self
+            .redis_repo
+            .list_sessions(&claims.id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .into_iter()
+            .find(|s| s.id == session_id)
+            .ok_or(ServiceError::UnAuthorizedError)?;
+
+        let mut amr = vec!["pwd"];
+        if let Some(code) = &reauth.code {
+            if !self.check_second_factor(account_id, code, false).await? {
+                return Err(ServiceError::UnAuthorizedError);
+            }
+            amr.push("otp");
+        }
+        let authentication = Authentication::now(&amr);
+
+        // Retire the tokens issued before, so the session keeps a single live refresh token
+        // and the access token used to re-authenticate is not accepted any longer.
+        self.redis_repo
+            .delete_family_tokens(session_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        self.redis_repo
+            .deny_access_token(&claims.jti, claims.exp as i64 - Utc::now().timestamp())
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        // Issue new tokens in the same session, keeping its scope.
+        let user_id = auth_info.id.to_string();
+        let ttl = utils::jwt::JwtUtils::get_refresh_exp();
+        let (access_token, access_claims) = utils::jwt::JwtUtils::generate_access_token(
+            &user_id,
+            &auth_info.roles,
+            session_id,
+            session.client_id.as_deref(),
+            claims.scope.as_deref(),
+            &authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
+        let refresh_token = utils::jwt::JwtUtils::generate_refresh_token(
+            &user_id,
+            &auth_info.roles,
+            session_id,
+            claims.scope.as_deref(),
+            &authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
+
+        self.redis_repo
+            .store_refresh_token(
+                &user_id,
+                session_id,
+                session.client_id.as_deref(),
+                &refresh_token,
+                ttl,
             )
-            .map_err(|e| ServiceError::JwtError(e))?;
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        self.redis_repo
+            .set_session_access_token(session_id, &access_claims.jti, access_claims.exp as i64)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        self.redis_repo
+            .touch_session(&user_id, session_id, Utc::now().timestamp(), ttl)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        let login_session = self
+            .create_login_session(&user_id, session_id, &authentication, ttl)
+            .await?;
+
+        Ok(Token {
+            access_token,
+            refresh_token,
+            scope: claims.scope.clone(),
+            login_session: Some(login_session),
+        })
+    }
+
+    /// Checks a second factor of an account with a confirmed TOTP secret.
+    /// Accepted codes are marked as used, TOTP codes cannot be replayed within their time window.
+    /// Wrong codes are counted per account, after too many of them every code is refused
+    /// until the lockout expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The account the code was entered for.
+    /// * `code` - A TOTP code, or a recovery code if `allow_recovery` is set.
+    /// * `allow_recovery` - Whether recovery codes are accepted.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the code was accepted. Always false without a confirmed secret.
+    /// * `Err(ServiceError)` - `Forbidden` if the second factor is locked, or a database or Redis error.
+    async fn check_second_factor(
+        &self,
+        account_id: i32,
+        code: &str,
+        allow_recovery: bool,
+    ) -> Result<bool, ServiceError> {
+        let user_id = account_id.to_string();
+        let failures = self
+            .redis_repo
+            .get_account_mfa_failures(&user_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        if failures >= MAX_ACCOUNT_MFA_FAILURES {
+            return Err(ServiceError::Forbidden(String::from(
+                "Too many wrong codes, try again later",
+            )));
+        }
+
+        let accepted = self
+            .verify_second_factor(account_id, code, allow_recovery)
+            .await?;
 
-            // Store the refresh token in Redis with an appropriate expiration time.
+        if accepted {
             self.redis_repo
-                .store_refresh_token(
-                    &auth_info.id.to_string(),
-                    &refresh_token,
-                    utils::jwt::JwtUtils::get_refresh_exp(),
-                )
+                .clear_account_mfa_failures(&user_id)
                 .await
                 .map_err(|e| {
                     error!("Redis error: {}", e);
                     ServiceError::RedisError
                 })?;
+        } else {
+            self.redis_repo
+                .record_account_mfa_failure(&user_id, MFA_LOCKOUT_TTL)
+                .await
+                .map_err(|e| {
+                    error!("Redis error: {}", e);
+                    ServiceError::RedisError
+                })?;
+        }
+
+        Ok(accepted)
+    }
+
+    /// Verifies a TOTP code or a recovery code and marks it as used, see `check_second_factor`.
+    async fn verify_second_factor(
+        &self,
+        account_id: i32,
+        code: &str,
+        allow_recovery: bool,
+    ) -> Result<bool, ServiceError> {
+        let secret = match self.pg_repo.get_totp(account_id).await {
+            Ok(secret) if secret.confirmed => secret,
+            Ok(_) | Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
 
-            // Return the generated tokens.
-            return Ok(Token {
-                access_token,
-                refresh_token,
-            });
+        if let Some(step) = totp::verify(&secret.secret, code.trim(), Utc::now().timestamp()) {
+            let used = self
+                .pg_repo
+                .use_totp_step(account_id, step as i64)
+                .await
+                .map_err(ServiceError::DatabaseError)?;
+            return Ok(used == 1);
         }
 
-        // Return an error if password verification fails.
-        Err(ServiceError::UnAuthorizedError)
+        if !allow_recovery {
+            return Ok(false);
+        }
+
+        let used = self
+            .pg_repo
+            .use_recovery_code(account_id, &totp::hash_recovery_code(code))
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        Ok(used == 1)
+    }
+
+    /// Returns the API scopes tokens of the given roles may carry, inherited roles included.
+    ///
+    /// # Arguments
+    ///
+    /// * `roles` - The roles assigned to the account or client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - The scopes of the roles.
+    /// * `Err(ServiceError)` - If the database cannot be queried.
+    pub async fn roles_scopes(&self, roles: &[String]) -> Result<Vec<String>, ServiceError> {
+        self.pg_repo
+            .get_roles_scopes(roles)
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Starts a new session for an authenticated account and issues its tokens.
+    /// Every session is a new refresh token family.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The id of the authenticated account.
+    /// * `roles` - The roles of the authenticated account.
+    /// * `device` - The user agent and IP address the login comes from.
+    /// * `client_id` - The OAuth client the session is started for, `None` for direct logins.
+    /// * `scope` - The space separated scopes the session's tokens are restricted to.
+    /// * `authentication` - How and when the user authenticated.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Token)` - Struct containing the generated access and refresh tokens.
+    /// * `Err(ServiceError)` - If token generation or Redis storage fails.
+    pub async fn start_session(
+        &self,
+        user_id: &str,
+        roles: &[String],
+        device: DeviceInfo,
+        client_id: Option<&str>,
+        scope: Option<&str>,
+        authentication: &Authentication,
+    ) -> Result<Token, ServiceError> {
+        let user_id = user_id.to_string();
+        let session_id = Uuid::new_v4().to_string();
+        let ttl = utils::jwt::JwtUtils::get_refresh_exp();
+
+        // Generate new access and refresh tokens bound to the session.
+        let (access_token, access_claims) = utils::jwt::JwtUtils::generate_access_token(
+            &user_id,
+            roles,
+            &session_id,
+            client_id,
+            scope,
+            authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
+
+        let refresh_token = utils::jwt::JwtUtils::generate_refresh_token(
+            &user_id,
+            roles,
+            &session_id,
+            scope,
+            authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
+
+        // Remember the access token of the session so revoking the session can deny it,
+        // creating the session afterwards sets the expiry of the whole hash.
+        self.redis_repo
+            .set_session_access_token(&session_id, &access_claims.jti, access_claims.exp as i64)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        // Register the session in the user's session index.
+        let now = Utc::now().timestamp();
+        let session = Session {
+            id: session_id.clone(),
+            user_id: user_id.clone(),
+            user_agent: device.user_agent,
+            ip: device.ip,
+            created_at: now,
+            last_used_at: now,
+            client_id: client_id.map(String::from),
+            current: true,
+        };
+        self.redis_repo
+            .create_session(&session, ttl)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        // Store the refresh token in Redis with an appropriate expiration time.
+        self.redis_repo
+            .store_refresh_token(&user_id, &session_id, client_id, &refresh_token, ttl)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        // Direct logins also get a browser login session for the authorization endpoints.
+        let login_session = match client_id {
+            Some(_) => None,
+            None => Some(
+                self.create_login_session(&user_id, &session_id, authentication, ttl)
+                    .await?,
+            ),
+        };
+
+        // Return the generated tokens.
+        Ok(Token {
+            access_token,
+            refresh_token,
+            scope: scope.map(String::from),
+            login_session,
+        })
+    }
+
+    /// Creates the browser login session of a direct session.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The id of the authenticated account.
+    /// * `session_id` - The id of the session.
+    /// * `authentication` - How and when the user authenticated.
+    /// * `ttl` - Lifetime of the login session in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The login session token.
+    /// * `Err(ServiceError)` - If Redis storage fails.
+    async fn create_login_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        authentication: &Authentication,
+        ttl: i64,
+    ) -> Result<String, ServiceError> {
+        let token = utils::random::random_token(32);
+        let login = LoginSession {
+            user_id: user_id.to_string(),
+            session_id: session_id.to_string(),
+            authentication: authentication.clone(),
+        };
+        self.redis_repo
+            .store_login_session(&token, &login, ttl)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        Ok(token)
+    }
+
+    /// Identifies the user behind a browser login session.
+    ///
+    /// The session it belongs to must still be active, so logging out or revoking the
+    /// session also ends the login session. Roles are read from the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The login session token from the cookie.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Claims)` - The claims of the logged in user.
+    /// * `None` - If the login session is unknown, expired or its session was revoked.
+    pub async fn resolve_login_session(&self, token: &str) -> Option<Claims> {
+        let login = self.redis_repo.get_login_session(token).await.ok()??;
+
+        let sessions = self.redis_repo.list_sessions(&login.user_id).await.ok()?;
+        if !sessions.iter().any(|s| s.id == login.session_id) {
+            return None;
+        }
+
+        let account = self
+            .pg_repo
+            .get_account_by_id(parse_account_id(&login.user_id).ok()?)
+            .await
+            .ok()?;
+        check_login_policy(&account).ok()?;
+
+        let now = Utc::now();
+        Some(Claims {
+            id: login.user_id,
+            roles: account.roles,
+            exp: (now.timestamp() + utils::jwt::JwtUtils::get_access_exp()) as usize,
+            iat: now.timestamp() as usize,
+            iat_ms: now.timestamp_millis(),
+            jti: Uuid::new_v4().to_string(),
+            sid: Some(login.session_id),
+            sub_type: Default::default(),
+            aud: None,
+            scope: None,
+            act: None,
+            client_id: None,
+            auth_time: Some(login.authentication.auth_time as usize),
+            amr: login.authentication.amr.clone(),
+            acr: Some(login.authentication.acr().to_string()),
+        })
+    }
+
+    /// Lists the active sessions of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose sessions to list.
+    /// * `current_sid` - The session the request was made from, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Session>)` - The sessions, most recently used first.
+    /// * `Err(ServiceError)` - If the sessions cannot be read from Redis.
+    pub async fn list_sessions(
+        &self,
+        user_id: &str,
+        current_sid: Option<&str>,
+    ) -> Result<Vec<Session>, ServiceError> {
+        let mut sessions = self.redis_repo.list_sessions(user_id).await.map_err(|e| {
+            error!("Redis error: {}", e);
+            ServiceError::RedisError
+        })?;
+
+        for session in sessions.iter_mut() {
+            session.current = current_sid == Some(session.id.as_str());
+        }
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.last_used_at));
+
+        Ok(sessions)
     }
 
-    /// Remove refresh token in Redis
+    /// Revokes one session of a user, logging the device out.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user owning the session.
+    /// * `session_id` - The session to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the session was revoked.
+    /// * `Err(ServiceError)` - `NotFound` if the user has no such session, or a Redis error.
+    pub async fn revoke_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+    ) -> Result<(), ServiceError> {
+        let sessions = self.redis_repo.list_sessions(user_id).await.map_err(|e| {
+            error!("Redis error: {}", e);
+            ServiceError::RedisError
+        })?;
+
+        // Only allow revoking the user's own sessions.
+        if !sessions.iter().any(|s| s.id == session_id) {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.redis_repo
+            .revoke_token_family(session_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })
+    }
+
+    /// Logs a user out of every device: revokes all their sessions and refresh tokens
+    /// and rejects every access token issued until now.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user to log out.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If every session was revoked.
+    /// * `Err(ServiceError)` - If a Redis operation fails.
+    pub async fn logout_all(&self, user_id: &str) -> Result<(), ServiceError> {
+        // Revoke every refresh token of the user.
+        self.redis_repo
+            .revoke_user_sessions(user_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        // Reject the access tokens already handed out until they expire.
+        self.redis_repo
+            .set_tokens_valid_after(
+                user_id,
+                Utc::now().timestamp_millis(),
+                utils::jwt::JwtUtils::get_access_exp(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })
+    }
+
+    /// Logs the current device out: removes the refresh token, ends the session of the
+    /// access token and denies the access token for the rest of its lifetime.
+    /// A refresh token of another user is left untouched.
     ///
     /// # Arguments
     ///
     /// * `refresh_token` - The token to remove
+    /// * `claims` - The claims of the access token making the request.
     ///
     /// # Returns
     ///
     /// *`Ok(())` - If remove success
-    /// *`Err(actix_web::error::Error)` - Actix error if the account is not found or the credentials are invalid.
-    pub async fn logout(&self, refresh_token: &str) -> Result<(), actix_web::error::Error> {
+    /// *`Err(actix_web::error::Error)` - Forbidden if the refresh token belongs to another user,
+    ///   actix error if a Redis operation fails.
+    pub async fn logout(
+        &self,
+        refresh_token: &str,
+        claims: &Claims,
+    ) -> Result<(), actix_web::error::Error> {
+        let record = self
+            .redis_repo
+            .get_refresh_token(refresh_token)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if record.as_ref().is_some_and(|r| r.user_id != claims.id) {
+            return Err(actix_web::error::ErrorForbidden(
+                "Refresh token belongs to another user",
+            ));
+        }
+
+        if record.is_some() {
+            self.redis_repo
+                .delete_refresh_token(refresh_token)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+
+        if let Some(session_id) = &claims.sid {
+            self.redis_repo
+                .revoke_token_family(session_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+
+        // Deny the access token until it expires.
+        let remaining = claims.exp as i64 - Utc::now().timestamp();
         self.redis_repo
-            .delete_refresh_token(refresh_token)
+            .deny_access_token(&claims.jti, remaining)
             .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))
+            .map_err(actix_web::error::ErrorInternalServerError)
+    }
+}
+
+/// Applies the login policy to an account whose credentials were verified.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the account may log in.
+/// * `Err(ServiceError)` - `Forbidden` if verified email addresses are required and the account's is not.
+pub fn check_login_policy(account: &Account) -> Result<(), ServiceError> {
+    if policy::require_verified_email() && !account.email_verified {
+        return Err(ServiceError::Forbidden(String::from(
+            "Email address is not verified",
+        )));
     }
+
+    Ok(())
+}
+
+/// Parses the account id of a user token.
+fn parse_account_id(user_id: &str) -> Result<i32, ServiceError> {
+    user_id.parse().map_err(ServiceError::InvalidIdFormat)
+}
+
+/// Generates a set of recovery codes.
+///
+/// # Returns
+///
+/// * The recovery codes to show to the user, and their hashes to store.
+fn new_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| totp::generate_recovery_code())
+        .collect();
+    let hashes = codes.iter().map(|c| totp::hash_recovery_code(c)).collect();
+    (codes, hashes)
 }
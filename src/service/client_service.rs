@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use log::{error, info};
+use url::{Host, Url};
+use uuid::Uuid;
+
+use crate::{
+    error::{oauth_error::OAuthError, service_error::ServiceError},
+    model::client::{Client, ClientRegistrationRequest, ClientRegistrationResponse, ClientSecret},
+    traits::{client_trait::ClientRepository, redis_traits::TokenRedisRepository},
+    utils,
+};
+
+/// Role given to clients created through dynamic registration.
+const REGISTERED_CLIENT_ROLE: &str = "service";
+
+/// Service responsible for registering and managing OAuth clients.
+pub struct ClientService<C: ClientRepository, T: TokenRedisRepository> {
+    /// Repository of the registered OAuth clients.
+    client_repo: Arc<C>,
+    /// Repository for Redis operations, used to revoke the sessions of disabled clients.
+    redis_repo: Arc<T>,
+    /// Token required to register clients dynamically, `None` disables dynamic registration.
+    initial_access_token: Option<String>,
+}
+
+impl<C: ClientRepository, T: TokenRedisRepository> ClientService<C, T> {
+    /// Creates a new instance of `ClientService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_repo` - A shared reference to the client repository.
+    /// * `redis_repo` - A shared reference to the Redis repository.
+    /// * `initial_access_token` - Token required to call the registration endpoint.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `ClientService`.
+    pub fn new(
+        client_repo: Arc<C>,
+        redis_repo: Arc<T>,
+        initial_access_token: Option<String>,
+    ) -> Self {
+        Self {
+            client_repo,
+            redis_repo,
+            initial_access_token,
+        }
+    }
+
+    /// Registers a new client (RFC 7591).
+    ///
+    /// Clients registering with `token_endpoint_auth_method=none` are public clients,
+    /// every other client is confidential and receives a secret that is only returned once.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The client metadata.
+    /// * `access_token` - The bearer token of the request, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ClientRegistrationResponse)` - The registered client and its credentials.
+    /// * `Err(OAuthError)` - `InvalidToken` if the initial access token is wrong,
+    ///   or an error describing the invalid metadata.
+    pub async fn register(
+        &self,
+        request: ClientRegistrationRequest,
+        access_token: Option<&str>,
+    ) -> Result<ClientRegistrationResponse, OAuthError> {
+        let authorized = match (&self.initial_access_token, access_token) {
+            (Some(expected), Some(given)) => utils::password::constant_time_eq(expected, given),
+            _ => false,
+        };
+        if !authorized {
+            info!("Client registration with an invalid initial access token");
+            return Err(OAuthError::InvalidToken);
+        }
+
+        if request.client_name.trim().is_empty() {
+            return Err(OAuthError::InvalidClientMetadata(String::from(
+                "client_name is required",
+            )));
+        }
+        validate_redirect_uris(&request.redirect_uris).map_err(OAuthError::InvalidRedirectUri)?;
+
+        let auth_method = request
+            .token_endpoint_auth_method
+            .unwrap_or_else(|| String::from("client_secret_basic"));
+        let secret = match auth_method.as_str() {
+            "none" => None,
+            "client_secret_basic" | "client_secret_post" => Some(utils::random::random_token(32)),
+            _ => {
+                return Err(OAuthError::InvalidClientMetadata(String::from(
+                    "Unsupported token_endpoint_auth_method",
+                )))
+            }
+        };
+
+        let client_secret = secret
+            .as_deref()
+            .map(utils::password::Hasher::hash_password)
+            .transpose()
+            .map_err(|e| {
+                error!("Hashing error: {}", e);
+                OAuthError::ServerError
+            })?;
+
+        let client = Client {
+            client_id: Uuid::new_v4().to_string(),
+            name: request.client_name,
+            redirect_uris: request.redirect_uris,
+            client_secret,
+            role: String::from(REGISTERED_CLIENT_ROLE),
+            disabled: false,
+        };
+        self.client_repo.insert_client(&client).await.map_err(|e| {
+            error!("Create client error: {}", e);
+            OAuthError::ServerError
+        })?;
+
+        info!("Client {} registered", client.client_id);
+        Ok(ClientRegistrationResponse {
+            client_id: client.client_id,
+            client_secret_expires_at: secret.as_ref().map(|_| 0),
+            client_secret: secret,
+            client_id_issued_at: Utc::now().timestamp(),
+            client_name: client.name,
+            redirect_uris: client.redirect_uris,
+            token_endpoint_auth_method: auth_method,
+        })
+    }
+
+    /// Lists every registered client, secrets are never exposed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Client>)` - The registered clients.
+    /// * `Err(ServiceError)` - If the database cannot be queried.
+    pub async fn list_clients(&self) -> Result<Vec<Client>, ServiceError> {
+        self.client_repo
+            .list_clients()
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Generates a new secret for a client, the previous secret stops working immediately.
+    /// Rotating the secret of a public client makes it confidential.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client whose secret to rotate.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ClientSecret)` - The new secret, which is not stored in clear.
+    /// * `Err(ServiceError)` - `NotFound` if the client does not exist, or a database error.
+    pub async fn rotate_secret(&self, client_id: &str) -> Result<ClientSecret, ServiceError> {
+        let secret = utils::random::random_token(32);
+        let hash = utils::password::Hasher::hash_password(&secret).map_err(|e| {
+            error!("Hashing error: {}", e);
+            ServiceError::HashError
+        })?;
+
+        let updated = self
+            .client_repo
+            .update_client_secret(client_id, &hash)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if updated == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        info!("Secret of client {} rotated", client_id);
+        Ok(ClientSecret {
+            client_id: client_id.to_string(),
+            client_secret: secret,
+        })
+    }
+
+    /// Replaces the redirect URIs of a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client to update.
+    /// * `redirect_uris` - The new redirect URIs, absolute and without fragment.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the client was updated.
+    /// * `Err(ServiceError)` - `BadRequest` for an invalid URI, `NotFound` if the client does not exist.
+    pub async fn update_redirect_uris(
+        &self,
+        client_id: &str,
+        redirect_uris: &[String],
+    ) -> Result<(), ServiceError> {
+        validate_redirect_uris(redirect_uris).map_err(ServiceError::BadRequest)?;
+
+        let updated = self
+            .client_repo
+            .update_redirect_uris(client_id, redirect_uris)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if updated == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Disables a client: it can no longer authenticate, start authorizations or obtain tokens.
+    /// The sessions started for it are revoked, with every refresh token it already holds.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - The client to disable.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the client was disabled.
+    /// * `Err(ServiceError)` - `NotFound` if the client does not exist, or a database or Redis error.
+    pub async fn disable_client(&self, client_id: &str) -> Result<(), ServiceError> {
+        let updated = self
+            .client_repo
+            .set_client_disabled(client_id, true)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if updated == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.redis_repo
+            .revoke_client_sessions(client_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        info!("Client {} disabled", client_id);
+        Ok(())
+    }
+}
+
+/// Checks that every redirect URI is absolute and has no fragment (RFC 6749 section 3.1.2).
+/// Codes must only travel over TLS, so plain `http` is only accepted for loopback
+/// addresses used by native apps (RFC 8252 section 7.3).
+fn validate_redirect_uris(redirect_uris: &[String]) -> Result<(), String> {
+    for uri in redirect_uris {
+        match Url::parse(uri) {
+            Ok(url) if url.fragment().is_none() && is_secure_redirect(&url) => {}
+            _ => return Err(format!("Invalid redirect URI: {uri}")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that a redirect URI uses `https`, or `http` on a loopback address.
+fn is_secure_redirect(url: &Url) -> bool {
+    match url.scheme() {
+        "https" => true,
+        "http" => match url.host() {
+            Some(Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(Host::Ipv6(ip)) => ip.is_loopback(),
+            Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+            None => false,
+        },
+        _ => false,
+    }
+}
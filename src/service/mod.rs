@@ -1,3 +1,9 @@
 pub mod account_service;
 pub mod auth_service;
+pub mod client_service;
+pub mod oauth_service;
+pub mod password_service;
+pub mod rbac_service;
 pub mod token_service;
+pub mod verification_service;
+pub mod webauthn_service;
@@ -0,0 +1,1426 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use log::{error, info};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{
+    config::{oauth, oidc, scope},
+    error::{oauth_error::OAuthError, service_error::ServiceError},
+    model::{
+        client::Client,
+        grant::Grant,
+        oauth::{
+            AuthorizationCode, AuthorizeRequest, ClientCredentials, ConsentPrompt, ConsentRequest,
+            DeviceAuthorization, DeviceAuthorizationRequest, DeviceAuthorizationResponse,
+            DeviceAuthorizationStatus, DeviceVerification, IntrospectionRequest,
+            IntrospectionResponse, RevocationRequest, TokenRequest, TokenResponse,
+        },
+        session::DeviceInfo,
+        token::Token,
+    },
+    service::{auth_service::AuthService, rbac_service::RbacService, token_service::TokenService},
+    traits::{
+        account_trait::AccountRepository, client_trait::ClientRepository,
+        grant_trait::GrantRepository, rbac_trait::RbacRepository,
+        redis_traits::TokenRedisRepository,
+    },
+    utils::{
+        self,
+        jwt::{Actor, Authentication, Claims, JwtUtils},
+    },
+};
+
+/// Lifetime of an authorization code in seconds.
+const AUTHORIZATION_CODE_TTL: i64 = 60;
+
+/// Lifetime of a consent screen in seconds.
+const CONSENT_REQUEST_TTL: i64 = 600;
+
+/// Lifetime of a device code and its user code in seconds.
+const DEVICE_CODE_TTL: i64 = 600;
+
+/// Minimum number of seconds a device waits between two polls of the token endpoint.
+const DEVICE_POLL_INTERVAL: i64 = 5;
+
+/// `grant_type` of the device authorization grant (RFC 8628 section 3.4).
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// `grant_type` of the token exchange grant (RFC 8693 section 2.1).
+const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+
+/// Token type identifier of access tokens (RFC 8693 section 3).
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// Service implementing the standard OAuth 2.0 endpoints on top of `TokenService`.
+/// Tokens issued through OAuth grants start sessions through `AuthService`, exactly like a password login.
+pub struct OAuthService<
+    R: AccountRepository,
+    T: TokenRedisRepository,
+    C: ClientRepository,
+    G: GrantRepository,
+    P: RbacRepository,
+> {
+    /// Service used to start sessions and issue tokens.
+    auth_service: Arc<AuthService<R, T>>,
+    /// Service used to decode, verify and revoke tokens.
+    token_service: Arc<TokenService<R, T>>,
+    /// Repository for Redis operations, used to store authorization codes.
+    redis_repo: Arc<T>,
+    /// Repository of the registered OAuth clients.
+    client_repo: Arc<C>,
+    /// Repository of the scopes users granted to clients.
+    grant_repo: Arc<G>,
+    /// Service resolving role inheritance, used to keep privileged roles out of exchanged tokens.
+    rbac_service: Arc<RbacService<P>>,
+}
+
+impl<
+        R: AccountRepository,
+        T: TokenRedisRepository,
+        C: ClientRepository,
+        G: GrantRepository,
+        P: RbacRepository,
+    > OAuthService<R, T, C, G, P>
+{
+    /// Creates a new instance of `OAuthService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `auth_service` - A shared reference to the authentication service.
+    /// * `token_service` - A shared reference to the token service.
+    /// * `redis_repo` - A shared reference to the Redis repository.
+    /// * `client_repo` - A shared reference to the client repository.
+    /// * `grant_repo` - A shared reference to the grant repository.
+    /// * `rbac_service` - A shared reference to the role-based access control service.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `OAuthService`.
+    pub fn new(
+        auth_service: Arc<AuthService<R, T>>,
+        token_service: Arc<TokenService<R, T>>,
+        redis_repo: Arc<T>,
+        client_repo: Arc<C>,
+        grant_repo: Arc<G>,
+        rbac_service: Arc<RbacService<P>>,
+    ) -> Self {
+        Self {
+            auth_service,
+            token_service,
+            redis_repo,
+            client_repo,
+            grant_repo,
+            rbac_service,
+        }
+    }
+
+    /// Identifies the logged in user calling the authorization endpoint, either from a
+    /// Bearer access token or from the `login_session` cookie set by `/api/auth/login`.
+    /// The cookie is `SameSite=Lax` so it is sent when a client redirects the user agent here.
+    /// Client tokens never identify a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_token` - The Bearer token of the request, if any.
+    /// * `login_session` - The `login_session` cookie of the request, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Claims)` - The claims of the user if one of the tokens is valid.
+    /// * `None` - If the user is not logged in.
+    pub async fn authenticate_resource_owner(
+        &self,
+        access_token: Option<&str>,
+        login_session: Option<&str>,
+    ) -> Option<Claims> {
+        if let Some(token) = access_token {
+            if let Ok(claims) = self.token_service.verify_access_token(token).await {
+                return (!claims.is_client()).then_some(claims);
+            }
+        }
+
+        match login_session {
+            Some(token) => self.auth_service.resolve_login_session(token).await,
+            None => None,
+        }
+    }
+
+    /// Handles an authorization request of the authorization code flow with PKCE.
+    ///
+    /// The client and redirect URI are validated first; if they are invalid the error is returned
+    /// directly so the user agent is never sent to an unverified URI. Every later error is
+    /// reported to the client through the redirect, as defined by RFC 6749 section 4.1.2.1.
+    ///
+    /// A code is issued right away if the user already granted every requested scope to the
+    /// client, otherwise the user agent is sent to the consent screen first.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The authorization request parameters.
+    /// * `owner` - The claims of the logged in user, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The URI to redirect the user agent to: the consent screen,
+    ///   or the client's redirect URI carrying either a code or an error.
+    /// * `Err(OAuthError)` - If the client or redirect URI is invalid.
+    pub async fn authorize(
+        &self,
+        request: AuthorizeRequest,
+        owner: Option<Claims>,
+    ) -> Result<String, OAuthError> {
+        let client = match self.client_repo.get_client_by_id(&request.client_id).await {
+            Ok(client) if !client.disabled => client,
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                return Err(OAuthError::InvalidRequest(String::from(
+                    "Unknown client_id",
+                )))
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(OAuthError::ServerError);
+            }
+        };
+
+        // The redirect URI must exactly match a registered one, it may only be omitted
+        // when the client registered a single URI.
+        let redirect_uri = match request.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(&uri) => uri,
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => {
+                return Err(OAuthError::InvalidRequest(String::from(
+                    "Invalid redirect_uri",
+                )))
+            }
+        };
+        let state = request.state.as_deref();
+
+        if request.response_type != "code" {
+            return Ok(redirect_error(
+                &redirect_uri,
+                "unsupported_response_type",
+                state,
+            ));
+        }
+
+        // Only the S256 PKCE method is accepted.
+        let code_challenge = match (request.code_challenge, request.code_challenge_method) {
+            (Some(challenge), Some(method)) if method == "S256" => challenge,
+            _ => return Ok(redirect_error(&redirect_uri, "invalid_request", state)),
+        };
+
+        let owner = match owner {
+            Some(owner) => owner,
+            None => return Ok(redirect_error(&redirect_uri, "login_required", state)),
+        };
+
+        let auth_time = self.auth_time(&owner).await?;
+        // Scopes the user's roles do not hold are silently dropped.
+        let role_scopes = self
+            .auth_service
+            .roles_scopes(&owner.roles)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+        let scope = scope::allowed_scope(request.scope.as_deref(), &role_scopes);
+        let authorization = AuthorizationCode {
+            client_id: client.client_id,
+            redirect_uri: redirect_uri.clone(),
+            user_id: owner.id,
+            roles: owner.roles,
+            code_challenge,
+            scope,
+            nonce: request.nonce,
+            auth_time,
+            amr: owner.amr,
+        };
+
+        // Ask for consent unless every requested scope was already granted.
+        let prompt = request.prompt.as_deref();
+        if prompt == Some("consent") || !self.is_granted(&authorization).await? {
+            if prompt == Some("none") {
+                return Ok(redirect_error(&redirect_uri, "consent_required", state));
+            }
+
+            let consent_id = utils::random::random_token(32);
+            let consent = ConsentRequest {
+                authorization,
+                state: request.state,
+            };
+            self.redis_repo
+                .store_consent_request(&consent_id, &consent, CONSENT_REQUEST_TTL)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    OAuthError::ServerError
+                })?;
+
+            return Ok(redirect_with(
+                &format!("{}/oauth/consent", oidc::issuer()),
+                &[("consent_id", &consent_id)],
+                None,
+            ));
+        }
+
+        self.issue_authorization_code(authorization, state).await
+    }
+
+    /// Stores a short-lived, single-use authorization code and builds the redirect carrying it.
+    async fn issue_authorization_code(
+        &self,
+        authorization: AuthorizationCode,
+        state: Option<&str>,
+    ) -> Result<String, OAuthError> {
+        let code = utils::random::random_token(32);
+        self.redis_repo
+            .store_authorization_code(&code, &authorization, AUTHORIZATION_CODE_TTL)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+
+        info!("Authorization code issued to {}", authorization.client_id);
+        Ok(redirect_with(
+            &authorization.redirect_uri,
+            &[("code", &code)],
+            state,
+        ))
+    }
+
+    /// Checks whether the user already granted the client every scope of an authorization.
+    async fn is_granted(&self, authorization: &AuthorizationCode) -> Result<bool, OAuthError> {
+        let account_id = account_id(&authorization.user_id)?;
+
+        match self
+            .grant_repo
+            .get_grant(account_id, &authorization.client_id)
+            .await
+        {
+            Ok(grant) => Ok(scopes(authorization.scope.as_deref())
+                .iter()
+                .all(|s| grant.scopes.contains(s))),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(e) => {
+                error!("{}", e);
+                Err(OAuthError::ServerError)
+            }
+        }
+    }
+
+    /// Describes an authorization request waiting for the user's consent.
+    ///
+    /// # Arguments
+    ///
+    /// * `consent_id` - The id of the consent screen.
+    /// * `owner` - The claims of the logged in user.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ConsentPrompt)` - The client asking for access and the requested scopes.
+    /// * `Err(ServiceError)` - `NotFound` if the request is unknown, expired or belongs to another user.
+    pub async fn consent_prompt(
+        &self,
+        consent_id: &str,
+        owner: &Claims,
+    ) -> Result<ConsentPrompt, ServiceError> {
+        let consent = self
+            .redis_repo
+            .get_consent_request(consent_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .filter(|c| c.authorization.user_id == owner.id)
+            .ok_or(ServiceError::NotFound)?;
+
+        let client = self
+            .client_repo
+            .get_client_by_id(&consent.authorization.client_id)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+
+        Ok(ConsentPrompt {
+            consent_id: consent_id.to_string(),
+            client_id: client.client_id,
+            client_name: client.name,
+            scopes: scopes(consent.authorization.scope.as_deref()),
+        })
+    }
+
+    /// Records the decision of the user on the consent screen.
+    /// Approving stores the grant and issues the authorization code, denying reports
+    /// `access_denied` to the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `consent_id` - The id of the consent screen.
+    /// * `owner` - The claims of the logged in user.
+    /// * `approve` - Whether the user grants the requested scopes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The client's redirect URI carrying either a code or an error.
+    /// * `Err(OAuthError)` - If the request is unknown, expired or belongs to another user.
+    pub async fn decide_consent(
+        &self,
+        consent_id: &str,
+        owner: &Claims,
+        approve: bool,
+    ) -> Result<String, OAuthError> {
+        let owned = self
+            .redis_repo
+            .get_consent_request(consent_id)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?
+            .filter(|c| c.authorization.user_id == owner.id)
+            .is_some();
+        if !owned {
+            return Err(OAuthError::InvalidRequest(String::from(
+                "Unknown consent_id",
+            )));
+        }
+
+        // Consume the request only once it is known to belong to the user.
+        // A concurrent decision may have consumed it in between, only one of them is honoured.
+        let consent = self
+            .redis_repo
+            .consume_consent_request(consent_id)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?
+            .ok_or_else(|| OAuthError::InvalidRequest(String::from("Unknown consent_id")))?;
+        let state = consent.state.as_deref();
+
+        if !approve {
+            info!("Consent denied to {}", consent.authorization.client_id);
+            return Ok(redirect_error(
+                &consent.authorization.redirect_uri,
+                "access_denied",
+                state,
+            ));
+        }
+
+        self.record_grant(
+            &consent.authorization.user_id,
+            &consent.authorization.client_id,
+            consent.authorization.scope.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            OAuthError::ServerError
+        })?;
+
+        self.issue_authorization_code(consent.authorization, state)
+            .await
+    }
+
+    /// Adds scopes to the grant of a user to a client, creating the grant if needed.
+    async fn record_grant(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        scope: Option<&str>,
+    ) -> Result<(), ServiceError> {
+        let account_id = user_id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+
+        let mut granted = match self.grant_repo.get_grant(account_id, client_id).await {
+            Ok(grant) => grant.scopes,
+            Err(sqlx::Error::RowNotFound) => Vec::new(),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+        for scope in scopes(scope) {
+            if !granted.contains(&scope) {
+                granted.push(scope);
+            }
+        }
+
+        self.grant_repo
+            .upsert_grant(account_id, client_id, &granted, Utc::now().timestamp())
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+
+        info!("Consent recorded for {}", client_id);
+        Ok(())
+    }
+
+    /// Lists the clients a user granted access to.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose grants to list.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Grant>)` - The grants, most recent first.
+    /// * `Err(ServiceError)` - If the user id is invalid or the database cannot be queried.
+    pub async fn list_grants(&self, user_id: &str) -> Result<Vec<Grant>, ServiceError> {
+        let account_id = user_id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+
+        self.grant_repo
+            .list_grants(account_id)
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Revokes the access a user granted to a client: deletes the grant and revokes
+    /// every session, and so every refresh token, the client obtained for the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user revoking access.
+    /// * `client_id` - The client losing access.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the grant was revoked.
+    /// * `Err(ServiceError)` - `NotFound` if the user never granted access to the client.
+    pub async fn revoke_grant(&self, user_id: &str, client_id: &str) -> Result<(), ServiceError> {
+        let account_id = user_id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+
+        let deleted = self
+            .grant_repo
+            .delete_grant(account_id, client_id)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if deleted == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        // Revoke the refresh token families issued to the client.
+        let redis_error = |e| {
+            error!("Redis error: {}", e);
+            ServiceError::RedisError
+        };
+        let sessions = self
+            .redis_repo
+            .list_sessions(user_id)
+            .await
+            .map_err(redis_error)?;
+        for session in sessions
+            .iter()
+            .filter(|s| s.client_id.as_deref() == Some(client_id))
+        {
+            self.redis_repo
+                .revoke_token_family(&session.id)
+                .await
+                .map_err(redis_error)?;
+        }
+
+        info!("Grant of {} revoked", client_id);
+        Ok(())
+    }
+
+    /// Handles a token request, dispatching on its `grant_type`.
+    ///
+    /// Supported grants are `authorization_code`, `refresh_token` and `client_credentials`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The token request parameters.
+    /// * `basic` - Client credentials from the `Authorization: Basic` header, if any.
+    /// * `device` - The user agent and IP address of the client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TokenResponse)` - The issued tokens.
+    /// * `Err(OAuthError)` - If the grant is unsupported or invalid, or the client fails to authenticate.
+    pub async fn token(
+        &self,
+        mut request: TokenRequest,
+        basic: Option<ClientCredentials>,
+        device: DeviceInfo,
+    ) -> Result<TokenResponse, OAuthError> {
+        let credentials = client_credentials(
+            basic,
+            request.client_id.take(),
+            request.client_secret.take(),
+        );
+
+        match request.grant_type.as_str() {
+            "authorization_code" => {
+                self.exchange_authorization_code(request, credentials, device)
+                    .await
+            }
+            "refresh_token" => self.refresh(request, credentials).await,
+            "client_credentials" => self.issue_client_token(request, credentials).await,
+            DEVICE_CODE_GRANT_TYPE => self.poll_device_code(request, credentials, device).await,
+            TOKEN_EXCHANGE_GRANT_TYPE => self.exchange_token(request, credentials).await,
+            _ => Err(OAuthError::UnsupportedGrantType),
+        }
+    }
+
+    /// Redeems an authorization code after checking the client, redirect URI and PKCE verifier.
+    /// Confidential clients must also authenticate with their secret.
+    async fn exchange_authorization_code(
+        &self,
+        request: TokenRequest,
+        credentials: Credentials,
+        device: DeviceInfo,
+    ) -> Result<TokenResponse, OAuthError> {
+        let (code, redirect_uri, code_verifier) =
+            match (request.code, request.redirect_uri, request.code_verifier) {
+                (Some(code), Some(redirect_uri), Some(code_verifier)) => {
+                    (code, redirect_uri, code_verifier)
+                }
+                _ => {
+                    return Err(OAuthError::InvalidRequest(String::from(
+                        "code, redirect_uri, client_id and code_verifier are required",
+                    )))
+                }
+            };
+
+        // Authenticate confidential clients before touching the code.
+        let client = self.identify_client(credentials).await?;
+
+        // Consume the code first, so it can never be redeemed twice.
+        let authorization = self
+            .redis_repo
+            .consume_authorization_code(&code)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?
+            .ok_or_else(|| OAuthError::InvalidGrant(String::from("Invalid authorization code")))?;
+
+        if authorization.client_id != client.client_id || authorization.redirect_uri != redirect_uri
+        {
+            return Err(OAuthError::InvalidGrant(String::from(
+                "Authorization code was issued to another client or redirect_uri",
+            )));
+        }
+
+        if !verify_pkce(&code_verifier, &authorization.code_challenge) {
+            return Err(OAuthError::InvalidGrant(String::from(
+                "PKCE verification failed",
+            )));
+        }
+
+        let authentication = Authentication {
+            auth_time: authorization.auth_time,
+            amr: authorization.amr.clone(),
+        };
+        let token = self
+            .auth_service
+            .start_session(
+                &authorization.user_id,
+                &authorization.roles,
+                device,
+                Some(&authorization.client_id),
+                authorization.scope.as_deref(),
+                &authentication,
+            )
+            .await?;
+
+        // OpenID Connect clients also receive an ID token describing the login.
+        let id_token = if has_scope(authorization.scope.as_deref(), "openid") {
+            let id_token = JwtUtils::generate_id_token(
+                &authorization.user_id,
+                &authorization.client_id,
+                authorization.nonce,
+                authorization.auth_time,
+            )
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+            Some(id_token)
+        } else {
+            None
+        };
+
+        Ok(token_response(token, id_token))
+    }
+
+    /// Finds when the user behind a token logged in, from its `auth_time` claim.
+    /// Older tokens report when their session was created, or their issue time if the session is gone.
+    async fn auth_time(&self, owner: &Claims) -> Result<i64, OAuthError> {
+        if let Some(auth_time) = owner.auth_time {
+            return Ok(auth_time as i64);
+        }
+
+        let sessions = self
+            .redis_repo
+            .list_sessions(&owner.id)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+
+        Ok(sessions
+            .into_iter()
+            .find(|s| owner.sid.as_deref() == Some(s.id.as_str()))
+            .map(|s| s.created_at)
+            .unwrap_or(owner.iat as i64))
+    }
+
+    /// Issues an access token to a confidential client acting on its own behalf
+    /// (`client_credentials` grant, RFC 6749 section 4.4). No refresh token is issued.
+    /// The token holds the requested scopes, or every scope of the client's role if none is requested.
+    async fn issue_client_token(
+        &self,
+        request: TokenRequest,
+        credentials: Credentials,
+    ) -> Result<TokenResponse, OAuthError> {
+        let client = self.authenticate_client(credentials).await?;
+
+        let allowed = self
+            .auth_service
+            .roles_scopes(std::slice::from_ref(&client.role))
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+        let scope = match request.scope {
+            Some(requested) => {
+                if !requested
+                    .split_whitespace()
+                    .all(|s| allowed.iter().any(|a| a == s))
+                {
+                    return Err(OAuthError::InvalidScope);
+                }
+                Some(requested)
+            }
+            None => scope::default_scope(&allowed),
+        };
+
+        let (access_token, claims) =
+            JwtUtils::generate_client_token(&client.client_id, &client.role, scope.as_deref())
+                .map_err(|e| {
+                    error!("{}", e);
+                    OAuthError::ServerError
+                })?;
+
+        info!("Client token issued to {}", client.client_id);
+        Ok(TokenResponse {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: JwtUtils::get_access_exp(),
+            refresh_token: None,
+            id_token: None,
+            issued_token_type: None,
+            scope: claims.scope,
+        })
+    }
+
+    /// Starts a device authorization (RFC 8628 section 3.1): issues a device code the device polls
+    /// with and a short user code the user enters on the verification page.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The client identification and requested scope.
+    /// * `basic` - Client credentials from the `Authorization: Basic` header, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DeviceAuthorizationResponse)` - The codes and where the user enters them.
+    /// * `Err(OAuthError)` - If the client fails to authenticate.
+    pub async fn device_authorization(
+        &self,
+        request: DeviceAuthorizationRequest,
+        basic: Option<ClientCredentials>,
+    ) -> Result<DeviceAuthorizationResponse, OAuthError> {
+        let credentials = client_credentials(basic, request.client_id, request.client_secret);
+        let client = self.identify_client(credentials).await?;
+
+        let device_code = utils::random::random_token(32);
+        let user_code = utils::random::random_user_code();
+        let authorization = DeviceAuthorization {
+            client_id: client.client_id,
+            user_code: normalize_user_code(&user_code),
+            scope: request.scope,
+            status: DeviceAuthorizationStatus::Pending,
+            user_id: None,
+            roles: Vec::new(),
+            authentication: None,
+        };
+        self.redis_repo
+            .store_device_authorization(&device_code, &authorization, DEVICE_CODE_TTL)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                OAuthError::ServerError
+            })?;
+
+        info!("Device code issued to {}", authorization.client_id);
+
+        let verification_uri = format!("{}/oauth/device", oidc::issuer());
+        Ok(DeviceAuthorizationResponse {
+            device_code,
+            verification_uri_complete: format!("{verification_uri}?user_code={user_code}"),
+            user_code,
+            verification_uri,
+            expires_in: DEVICE_CODE_TTL,
+            interval: DEVICE_POLL_INTERVAL,
+        })
+    }
+
+    /// Describes the pending device authorization of a user code, for the user to review.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_code` - The user code as entered by the user, case and separators are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DeviceVerification)` - The client asking for access and the requested scope.
+    /// * `Err(ServiceError)` - `NotFound` if the user code is unknown, expired or already used.
+    pub async fn pending_device_authorization(
+        &self,
+        user_code: &str,
+    ) -> Result<DeviceVerification, ServiceError> {
+        let (_, authorization) = self.find_pending_device_authorization(user_code).await?;
+
+        let client = self
+            .client_repo
+            .get_client_by_id(&authorization.client_id)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+
+        Ok(DeviceVerification {
+            user_code: user_code.to_string(),
+            client_id: client.client_id,
+            client_name: client.name,
+            scope: authorization.scope,
+        })
+    }
+
+    /// Records the decision of a logged in user on a device authorization.
+    /// Once approved, the next poll of the device receives tokens for that user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_code` - The user code as entered by the user.
+    /// * `owner` - The claims of the logged in user.
+    /// * `approve` - Whether the user grants the device access.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the decision was recorded.
+    /// * `Err(ServiceError)` - `NotFound` if the user code is unknown, expired or already used.
+    pub async fn decide_device_authorization(
+        &self,
+        user_code: &str,
+        owner: &Claims,
+        approve: bool,
+    ) -> Result<(), ServiceError> {
+        let (device_code, mut authorization) =
+            self.find_pending_device_authorization(user_code).await?;
+
+        if approve {
+            authorization.status = DeviceAuthorizationStatus::Approved;
+            let role_scopes = self.auth_service.roles_scopes(&owner.roles).await?;
+            authorization.scope =
+                scope::allowed_scope(authorization.scope.as_deref(), &role_scopes);
+            authorization.user_id = Some(owner.id.clone());
+            authorization.roles = owner.roles.clone();
+            authorization.authentication = Some(owner.authentication());
+        } else {
+            authorization.status = DeviceAuthorizationStatus::Denied;
+        }
+
+        let updated = self
+            .redis_repo
+            .update_device_authorization(&device_code, &authorization)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        if !updated {
+            return Err(ServiceError::NotFound);
+        }
+
+        // Approving a device is a consent to the client, revocable like any other grant.
+        if approve {
+            self.record_grant(
+                &owner.id,
+                &authorization.client_id,
+                authorization.scope.as_deref(),
+            )
+            .await?;
+        }
+
+        info!(
+            "Device authorization for {} {}",
+            authorization.client_id,
+            if approve { "approved" } else { "denied" }
+        );
+        Ok(())
+    }
+
+    /// Resolves a user code to its still undecided device authorization.
+    async fn find_pending_device_authorization(
+        &self,
+        user_code: &str,
+    ) -> Result<(String, DeviceAuthorization), ServiceError> {
+        let redis_error = |e| {
+            error!("Redis error: {}", e);
+            ServiceError::RedisError
+        };
+
+        let device_code = self
+            .redis_repo
+            .find_device_code(&normalize_user_code(user_code))
+            .await
+            .map_err(redis_error)?
+            .ok_or(ServiceError::NotFound)?;
+
+        match self
+            .redis_repo
+            .get_device_authorization(&device_code)
+            .await
+            .map_err(redis_error)?
+        {
+            Some(authorization) if authorization.status == DeviceAuthorizationStatus::Pending => {
+                Ok((device_code, authorization))
+            }
+            _ => Err(ServiceError::NotFound),
+        }
+    }
+
+    /// Answers a device polling the token endpoint (RFC 8628 section 3.5).
+    /// Tokens are issued once, through a new session, exactly like a password login.
+    async fn poll_device_code(
+        &self,
+        request: TokenRequest,
+        credentials: Credentials,
+        device: DeviceInfo,
+    ) -> Result<TokenResponse, OAuthError> {
+        let device_code = request
+            .device_code
+            .ok_or_else(|| OAuthError::InvalidRequest(String::from("device_code is required")))?;
+        let client = self.identify_client(credentials).await?;
+
+        let redis_error = |e| {
+            error!("{}", e);
+            OAuthError::ServerError
+        };
+
+        // Devices polling faster than the interval are told to back off.
+        if !self
+            .redis_repo
+            .throttle_device_poll(&device_code, DEVICE_POLL_INTERVAL)
+            .await
+            .map_err(redis_error)?
+        {
+            return Err(OAuthError::SlowDown);
+        }
+
+        let authorization = self
+            .redis_repo
+            .get_device_authorization(&device_code)
+            .await
+            .map_err(redis_error)?
+            .ok_or(OAuthError::ExpiredToken)?;
+
+        if authorization.client_id != client.client_id {
+            return Err(OAuthError::InvalidGrant(String::from(
+                "Device code was issued to another client",
+            )));
+        }
+
+        match authorization.status {
+            DeviceAuthorizationStatus::Pending => return Err(OAuthError::AuthorizationPending),
+            DeviceAuthorizationStatus::Denied => {
+                self.redis_repo
+                    .consume_device_authorization(&device_code)
+                    .await
+                    .map_err(redis_error)?;
+                return Err(OAuthError::AccessDenied);
+            }
+            DeviceAuthorizationStatus::Approved => {}
+        }
+
+        // Consume the authorization, a concurrent poll may have redeemed it first.
+        let authorization = self
+            .redis_repo
+            .consume_device_authorization(&device_code)
+            .await
+            .map_err(redis_error)?
+            .ok_or(OAuthError::ExpiredToken)?;
+
+        let user_id = authorization.user_id.ok_or(OAuthError::ServerError)?;
+
+        let token = self
+            .auth_service
+            .start_session(
+                &user_id,
+                &authorization.roles,
+                device,
+                Some(&client.client_id),
+                authorization.scope.as_deref(),
+                &authorization.authentication.unwrap_or_default(),
+            )
+            .await?;
+
+        info!("Device authorization redeemed by {}", client.client_id);
+        Ok(token_response(token, None))
+    }
+
+    /// Exchanges a user's access token for a delegated one (RFC 8693).
+    ///
+    /// The calling client must be confidential and becomes the actor of the new token.
+    /// The new token can only narrow the subject token: its audience cannot change once set,
+    /// its scopes must be a subset of the subject token's scopes and privileged roles are dropped.
+    async fn exchange_token(
+        &self,
+        request: TokenRequest,
+        credentials: Credentials,
+    ) -> Result<TokenResponse, OAuthError> {
+        let client = self.authenticate_client(credentials).await?;
+
+        let subject_token = match (request.subject_token, request.subject_token_type) {
+            (Some(token), Some(token_type)) if token_type == ACCESS_TOKEN_TYPE => token,
+            (Some(_), Some(_)) => {
+                return Err(OAuthError::InvalidRequest(String::from(
+                    "Unsupported subject_token_type",
+                )))
+            }
+            _ => {
+                return Err(OAuthError::InvalidRequest(String::from(
+                    "subject_token and subject_token_type are required",
+                )))
+            }
+        };
+        if request
+            .requested_token_type
+            .is_some_and(|t| t != ACCESS_TOKEN_TYPE)
+        {
+            return Err(OAuthError::InvalidRequest(String::from(
+                "Unsupported requested_token_type",
+            )));
+        }
+
+        let subject = match self.token_service.verify_access_token(&subject_token).await {
+            Ok(claims) => claims,
+            Err(ServiceError::RedisError) => return Err(OAuthError::ServerError),
+            Err(_) => {
+                return Err(OAuthError::InvalidGrant(String::from(
+                    "Invalid subject token",
+                )))
+            }
+        };
+
+        // An audience restriction can never be lifted or moved to another service.
+        let audience = match (request.audience, &subject.aud) {
+            (Some(requested), Some(current)) if requested != *current => {
+                return Err(OAuthError::InvalidTarget)
+            }
+            (requested, current) => requested.or_else(|| current.clone()),
+        };
+
+        // Requested scopes must all be held by the subject token, an unscoped token holds none.
+        let scope = match request.scope {
+            Some(requested) => {
+                if !requested
+                    .split_whitespace()
+                    .all(|s| has_scope(subject.scope.as_deref(), s))
+                {
+                    return Err(OAuthError::InvalidScope);
+                }
+                Some(requested)
+            }
+            None => subject.scope.clone(),
+        };
+
+        let roles = self.delegable_roles(&subject.roles).await?;
+
+        // The client acts for the subject, after any previous actor.
+        let actor = Actor {
+            sub: client.client_id,
+            act: subject.act.clone().map(Box::new),
+        };
+
+        info!("Token of {} exchanged by {}", subject.id, actor.sub);
+        let (access_token, claims) = JwtUtils::generate_exchanged_token(
+            &subject, roles, audience, scope, actor,
+        )
+        .map_err(|e| {
+            error!("{}", e);
+            OAuthError::ServerError
+        })?;
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: claims.exp as i64 - Utc::now().timestamp(),
+            refresh_token: None,
+            id_token: None,
+            issued_token_type: Some(String::from(ACCESS_TOKEN_TYPE)),
+            scope: claims.scope,
+        })
+    }
+
+    /// Keeps the roles of a subject that may be delegated: those that neither are
+    /// nor inherit one of the privileged roles.
+    async fn delegable_roles(&self, roles: &[String]) -> Result<Vec<String>, OAuthError> {
+        let privileged = oauth::exchange_privileged_roles();
+
+        let mut delegable = Vec::new();
+        for role in roles {
+            let effective = self
+                .rbac_service
+                .resolve_roles(std::slice::from_ref(role))
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    OAuthError::ServerError
+                })?;
+            if privileged.iter().all(|p| !effective.contains(p)) {
+                delegable.push(role.clone());
+            }
+        }
+        Ok(delegable)
+    }
+
+    /// Identifies the client of a request: confidential clients must authenticate,
+    /// public clients only need to send their client id.
+    async fn identify_client(&self, credentials: Credentials) -> Result<Client, OAuthError> {
+        let client_id = match &credentials {
+            Credentials::Secret(credentials) => credentials.client_id.clone(),
+            Credentials::Public(client_id) => client_id.clone(),
+            Credentials::None => {
+                return Err(OAuthError::InvalidRequest(String::from(
+                    "client_id is required",
+                )))
+            }
+        };
+
+        let client = self.find_client(&client_id).await?;
+        if client.is_confidential() {
+            return self.authenticate_client(credentials).await;
+        }
+
+        Ok(client)
+    }
+
+    /// Looks up a registered client, unknown and disabled clients fail client authentication.
+    async fn find_client(&self, client_id: &str) -> Result<Client, OAuthError> {
+        match self.client_repo.get_client_by_id(client_id).await {
+            Ok(client) if !client.disabled => Ok(client),
+            Ok(_) => {
+                info!("Disabled client {}", client_id);
+                Err(OAuthError::InvalidClient)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                info!("Unknown client {}", client_id);
+                Err(OAuthError::InvalidClient)
+            }
+            Err(e) => {
+                error!("{}", e);
+                Err(OAuthError::ServerError)
+            }
+        }
+    }
+
+    /// Authenticates a confidential client by checking its secret against the stored hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - The credentials presented by the client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Client)` - The authenticated client.
+    /// * `Err(OAuthError)` - `InvalidClient` if the client is unknown, public or the secret is wrong.
+    async fn authenticate_client(&self, credentials: Credentials) -> Result<Client, OAuthError> {
+        let credentials = match credentials {
+            Credentials::Secret(credentials) => credentials,
+            _ => {
+                info!("Client authentication missing");
+                return Err(OAuthError::InvalidClient);
+            }
+        };
+
+        let client = self.find_client(&credentials.client_id).await?;
+        let verified = client.client_secret.as_deref().is_some_and(|hash| {
+            utils::password::Hasher::verify_password(&credentials.client_secret, hash).is_ok()
+        });
+
+        if !verified {
+            info!("Client authentication failed for {}", client.client_id);
+            return Err(OAuthError::InvalidClient);
+        }
+
+        Ok(client)
+    }
+
+    /// Rotates a refresh token presented to the token endpoint.
+    /// The token must have been issued to the client presenting it.
+    async fn refresh(
+        &self,
+        request: TokenRequest,
+        credentials: Credentials,
+    ) -> Result<TokenResponse, OAuthError> {
+        let refresh_token = request
+            .refresh_token
+            .ok_or_else(|| OAuthError::InvalidRequest(String::from("refresh_token is required")))?;
+        let client = self.identify_client(credentials).await?;
+
+        match self
+            .token_service
+            .rotate_refresh_token(&refresh_token, Some(&client.client_id))
+            .await
+        {
+            Ok(token) => Ok(token_response(token, None)),
+            Err(ServiceError::RedisError) => Err(OAuthError::ServerError),
+            Err(_) => Err(OAuthError::InvalidGrant(String::from(
+                "Invalid refresh token",
+            ))),
+        }
+    }
+
+    /// Revokes a refresh or access token as described by RFC 7009.
+    ///
+    /// The client is authenticated, or identified if it is public, and may only revoke
+    /// the tokens issued to it (RFC 7009 section 2.1).
+    /// The `token_type_hint` only decides which token type is tried first,
+    /// unknown hints are ignored. Invalid or unknown tokens are not an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The token to revoke, its optional type hint and client credentials.
+    /// * `basic` - Client credentials from the `Authorization: Basic` header, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the token is revoked or was never valid.
+    /// * `Err(OAuthError)` - `InvalidClient` if the client cannot be authenticated,
+    ///   `InvalidRequest` if the token was issued to another client,
+    ///   or if the revocation could not be stored.
+    pub async fn revoke(
+        &self,
+        request: RevocationRequest,
+        basic: Option<ClientCredentials>,
+    ) -> Result<(), OAuthError> {
+        let credentials = client_credentials(basic, request.client_id, request.client_secret);
+        let client = self.identify_client(credentials).await?;
+
+        // Try the hinted token type first, then fall back to the other one.
+        let access_first = request.token_type_hint.as_deref() == Some("access_token");
+        let mut revoked = false;
+        for access in [access_first, !access_first] {
+            let result = if access {
+                self.token_service
+                    .revoke_access_token(&request.token, &client.client_id)
+                    .await
+            } else {
+                self.token_service
+                    .revoke_refresh_token(&request.token, &client.client_id)
+                    .await
+            };
+            revoked = match result {
+                Ok(revoked) => revoked,
+                Err(ServiceError::Forbidden(description)) => {
+                    return Err(OAuthError::InvalidRequest(description))
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if revoked {
+                break;
+            }
+        }
+
+        if revoked {
+            info!("Token revoked");
+        } else {
+            info!("Revocation requested for an unknown token");
+        }
+
+        Ok(())
+    }
+
+    /// Describes a token as defined by RFC 7662, taking Redis revocation state into account.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The token to introspect, its optional type hint and client credentials.
+    /// * `basic` - Client credentials from the `Authorization: Basic` header, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(IntrospectionResponse)` - The token description, `active: false` for unusable tokens.
+    /// * `Err(OAuthError)` - `InvalidClient` if the caller is not an authenticated confidential client.
+    pub async fn introspect(
+        &self,
+        request: IntrospectionRequest,
+        basic: Option<ClientCredentials>,
+    ) -> Result<IntrospectionResponse, OAuthError> {
+        // Resource servers are registered as confidential clients.
+        let credentials = client_credentials(basic, request.client_id, request.client_secret);
+        self.authenticate_client(credentials).await?;
+
+        // Try the hinted token type first, then fall back to the other one.
+        let refresh_first = request.token_type_hint.as_deref() == Some("refresh_token");
+        for refresh in [refresh_first, !refresh_first] {
+            let result = if refresh {
+                self.token_service
+                    .verify_refresh_token(&request.token)
+                    .await
+            } else {
+                self.token_service.verify_access_token(&request.token).await
+            };
+
+            match result {
+                Ok(claims) => return Ok(active_response(claims, refresh)),
+                Err(ServiceError::RedisError) => return Err(OAuthError::ServerError),
+                Err(_) => continue,
+            }
+        }
+
+        Ok(IntrospectionResponse::default())
+    }
+}
+
+/// How a client identified itself on a token or introspection request.
+enum Credentials {
+    /// A client id and secret.
+    Secret(ClientCredentials),
+    /// A client id alone, as sent by public clients.
+    Public(String),
+    /// No client identification at all.
+    None,
+}
+
+/// Collects the client credentials of a request, which may come from the
+/// `Authorization: Basic` header or from the form body (RFC 6749 section 2.3.1).
+fn client_credentials(
+    basic: Option<ClientCredentials>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Credentials {
+    match (basic, client_id, client_secret) {
+        (Some(basic), _, _) => Credentials::Secret(basic),
+        (None, Some(client_id), Some(client_secret)) => Credentials::Secret(ClientCredentials {
+            client_id,
+            client_secret,
+        }),
+        (None, Some(client_id), None) => Credentials::Public(client_id),
+        _ => Credentials::None,
+    }
+}
+
+/// Builds the token endpoint response from an issued token pair and an optional ID token.
+fn token_response(token: Token, id_token: Option<String>) -> TokenResponse {
+    TokenResponse {
+        access_token: token.access_token,
+        token_type: String::from("Bearer"),
+        expires_in: JwtUtils::get_access_exp(),
+        refresh_token: Some(token.refresh_token),
+        id_token,
+        issued_token_type: None,
+        scope: token.scope,
+    }
+}
+
+/// Normalizes a user code as typed by a user: uppercase, without separators.
+fn normalize_user_code(user_code: &str) -> String {
+    user_code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Splits a space separated scope string.
+fn scopes(scope: Option<&str>) -> Vec<String> {
+    scope
+        .map(|scope| scope.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Parses the account id of a user token.
+fn account_id(user_id: &str) -> Result<i32, OAuthError> {
+    user_id.parse::<i32>().map_err(|e| {
+        error!("{}", e);
+        OAuthError::ServerError
+    })
+}
+
+/// Checks whether a space separated scope string contains the given scope.
+fn has_scope(scope: Option<&str>, wanted: &str) -> bool {
+    scope.is_some_and(|scope| scope.split_whitespace().any(|s| s == wanted))
+}
+
+/// Checks a PKCE `code_verifier` against its S256 `code_challenge` (RFC 7636 section 4.6).
+fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    if !(43..=128).contains(&code_verifier.len()) {
+        return false;
+    }
+
+    let digest = Sha256::digest(code_verifier.as_bytes());
+    utils::password::constant_time_eq(&URL_SAFE_NO_PAD.encode(digest), code_challenge)
+}
+
+/// Appends query parameters and the client's `state` to a redirect URI.
+fn redirect_with(redirect_uri: &str, params: &[(&str, &str)], state: Option<&str>) -> String {
+    // Registered redirect URIs are absolute, fall back to the raw URI if one is not.
+    let mut url = match Url::parse(redirect_uri) {
+        Ok(url) => url,
+        Err(_) => return redirect_uri.to_string(),
+    };
+
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    url.to_string()
+}
+
+/// Builds a redirect URI reporting an authorization error to the client.
+fn redirect_error(redirect_uri: &str, error: &str, state: Option<&str>) -> String {
+    redirect_with(redirect_uri, &[("error", error)], state)
+}
+
+/// Builds the introspection response of an active token.
+fn active_response(claims: Claims, refresh: bool) -> IntrospectionResponse {
+    let token_type = if refresh {
+        "refresh_token"
+    } else {
+        "access_token"
+    };
+
+    IntrospectionResponse {
+        active: true,
+        client_id: claims.is_client().then(|| claims.id.clone()),
+        sub: Some(claims.id),
+        aud: claims.aud,
+        scope: claims.scope,
+        act: claims.act,
+        roles: Some(claims.roles),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        jti: Some(claims.jti),
+        token_type: Some(token_type.to_string()),
+    }
+}
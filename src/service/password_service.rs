@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use log::{error, info};
+
+use crate::{
+    config::mail,
+    error::service_error::ServiceError,
+    model::{account::PasswordReset, mail::Mail},
+    service::auth_service::AuthService,
+    traits::{
+        account_trait::AccountRepository, mailer_trait::Mailer, redis_traits::TokenRedisRepository,
+    },
+    utils,
+};
+
+/// Lifetime of a password reset token in seconds.
+const PASSWORD_RESET_TTL: i64 = 900;
+
+/// Service responsible for recovering accounts whose password was forgotten.
+/// Reset tokens are random, single-use and stored in Redis for a short time.
+pub struct PasswordService<R: AccountRepository, T: TokenRedisRepository, M: Mailer> {
+    /// Service used to log the account out everywhere once its password changed.
+    auth_service: Arc<AuthService<R, T>>,
+    /// Repository of the accounts being recovered.
+    account_repo: Arc<R>,
+    /// Repository for Redis operations, used to store reset tokens.
+    redis_repo: Arc<T>,
+    /// Transport reset mail is sent through.
+    mailer: Arc<M>,
+}
+
+impl<R: AccountRepository, T: TokenRedisRepository, M: Mailer> PasswordService<R, T, M> {
+    /// Creates a new instance of `PasswordService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `auth_service` - A shared reference to the service managing sessions.
+    /// * `account_repo` - A shared reference to the account repository.
+    /// * `redis_repo` - A shared reference to the Redis repository.
+    /// * `mailer` - A shared reference to the mail transport.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `PasswordService`.
+    pub fn new(
+        auth_service: Arc<AuthService<R, T>>,
+        account_repo: Arc<R>,
+        redis_repo: Arc<T>,
+        mailer: Arc<M>,
+    ) -> Self {
+        Self {
+            auth_service,
+            account_repo,
+            redis_repo,
+            mailer,
+        }
+    }
+
+    /// Sends a password reset link to an email address, if it is the verified address of an account.
+    /// Nothing tells the caller whether a mail was sent, so addresses cannot be probed.
+    /// The link replaces the ones sent before.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The address of the account to recover.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the link was sent, or there was nothing to send.
+    /// * `Err(ServiceError)` - If the database or Redis fails, or the mail cannot be sent.
+    pub async fn forgot_password(&self, email: &str) -> Result<(), ServiceError> {
+        let account = match self.account_repo.get_account_by_email(email).await {
+            Ok(account) => account,
+            Err(sqlx::Error::RowNotFound) => {
+                info!("No account to recover");
+                return Ok(());
+            }
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+        // An unverified address may not belong to the owner of the account.
+        let address = match account.email {
+            Some(address) if account.email_verified => address,
+            _ => {
+                info!("Account {} has no verified address to recover", account.id);
+                return Ok(());
+            }
+        };
+
+        let token = utils::random::random_token(32);
+        self.redis_repo
+            .store_password_reset(&token, &account.id.to_string(), PASSWORD_RESET_TTL)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        let mail = Mail {
+            to: address,
+            subject: String::from("Reset your password"),
+            body: format!(
+                "Hello {},\n\nFollow this link to choose a new password:\n{}{}\n\nThe link expires in {} minutes. If you did not ask to reset your password, ignore this mail.",
+                account.username,
+                mail::password_reset_url(),
+                token,
+                PASSWORD_RESET_TTL / 60
+            ),
+        };
+        self.mailer.send(&mail).await.map_err(|e| {
+            error!("Mail error: {}", e);
+            ServiceError::MailError(e)
+        })
+    }
+
+    /// Sets a new password with a reset token, then revokes the other reset tokens and the
+    /// pending MFA challenges of the account, and logs it out of every device.
+    ///
+    /// # Arguments
+    ///
+    /// * `reset` - The reset token and the new password.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the password was changed.
+    /// * `Err(ServiceError)` - `BadRequest` if the token is unknown, expired or already used.
+    pub async fn reset_password(&self, reset: PasswordReset) -> Result<(), ServiceError> {
+        if reset.password.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "Password must not be empty",
+            )));
+        }
+
+        let user_id = self
+            .redis_repo
+            .consume_password_reset(&reset.token)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .ok_or_else(|| {
+                ServiceError::BadRequest(String::from("Invalid or expired reset token"))
+            })?;
+        let account_id = user_id.parse().map_err(ServiceError::InvalidIdFormat)?;
+
+        let password_hash =
+            utils::password::Hasher::hash_password(&reset.password).map_err(|e| {
+                error!("Hashing error: {}", e);
+                ServiceError::HashError
+            })?;
+
+        match self
+            .account_repo
+            .update_password(account_id, &password_hash)
+            .await
+        {
+            Ok(0) => return Err(ServiceError::NotFound),
+            Ok(_) => info!("Password of account {} reset", account_id),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        }
+
+        // Whoever knew the old password must not finish a login or stay logged in.
+        self.redis_repo
+            .revoke_password_resets(&user_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        self.redis_repo
+            .revoke_mfa_challenges(&user_id)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        self.auth_service.logout_all(&user_id).await
+    }
+}
@@ -0,0 +1,568 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use actix_web::http::Method;
+use log::{error, info, warn};
+
+use crate::{
+    error::service_error::ServiceError,
+    model::rbac::{NewRole, Permission, Role},
+    traits::rbac_trait::RbacRepository,
+    utils::path_pattern::PathPattern,
+};
+
+/// How long the role permissions are cached before being reloaded from the database,
+/// so changes made through another instance are picked up without a restart.
+const POLICY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How the rule deciding a request is picked among the rules matching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleOrdering {
+    /// The most specific rule decides: the longest pattern, then literal segments over
+    /// parameters, then a rule for the request method over a rule for every method.
+    #[default]
+    LongestMatch,
+    /// The rule with the highest priority decides, ties go to the most specific rule.
+    Priority,
+}
+
+/// A method and path pattern, with the roles whose permissions allow it.
+struct Rule {
+    /// HTTP method of the rule, `None` for every method.
+    method: Option<String>,
+    pattern: PathPattern,
+    priority: i32,
+    roles: Vec<String>,
+}
+
+impl Rule {
+    /// Checks whether the rule applies to a request. `HEAD` requests are `GET` requests
+    /// without a body, so rules for `GET` apply to them too.
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.method
+            .as_deref()
+            .is_none_or(|m| m == method || (m == "GET" && method == "HEAD"))
+            && self.pattern.matches(path)
+    }
+
+    /// Ranks the rule among the rules matching the same request, the highest rank decides.
+    fn rank(&self, ordering: RuleOrdering) -> (i32, (usize, usize, bool), bool) {
+        let priority = match ordering {
+            RuleOrdering::Priority => self.priority,
+            RuleOrdering::LongestMatch => 0,
+        };
+
+        (priority, self.pattern.specificity(), self.method.is_some())
+    }
+}
+
+/// The role permissions loaded from the database.
+struct Policy {
+    /// Rules with the roles they allow, one per method and path pattern.
+    rules: Vec<Rule>,
+    /// Roles each role directly inherits.
+    parents: HashMap<String, Vec<String>>,
+    /// When the rules were loaded.
+    loaded_at: Instant,
+}
+
+/// Service responsible for role-based access control.
+/// Roles, permissions and their assignments live in PostgreSQL and are cached in process,
+/// the cache is dropped whenever they are changed through this service.
+pub struct RbacService<P: RbacRepository> {
+    /// Repository of the roles and permissions.
+    rbac_repo: Arc<P>,
+    /// Cached role permissions, `None` until loaded or after a change.
+    policy: RwLock<Option<Arc<Policy>>>,
+}
+
+impl<P: RbacRepository> RbacService<P> {
+    /// Creates a new instance of `RbacService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rbac_repo` - A shared reference to the roles and permissions repository.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `RbacService`.
+    pub fn new(rbac_repo: Arc<P>) -> Self {
+        Self {
+            rbac_repo,
+            policy: RwLock::new(None),
+        }
+    }
+
+    /// Checks whether any of the given roles, or a role they inherit, may send a request.
+    /// Among the rules matching the method and path, the highest ranked one decides
+    /// according to `ordering`. Requests matching no rule are denied.
+    ///
+    /// # Arguments
+    ///
+    /// * `roles` - The roles of the user or client.
+    /// * `method` - The request method.
+    /// * `path` - The requested path.
+    /// * `ordering` - How the deciding rule is picked.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether access is allowed.
+    /// * `Err(ServiceError)` - If the permissions cannot be loaded.
+    pub async fn is_allowed(
+        &self,
+        roles: &[String],
+        method: &str,
+        path: &str,
+        ordering: RuleOrdering,
+    ) -> Result<bool, ServiceError> {
+        let policy = self.policy().await?;
+        let roles = effective_roles(roles, &policy.parents);
+
+        let matching: Vec<&Rule> = policy
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(method, path))
+            .collect();
+        let top = match matching.iter().map(|rule| rule.rank(ordering)).max() {
+            Some(top) => top,
+            None => return Ok(false),
+        };
+
+        // Rules ranked equally all decide, any of them may allow the request.
+        Ok(matching
+            .iter()
+            .filter(|rule| rule.rank(ordering) == top)
+            .any(|rule| rule.roles.iter().any(|r| roles.contains(r.as_str()))))
+    }
+
+    /// Resolves the roles a subject holds, inherited roles included.
+    ///
+    /// # Arguments
+    ///
+    /// * `roles` - The roles assigned to the user or client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(HashSet<String>)` - The assigned roles and every role they inherit.
+    /// * `Err(ServiceError)` - If the role inheritance cannot be loaded.
+    pub async fn resolve_roles(&self, roles: &[String]) -> Result<HashSet<String>, ServiceError> {
+        let policy = self.policy().await?;
+
+        Ok(effective_roles(roles, &policy.parents)
+            .into_iter()
+            .map(String::from)
+            .collect())
+    }
+
+    /// Returns the cached role permissions, reloading them once they expired.
+    async fn policy(&self) -> Result<Arc<Policy>, ServiceError> {
+        if let Some(policy) = self.policy.read().unwrap().as_ref() {
+            if policy.loaded_at.elapsed() < POLICY_CACHE_TTL {
+                return Ok(policy.clone());
+            }
+        }
+
+        let mut rules: Vec<Rule> = Vec::new();
+        for permission in self
+            .rbac_repo
+            .list_role_permissions()
+            .await
+            .map_err(ServiceError::DatabaseError)?
+        {
+            let pattern = match PathPattern::parse(&permission.path_pattern) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    warn!("Skipping rule {}: {}", permission.path_pattern, e);
+                    continue;
+                }
+            };
+
+            // Permissions with the same method and pattern form one rule.
+            match rules
+                .iter_mut()
+                .find(|rule| rule.method == permission.method && rule.pattern == pattern)
+            {
+                Some(rule) => {
+                    rule.priority = rule.priority.max(permission.priority);
+                    rule.roles.push(permission.role);
+                }
+                None => rules.push(Rule {
+                    method: permission.method,
+                    pattern,
+                    priority: permission.priority,
+                    roles: vec![permission.role],
+                }),
+            }
+        }
+
+        let parents = self.load_parents().await?;
+
+        let policy = Arc::new(Policy {
+            rules,
+            parents,
+            loaded_at: Instant::now(),
+        });
+        *self.policy.write().unwrap() = Some(policy.clone());
+
+        info!("Role permissions loaded");
+        Ok(policy)
+    }
+
+    /// Loads the roles each role directly inherits.
+    async fn load_parents(&self) -> Result<HashMap<String, Vec<String>>, ServiceError> {
+        let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+        for link in self
+            .rbac_repo
+            .list_role_inheritance()
+            .await
+            .map_err(ServiceError::DatabaseError)?
+        {
+            parents.entry(link.role).or_default().push(link.inherits);
+        }
+
+        Ok(parents)
+    }
+
+    /// Drops the cached role permissions so the next check reloads them.
+    fn invalidate(&self) {
+        *self.policy.write().unwrap() = None;
+    }
+
+    /// Lists every role with its permissions.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Role>)` - The roles, ordered by name.
+    /// * `Err(ServiceError)` - If the database cannot be queried.
+    pub async fn list_roles(&self) -> Result<Vec<Role>, ServiceError> {
+        self.rbac_repo
+            .list_roles()
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Creates a role without permissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role to create.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the role was created.
+    /// * `Err(ServiceError)` - `BadRequest` for an empty name, `Conflict` if the role exists.
+    pub async fn create_role(&self, role: NewRole) -> Result<(), ServiceError> {
+        if role.name.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from("name is required")));
+        }
+
+        self.rbac_repo
+            .insert_role(&role)
+            .await
+            .map_err(|e| conflict(e, "Role already exists"))?;
+
+        info!("Role {} created", role.name);
+        Ok(())
+    }
+
+    /// Deletes a role and its permission assignments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The role to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the role was deleted.
+    /// * `Err(ServiceError)` - `NotFound` if the role does not exist,
+    ///   `Conflict` if accounts or clients still have it.
+    pub async fn delete_role(&self, name: &str) -> Result<(), ServiceError> {
+        let deleted = self
+            .rbac_repo
+            .delete_role(name)
+            .await
+            .map_err(|e| conflict(e, "Role is still assigned"))?;
+        if deleted == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.invalidate();
+        info!("Role {} deleted", name);
+        Ok(())
+    }
+
+    /// Lists every permission.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Permission>)` - The permissions, ordered by name.
+    /// * `Err(ServiceError)` - If the database cannot be queried.
+    pub async fn list_permissions(&self) -> Result<Vec<Permission>, ServiceError> {
+        self.rbac_repo
+            .list_permissions()
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Creates a permission. It takes effect once granted to a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `permission` - The permission to create.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the permission was created.
+    /// * `Err(ServiceError)` - `BadRequest` for an invalid name, method or path pattern,
+    ///   `Conflict` if the permission exists.
+    pub async fn create_permission(&self, mut permission: Permission) -> Result<(), ServiceError> {
+        if permission.name.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from("name is required")));
+        }
+        PathPattern::parse(&permission.path_pattern).map_err(ServiceError::BadRequest)?;
+        if let Some(method) = permission.method.take() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| ServiceError::BadRequest(format!("Invalid method {}", method)))?;
+            permission.method = Some(method.to_string());
+        }
+
+        self.rbac_repo
+            .insert_permission(&permission)
+            .await
+            .map_err(|e| conflict(e, "Permission already exists"))?;
+
+        info!("Permission {} created", permission.name);
+        Ok(())
+    }
+
+    /// Deletes a permission and removes it from every role.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The permission to delete.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the permission was deleted.
+    /// * `Err(ServiceError)` - `NotFound` if the permission does not exist, or a database error.
+    pub async fn delete_permission(&self, name: &str) -> Result<(), ServiceError> {
+        let deleted = self
+            .rbac_repo
+            .delete_permission(name)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if deleted == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.invalidate();
+        info!("Permission {} deleted", name);
+        Ok(())
+    }
+
+    /// Grants a permission to a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role receiving the permission.
+    /// * `permission` - The permission to grant.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the role holds the permission.
+    /// * `Err(ServiceError)` - `NotFound` if the role or permission does not exist.
+    pub async fn grant_permission(&self, role: &str, permission: &str) -> Result<(), ServiceError> {
+        self.rbac_repo
+            .grant_permission(role, permission)
+            .await
+            .map_err(not_found)?;
+
+        self.invalidate();
+        info!("Permission {} granted to {}", permission, role);
+        Ok(())
+    }
+
+    /// Removes a permission from a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role losing the permission.
+    /// * `permission` - The permission to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the permission was removed.
+    /// * `Err(ServiceError)` - `NotFound` if the role did not hold the permission.
+    pub async fn revoke_permission(
+        &self,
+        role: &str,
+        permission: &str,
+    ) -> Result<(), ServiceError> {
+        let revoked = self
+            .rbac_repo
+            .revoke_permission(role, permission)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if revoked == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.invalidate();
+        info!("Permission {} revoked from {}", permission, role);
+        Ok(())
+    }
+
+    /// Makes a role inherit every permission of another role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The inheriting role.
+    /// * `inherits` - The role whose permissions are inherited.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the role inherits the other.
+    /// * `Err(ServiceError)` - `NotFound` if either role does not exist,
+    ///   `Conflict` if the link would create an inheritance cycle.
+    pub async fn add_inheritance(&self, role: &str, inherits: &str) -> Result<(), ServiceError> {
+        // Reject cycles, including a role inheriting itself.
+        let parents = self.load_parents().await?;
+        if effective_roles(&[inherits.to_string()], &parents).contains(role) {
+            return Err(ServiceError::Conflict(String::from(
+                "Role inheritance cycle",
+            )));
+        }
+
+        self.rbac_repo
+            .insert_role_inheritance(role, inherits)
+            .await
+            .map_err(not_found)?;
+
+        self.invalidate();
+        info!("Role {} inherits {}", role, inherits);
+        Ok(())
+    }
+
+    /// Stops a role from inheriting the permissions of another role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The inheriting role.
+    /// * `inherits` - The role whose permissions were inherited.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the link was removed.
+    /// * `Err(ServiceError)` - `NotFound` if the role did not inherit the other.
+    pub async fn remove_inheritance(&self, role: &str, inherits: &str) -> Result<(), ServiceError> {
+        let deleted = self
+            .rbac_repo
+            .delete_role_inheritance(role, inherits)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if deleted == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.invalidate();
+        info!("Role {} no longer inherits {}", role, inherits);
+        Ok(())
+    }
+
+    /// Assigns a role to an account. The role is added to the tokens issued from the next login.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The account receiving the role.
+    /// * `role` - The role to assign.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the account has the role.
+    /// * `Err(ServiceError)` - `NotFound` if the account or role does not exist.
+    pub async fn assign_role(&self, user_id: &str, role: &str) -> Result<(), ServiceError> {
+        let account_id = user_id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+
+        self.rbac_repo
+            .assign_account_role(account_id, role)
+            .await
+            .map_err(not_found)?;
+
+        info!("Role {} assigned to user {}", role, user_id);
+        Ok(())
+    }
+
+    /// Removes a role from an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The account losing the role.
+    /// * `role` - The role to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the role was removed.
+    /// * `Err(ServiceError)` - `NotFound` if the account did not have the role.
+    pub async fn unassign_role(&self, user_id: &str, role: &str) -> Result<(), ServiceError> {
+        let account_id = user_id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+
+        let deleted = self
+            .rbac_repo
+            .unassign_account_role(account_id, role)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if deleted == 0 {
+            return Err(ServiceError::NotFound);
+        }
+
+        info!("Role {} removed from user {}", role, user_id);
+        Ok(())
+    }
+}
+
+/// Turns foreign key violations, a reference to a missing row, into a `NotFound` error.
+fn not_found(e: sqlx::Error) -> ServiceError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => ServiceError::NotFound,
+        _ => ServiceError::DatabaseError(e),
+    }
+}
+
+/// Expands roles with every role they inherit, directly or not.
+fn effective_roles<'a>(
+    roles: &'a [String],
+    parents: &'a HashMap<String, Vec<String>>,
+) -> HashSet<&'a str> {
+    let mut effective = HashSet::new();
+    let mut pending: Vec<&str> = roles.iter().map(String::as_str).collect();
+
+    while let Some(role) = pending.pop() {
+        if effective.insert(role) {
+            if let Some(inherited) = parents.get(role) {
+                pending.extend(inherited.iter().map(String::as_str));
+            }
+        }
+    }
+
+    effective
+}
+
+/// Turns unique and foreign key violations into a `Conflict` error.
+fn conflict(e: sqlx::Error, message: &str) -> ServiceError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() || db.is_foreign_key_violation() => {
+            ServiceError::Conflict(message.to_string())
+        }
+        _ => {
+            error!("{}", e);
+            ServiceError::DatabaseError(e)
+        }
+    }
+}
@@ -4,110 +4,300 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use chrono::Utc;
 use jsonwebtoken::{DecodingKey, Validation};
-use log::{error, info};
+use log::{error, info, warn};
 
 use crate::{
+    config::scope,
     error::service_error::ServiceError,
-    traits::redis_traits::TokenRedisRepository,
+    model::token::Token,
+    service::auth_service,
+    traits::{account_trait::AccountRepository, redis_traits::TokenRedisRepository},
     utils::jwt::{self, Claims},
 };
 
 /// Service responsible for handling token verification and generation.
 /// This service supports both access tokens and refresh tokens,
-/// leveraging Redis to validate the existence of refresh tokens.
-pub struct TokenService<T: TokenRedisRepository> {
-    /// Repository for interacting with Redis, specifically for storing and validating refresh tokens.
+/// leveraging Redis to validate and rotate refresh tokens.
+pub struct TokenService<R: AccountRepository, T: TokenRedisRepository> {
+    /// Repository of the accounts, used to reload their roles when tokens are rotated.
+    account_repo: Arc<R>,
+    /// Repository for interacting with Redis, specifically for storing and rotating refresh tokens.
     token_redis_repo: Arc<T>,
 }
 
-impl<T: TokenRedisRepository> TokenService<T> {
+impl<R: AccountRepository, T: TokenRedisRepository> TokenService<R, T> {
     /// Creates a new instance of `TokenService`.
     ///
     /// # Arguments
     ///
+    /// * `account_repo` - A shared reference to the account repository.
     /// * `token_redis_repo` - A shared reference to the Redis repository used for token storage.
     ///
     /// # Returns
     ///
     /// * New instance of `TokenService`.
-    pub fn new(token_redis_repo: Arc<T>) -> Self {
-        Self { token_redis_repo }
+    pub fn new(account_repo: Arc<R>, token_redis_repo: Arc<T>) -> Self {
+        Self {
+            account_repo,
+            token_redis_repo,
+        }
     }
 
-    /// Verifies a refresh token by:
-    /// - Checking if it exists in Redis.
+    /// Rotates a refresh token by:
     /// - Decoding and validating the token using the `JWT_REFRESH_SECRET`.
-    /// - If the token is valid, a new access token is generated and returned.
+    /// - Issuing a new access token and a new refresh token in the same family.
+    /// - Replacing it in Redis by the new refresh token, so it can never be presented again.
+    ///   Both happen at once, a session revoked meanwhile is not brought back.
+    ///
+    /// The new tokens carry the account's current roles, and lose the scopes those roles no longer hold.
+    /// Accounts the login policy no longer lets in cannot rotate their tokens.
+    /// If the token was already used, it is treated as stolen and its whole family is revoked.
+    /// A token issued to an OAuth client is only rotated for that client.
     ///
     /// # Arguments
     ///
-    /// * `token` - The refresh token to verify.
+    /// * `token` - The refresh token to rotate.
+    /// * `client_id` - The client presenting the token, `None` for direct logins.
     ///
     /// # Returns
     ///
-    /// * `Ok(String)` - The newly generated access token.
+    /// * `Ok(Token)` - The newly generated access and refresh tokens.
     /// * `Err(ServiceError)` - An Actix web error if validation fails.
-    pub async fn verify_refresh_token(&self, token: &str) -> Result<String, ServiceError> {
-        // Load the refresh token secret key from environment variables.
-        let secret_key = env::var("JWT_REFRESH_SECRET").expect("JWT_REFRESH_SECRET must be set");
+    pub async fn rotate_refresh_token(
+        &self,
+        token: &str,
+        client_id: Option<&str>,
+    ) -> Result<Token, ServiceError> {
+        // Decode and validate the token.
+        let claims = self.decode_refresh_token(token)?;
+
+        // Check the client before consuming, so another client cannot burn the token.
+        let live = self
+            .token_redis_repo
+            .get_refresh_token(token)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+        let record = match live {
+            Some(record) if record.client_id.as_deref() != client_id => {
+                info!("Refresh token presented by another client");
+                return Err(ServiceError::UnAuthorizedError);
+            }
+            Some(record) => record,
+            None => {
+                self.revoke_if_reused(token).await?;
+                return Err(ServiceError::UnAuthorizedError);
+            }
+        };
+
+        // Reload the account, its roles may have changed since the family started
+        // and the login policy may no longer let it in.
+        let account_id = claims
+            .id
+            .parse::<i32>()
+            .map_err(ServiceError::InvalidIdFormat)?;
+        let account = match self.account_repo.get_account_by_id(account_id).await {
+            Ok(account) => account,
+            Err(sqlx::Error::RowNotFound) => return Err(ServiceError::UnAuthorizedError),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+        auth_service::check_login_policy(&account)?;
+        let roles = account.roles;
+        let role_scopes = self
+            .account_repo
+            .get_roles_scopes(&roles)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        let scope = scope::allowed_scope(claims.scope.as_deref(), &role_scopes);
+
+        // Generate a new token pair with the current roles and the authentication of the session.
+        let authentication = claims.authentication();
+        let (access_token, access_claims) = jwt::JwtUtils::generate_access_token(
+            &claims.id,
+            &roles,
+            &record.family_id,
+            record.client_id.as_deref(),
+            scope.as_deref(),
+            &authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
+        let refresh_token = jwt::JwtUtils::generate_refresh_token(
+            &claims.id,
+            &roles,
+            &record.family_id,
+            scope.as_deref(),
+            &authentication,
+        )
+        .map_err(ServiceError::JwtError)?;
 
-        // Check if the refresh token exists in Redis.
-        let is_valid = self
+        // Replace the token by the new one in the same family, remembering it as used for the rest
+        // of its lifetime, unless the session was revoked in the meantime.
+        let remaining = claims.exp as i64 - Utc::now().timestamp();
+        let ttl = jwt::JwtUtils::get_refresh_exp();
+        let rotated = self
             .token_redis_repo
-            .is_refresh_token_valid(token)
+            .rotate_refresh_token(
+                token,
+                remaining,
+                &refresh_token,
+                ttl,
+                &access_claims.jti,
+                access_claims.exp as i64,
+            )
             .await
             .map_err(|e| {
                 // Log any error that occurs during Redis operation.
                 error!("{}", e);
                 ServiceError::RedisError
             })?;
+        if rotated.is_none() {
+            self.revoke_if_reused(token).await?;
+            return Err(ServiceError::UnAuthorizedError);
+        }
 
-        // If the token does not exist in Redis, reject it.
-        if !is_valid {
+        Ok(Token {
+            access_token,
+            refresh_token,
+            scope,
+            login_session: None,
+        })
+    }
+
+    /// Handles a refresh token that could not be rotated. A token that was already rotated
+    /// is being replayed, so its whole family is revoked.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token that was presented.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Once the family of a replayed token is revoked, or if the token was never rotated.
+    /// * `Err(ServiceError)` - If the Redis operation fails.
+    async fn revoke_if_reused(&self, token: &str) -> Result<(), ServiceError> {
+        let family = self
+            .token_redis_repo
+            .get_used_token_family(token)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+
+        if let Some(family_id) = family {
+            warn!(
+                "Refresh token reuse detected, revoking family {}",
+                family_id
+            );
+            self.token_redis_repo
+                .revoke_token_family(&family_id)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    ServiceError::RedisError
+                })?;
+        } else {
             info!("Invalid refresh token");
-            return Err(ServiceError::UnAuthorizedError);
         }
 
+        Ok(())
+    }
+
+    /// Decodes a refresh token and validates its signature and expiration
+    /// using the `JWT_REFRESH_SECRET`. Redis state is not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to decode.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Claims)` - Decoded claims if the token is well formed.
+    /// * `Err(ServiceError)` - An Actix web error if validation fails.
+    pub fn decode_refresh_token(&self, token: &str) -> Result<Claims, ServiceError> {
+        // Load the refresh token secret key from environment variables.
+        let secret_key = env::var("JWT_REFRESH_SECRET").expect("JWT_REFRESH_SECRET must be set");
+
         // Decode and validate the token using the secret key.
         let token_data = jsonwebtoken::decode(
             token,
             &DecodingKey::from_secret(secret_key.as_ref()),
             &Validation::default(),
         )
-        .map_err(|e| ServiceError::JwtError(e))?;
+        .map_err(ServiceError::JwtError)?;
+
+        Ok(token_data.claims)
+    }
 
-        let claims: Claims = token_data.claims;
+    /// Verifies a refresh token without consuming it: the token must decode
+    /// and still be stored in Redis.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to verify.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Claims)` - Decoded claims if the token is active.
+    /// * `Err(ServiceError)` - An Actix web error if validation fails.
+    pub async fn verify_refresh_token(&self, token: &str) -> Result<Claims, ServiceError> {
+        let claims = self.decode_refresh_token(token)?;
 
-        // Generate a new access token using the claims from the refresh token.
-        jwt::JwtUtils::generate_access_token(&claims.id, &claims.role)
-            .map_err(|e| ServiceError::JwtError(e))
+        let record = self
+            .token_redis_repo
+            .get_refresh_token(token)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+
+        match record {
+            Some(_) => Ok(claims),
+            None => Err(ServiceError::UnAuthorizedError),
+        }
     }
 
-    /// Verifies an access token by:
-    /// - Decoding and validating the token using the `JWT_ACCESS_SECRET`.
+    /// Decodes an access token by:
+    /// - Looking up the signing key from the token's `kid` header.
+    /// - Decoding and validating the token with that key and its algorithm.
     /// - Checking that the token's expiration (`exp`) is still valid.
     ///
+    /// Revocation state in Redis is not checked, use `verify_access_token` to authenticate a request.
+    ///
     /// # Arguments
     ///
-    /// * `token` - The access token to verify.
+    /// * `token` - The access token to decode.
     ///
     /// # Returns
     ///
-    /// * `Ok(Claims)` - Decoded claims if the token is valid.
+    /// * `Ok(Claims)` - Decoded claims if the token is well formed and not expired.
     /// * `Err(ServiceError)` - An Actix web error if validation fails.
-    pub fn verify_access_token(&self, token: &str) -> Result<Claims, ServiceError> {
-        // Load the access token secret key from environment variables.
-        let secret_key = env::var("JWT_ACCESS_SECRET").expect("JWT_ACCESS_SECRET must be set");
+    pub fn decode_access_token(&self, token: &str) -> Result<Claims, ServiceError> {
+        // Reject tokens signed with an unknown or retired key.
+        let header = jsonwebtoken::decode_header(token).map_err(ServiceError::JwtError)?;
+        let key = match header
+            .kid
+            .and_then(|kid| jwt::ACCESS_KEYS.read().unwrap().find(&kid))
+        {
+            Some(key) => key,
+            None => {
+                info!("Unknown access token key id");
+                return Err(ServiceError::UnAuthorizedError);
+            }
+        };
 
-        // Decode and validate the access token.
-        let token_data: Claims = jsonwebtoken::decode(
-            token,
-            &DecodingKey::from_secret(secret_key.as_ref()),
-            &Validation::default(),
-        )
-        .map_err(|e| ServiceError::JwtError(e))?
-        .claims;
+        // Decode and validate the access token, only accepting the key's own algorithm.
+        // The audience of exchanged tokens is checked by whoever consumes them.
+        let mut validation = Validation::new(key.algorithm);
+        validation.validate_aud = false;
+        let token_data: Claims = jsonwebtoken::decode(token, &key.decoding_key, &validation)
+            .map_err(ServiceError::JwtError)?
+            .claims;
 
         // Check the token's expiration time against the current time.
         let now = SystemTime::now()
@@ -120,7 +310,196 @@ impl<T: TokenRedisRepository> TokenService<T> {
             return Err(ServiceError::UnAuthorizedError);
         }
 
-        // Return the valid token claims.
         Ok(token_data)
     }
+
+    /// Checks whether a decoded access token has been revoked, either because it was
+    /// issued before the user's last "log out everywhere" or the revocation of its session,
+    /// or because its `jti` is on the denylist.
+    /// Client tokens can only be revoked through the denylist.
+    ///
+    /// # Arguments
+    ///
+    /// * `claims` - The claims of the access token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - If the token was revoked.
+    /// * `Ok(false)` - If the token is still active.
+    /// * `Err(ServiceError)` - If Redis cannot be queried.
+    pub async fn is_access_token_revoked(&self, claims: &Claims) -> Result<bool, ServiceError> {
+        // Reject tokens issued before the user's sessions were revoked, client tokens have no sessions.
+        let valid_after = if claims.is_client() {
+            None
+        } else {
+            self.token_redis_repo
+                .get_tokens_valid_after(&claims.id)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    ServiceError::RedisError
+                })?
+        };
+
+        if valid_after.is_some_and(|t| claims.iat_ms < t) {
+            info!("Revoked access token");
+            return Ok(true);
+        }
+
+        // Reject tokens issued before their session was revoked.
+        if let Some(session_id) = &claims.sid {
+            let session_valid_after = self
+                .token_redis_repo
+                .get_session_tokens_valid_after(session_id)
+                .await
+                .map_err(|e| {
+                    error!("{}", e);
+                    ServiceError::RedisError
+                })?;
+
+            if session_valid_after.is_some_and(|t| claims.iat_ms < t) {
+                info!("Access token of a revoked session");
+                return Ok(true);
+            }
+        }
+
+        // Reject tokens that were individually revoked.
+        let denied = self
+            .token_redis_repo
+            .is_access_token_denied(&claims.jti)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+
+        if denied {
+            info!("Denied access token");
+        }
+
+        Ok(denied)
+    }
+
+    /// Verifies an access token by decoding it with `decode_access_token`
+    /// and rejecting it if `is_access_token_revoked` reports it as revoked.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The access token to verify.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Claims)` - Decoded claims if the token is valid.
+    /// * `Err(ServiceError)` - An Actix web error if validation fails.
+    pub async fn verify_access_token(&self, token: &str) -> Result<Claims, ServiceError> {
+        let claims = self.decode_access_token(token)?;
+
+        if self.is_access_token_revoked(&claims).await? {
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        // Return the valid token claims.
+        Ok(claims)
+    }
+
+    /// Revokes a refresh token together with the session it belongs to,
+    /// which also rejects the access tokens issued for the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to revoke.
+    /// * `client_id` - The client requesting the revocation, only its own tokens are revoked.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - If the token was a live refresh token and is now revoked.
+    /// * `Ok(false)` - If the token is not a live refresh token issued by this service.
+    /// * `Err(ServiceError)` - `Forbidden` if the token was issued to another client,
+    ///   or if Redis cannot be updated.
+    pub async fn revoke_refresh_token(
+        &self,
+        token: &str,
+        client_id: &str,
+    ) -> Result<bool, ServiceError> {
+        if self.decode_refresh_token(token).is_err() {
+            return Ok(false);
+        }
+
+        let record = self
+            .token_redis_repo
+            .get_refresh_token(token)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+        let record = match record {
+            Some(record) if record.client_id.as_deref() != Some(client_id) => {
+                info!("Revocation of a refresh token issued to another client");
+                return Err(ServiceError::Forbidden(String::from(
+                    "Token was issued to another client",
+                )));
+            }
+            Some(record) => record,
+            None => return Ok(false),
+        };
+
+        self.token_redis_repo
+            .delete_refresh_token(token)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+        self.token_redis_repo
+            .revoke_token_family(&record.family_id)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+
+        Ok(true)
+    }
+
+    /// Revokes an access token by adding its `jti` to the denylist for the rest of its lifetime.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The access token to revoke.
+    /// * `client_id` - The client requesting the revocation, only its own tokens are revoked.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - If the token was a valid access token and is now revoked.
+    /// * `Ok(false)` - If the token is not an access token issued by this service.
+    /// * `Err(ServiceError)` - `Forbidden` if the token was issued to another client,
+    ///   or if Redis cannot be updated.
+    pub async fn revoke_access_token(
+        &self,
+        token: &str,
+        client_id: &str,
+    ) -> Result<bool, ServiceError> {
+        let claims = match self.decode_access_token(token) {
+            Ok(claims) => claims,
+            Err(_) => return Ok(false),
+        };
+        if claims.issued_to() != Some(client_id) {
+            info!("Revocation of an access token issued to another client");
+            return Err(ServiceError::Forbidden(String::from(
+                "Token was issued to another client",
+            )));
+        }
+
+        let remaining = claims.exp as i64 - Utc::now().timestamp();
+        self.token_redis_repo
+            .deny_access_token(&claims.jti, remaining)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                ServiceError::RedisError
+            })?;
+
+        Ok(true)
+    }
 }
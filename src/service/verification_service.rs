@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use log::{error, info};
+
+use crate::{
+    config::mail,
+    error::service_error::ServiceError,
+    model::mail::Mail,
+    traits::{
+        account_trait::AccountRepository, mailer_trait::Mailer, redis_traits::TokenRedisRepository,
+    },
+    utils::jwt::JwtUtils,
+};
+
+/// Minimum number of seconds between two verification mails to the same address.
+const VERIFICATION_MAIL_INTERVAL: i64 = 60;
+
+/// Service responsible for verifying the email addresses of accounts.
+/// Verification links carry a signed, expiring token bound to the account and its address.
+pub struct VerificationService<R: AccountRepository, T: TokenRedisRepository, M: Mailer> {
+    /// Repository of the accounts being verified.
+    account_repo: Arc<R>,
+    /// Repository for Redis operations, used to throttle mail per address.
+    redis_repo: Arc<T>,
+    /// Transport verification mail is sent through.
+    mailer: Arc<M>,
+}
+
+impl<R: AccountRepository, T: TokenRedisRepository, M: Mailer> VerificationService<R, T, M> {
+    /// Creates a new instance of `VerificationService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_repo` - A shared reference to the account repository.
+    /// * `redis_repo` - A shared reference to the Redis repository.
+    /// * `mailer` - A shared reference to the mail transport.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `VerificationService`.
+    pub fn new(account_repo: Arc<R>, redis_repo: Arc<T>, mailer: Arc<M>) -> Self {
+        Self {
+            account_repo,
+            redis_repo,
+            mailer,
+        }
+    }
+
+    /// Sends a verification link to an email address, if it belongs to an account that is not verified yet.
+    /// Nothing tells the caller whether a mail was sent, so addresses cannot be probed.
+    /// At most one mail is sent to an address per `VERIFICATION_MAIL_INTERVAL`.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The address to verify.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the link was sent, or there was nothing to send.
+    /// * `Err(ServiceError)` - If the database cannot be read, or the mail cannot be sent.
+    pub async fn send_verification(&self, email: &str) -> Result<(), ServiceError> {
+        // Throttled before the lookup, so unknown addresses are throttled alike.
+        let allowed = self
+            .redis_repo
+            .throttle_verification_mail(email, VERIFICATION_MAIL_INTERVAL)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+        if !allowed {
+            info!("Verification mail throttled");
+            return Ok(());
+        }
+
+        let account = match self.account_repo.get_account_by_email(email).await {
+            Ok(account) => account,
+            Err(sqlx::Error::RowNotFound) => {
+                info!("No account to verify");
+                return Ok(());
+            }
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+
+        let address = match account.email {
+            Some(address) if !account.email_verified => address,
+            _ => {
+                info!("Account {} has nothing to verify", account.id);
+                return Ok(());
+            }
+        };
+
+        let token = JwtUtils::generate_email_verification_token(&account.id.to_string(), &address)
+            .map_err(ServiceError::JwtError)?;
+
+        let mail = Mail {
+            to: address,
+            subject: String::from("Verify your email address"),
+            body: format!(
+                "Hello {},\n\nFollow this link to verify your email address:\n{}{}\n\nThe link expires in 24 hours.",
+                account.username,
+                mail::email_verification_url(),
+                token
+            ),
+        };
+        self.mailer.send(&mail).await.map_err(|e| {
+            error!("Mail error: {}", e);
+            ServiceError::MailError(e)
+        })
+    }
+
+    /// Verifies the email address of an account from the token of a verification link.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The verification token.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the address is verified, verifying twice is not an error.
+    /// * `Err(ServiceError)` - `BadRequest` if the token is invalid, expired, or the address changed since.
+    pub async fn verify_email(&self, token: &str) -> Result<(), ServiceError> {
+        let claims = JwtUtils::decode_email_verification_token(token).map_err(|e| {
+            info!("Invalid email verification token: {}", e);
+            ServiceError::BadRequest(String::from("Invalid or expired verification token"))
+        })?;
+        let account_id = claims.sub.parse().map_err(ServiceError::InvalidIdFormat)?;
+
+        match self
+            .account_repo
+            .set_email_verified(account_id, &claims.email)
+            .await
+        {
+            Ok(0) => Err(ServiceError::BadRequest(String::from(
+                "Invalid or expired verification token",
+            ))),
+            Ok(_) => {
+                info!("Email address of account {} verified", account_id);
+                Ok(())
+            }
+            Err(e) => Err(ServiceError::DatabaseError(e)),
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use log::{error, info};
+
+use crate::{
+    config::{scope, webauthn},
+    error::service_error::ServiceError,
+    model::{
+        account::Account,
+        session::DeviceInfo,
+        token::Token,
+        webauthn::{
+            AuthenticationCredential, AuthenticatorSelection, CreationOptions,
+            CredentialDescriptor, CredentialParameters, PublicKeyCredentialCreationOptions,
+            PublicKeyCredentialRequestOptions, RegistrationCredential, RelyingParty,
+            RequestOptions, UserEntity, WebAuthnChallenge, WebAuthnCredential,
+        },
+    },
+    service::auth_service::{self, AuthService},
+    traits::{
+        account_trait::AccountRepository, redis_traits::TokenRedisRepository,
+        webauthn_trait::WebAuthnRepository,
+    },
+    utils::{self, jwt::Authentication, webauthn::SUPPORTED_ALGORITHMS},
+};
+
+/// Lifetime of a ceremony challenge in seconds, also the timeout advertised to the browser.
+const CHALLENGE_TTL: i64 = 300;
+
+/// `type` of the client data of registration ceremonies.
+const REGISTRATION_CEREMONY: &str = "webauthn.create";
+
+/// `type` of the client data of authentication ceremonies.
+const AUTHENTICATION_CEREMONY: &str = "webauthn.get";
+
+/// Service implementing passkey registration and passwordless login (WebAuthn Level 2).
+/// Successful logins start sessions through `AuthService`, exactly like a password login.
+pub struct WebAuthnService<R: AccountRepository, T: TokenRedisRepository, W: WebAuthnRepository> {
+    /// Service used to start sessions and issue tokens.
+    auth_service: Arc<AuthService<R, T>>,
+    /// Repository of the accounts credentials belong to.
+    account_repo: Arc<R>,
+    /// Repository for Redis operations, used to store ceremony challenges.
+    redis_repo: Arc<T>,
+    /// Repository of the registered credentials.
+    credential_repo: Arc<W>,
+}
+
+impl<R: AccountRepository, T: TokenRedisRepository, W: WebAuthnRepository>
+    WebAuthnService<R, T, W>
+{
+    /// Creates a new instance of `WebAuthnService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `auth_service` - A shared reference to the authentication service.
+    /// * `account_repo` - A shared reference to the account repository.
+    /// * `redis_repo` - A shared reference to the Redis repository.
+    /// * `credential_repo` - A shared reference to the credential repository.
+    ///
+    /// # Returns
+    ///
+    /// * New instance of `WebAuthnService`.
+    pub fn new(
+        auth_service: Arc<AuthService<R, T>>,
+        account_repo: Arc<R>,
+        redis_repo: Arc<T>,
+        credential_repo: Arc<W>,
+    ) -> Self {
+        Self {
+            auth_service,
+            account_repo,
+            redis_repo,
+            credential_repo,
+        }
+    }
+
+    /// Starts registering a credential for a logged in user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user registering a credential.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CreationOptions)` - The options to pass to `navigator.credentials.create()`.
+    /// * `Err(ServiceError)` - If the account cannot be read or the challenge cannot be stored.
+    pub async fn start_registration(&self, user_id: &str) -> Result<CreationOptions, ServiceError> {
+        let account = self.account(user_id).await?;
+        let credentials = self
+            .credential_repo
+            .list_credentials(account.id)
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+
+        let challenge = self
+            .store_challenge(REGISTRATION_CEREMONY, Some(user_id))
+            .await?;
+
+        Ok(CreationOptions {
+            public_key: PublicKeyCredentialCreationOptions {
+                challenge,
+                rp: RelyingParty {
+                    id: webauthn::rp_id(),
+                    name: webauthn::rp_name(),
+                },
+                user: UserEntity {
+                    id: URL_SAFE_NO_PAD.encode(user_id),
+                    name: account.username.clone(),
+                    display_name: account.username,
+                },
+                pub_key_cred_params: SUPPORTED_ALGORITHMS
+                    .iter()
+                    .map(|alg| CredentialParameters {
+                        kind: "public-key",
+                        alg: *alg,
+                    })
+                    .collect(),
+                timeout: CHALLENGE_TTL * 1000,
+                attestation: "none",
+                authenticator_selection: AuthenticatorSelection {
+                    resident_key: "preferred",
+                    user_verification: "required",
+                },
+                // Keep authenticators from registering twice.
+                exclude_credentials: credentials
+                    .into_iter()
+                    .map(|c| CredentialDescriptor {
+                        kind: "public-key",
+                        id: c.credential_id,
+                    })
+                    .collect(),
+            },
+        })
+    }
+
+    /// Finishes registering a credential, verifying the authenticator's response.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user registering the credential.
+    /// * `credential` - The credential returned by `navigator.credentials.create()`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(WebAuthnCredential)` - The registered credential.
+    /// * `Err(ServiceError)` - `BadRequest` if the response does not verify,
+    ///   `Conflict` if the credential is already registered, or a storage error.
+    pub async fn finish_registration(
+        &self,
+        user_id: &str,
+        credential: RegistrationCredential,
+    ) -> Result<WebAuthnCredential, ServiceError> {
+        let client_data_json =
+            utils::webauthn::decode(&credential.response.client_data_json).map_err(bad_request)?;
+        let challenge = utils::webauthn::verify_client_data(
+            &client_data_json,
+            REGISTRATION_CEREMONY,
+            &webauthn::origins(),
+        )
+        .map_err(bad_request)?;
+
+        let record = self.consume_challenge(&challenge).await?;
+        if record.ceremony != REGISTRATION_CEREMONY || record.user_id.as_deref() != Some(user_id) {
+            return Err(bad_request("Unknown or expired challenge"));
+        }
+
+        let attestation_object = utils::webauthn::decode(&credential.response.attestation_object)
+            .map_err(bad_request)?;
+        let authenticator_data = utils::webauthn::attested_authenticator_data(&attestation_object)
+            .map_err(bad_request)?;
+        let authenticator_data =
+            utils::webauthn::parse_authenticator_data(&authenticator_data, &webauthn::rp_id())
+                .map_err(bad_request)?;
+        let attested = authenticator_data
+            .credential
+            .ok_or_else(|| bad_request("No credential was created"))?;
+
+        let credential_id = URL_SAFE_NO_PAD.encode(&attested.id);
+        if utils::webauthn::decode(&credential.id).ok().as_deref() != Some(&attested.id[..]) {
+            return Err(bad_request(
+                "Credential id does not match the authenticator data",
+            ));
+        }
+
+        let stored = WebAuthnCredential {
+            credential_id,
+            account_id: parse_account_id(user_id)?,
+            public_key: attested.public_key,
+            sign_count: authenticator_data.sign_count as i64,
+            name: credential.name.filter(|name| !name.trim().is_empty()),
+            created_at: Utc::now().timestamp(),
+            last_used_at: None,
+        };
+        self.credential_repo
+            .insert_credential(&stored)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    ServiceError::Conflict(String::from("Credential is already registered"))
+                }
+                _ => ServiceError::DatabaseError(e),
+            })?;
+
+        info!("User {} registered a WebAuthn credential", user_id);
+        Ok(stored)
+    }
+
+    /// Starts a passkey login.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - The user logging in, to offer their credentials to non-discoverable
+    ///   authenticators. `None` lets any discoverable credential answer.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RequestOptions)` - The options to pass to `navigator.credentials.get()`.
+    /// * `Err(ServiceError)` - If the credentials cannot be read or the challenge cannot be stored.
+    pub async fn start_authentication(
+        &self,
+        username: Option<&str>,
+    ) -> Result<RequestOptions, ServiceError> {
+        let account = match username {
+            Some(username) => match self.account_repo.get_account_by_username(username).await {
+                Ok(account) => Some(account),
+                // Unknown users get the same response as users without credentials.
+                Err(sqlx::Error::RowNotFound) => None,
+                Err(e) => return Err(ServiceError::DatabaseError(e)),
+            },
+            None => None,
+        };
+
+        let allow_credentials = match &account {
+            Some(account) => self
+                .credential_repo
+                .list_credentials(account.id)
+                .await
+                .map_err(ServiceError::DatabaseError)?
+                .into_iter()
+                .map(|c| CredentialDescriptor {
+                    kind: "public-key",
+                    id: c.credential_id,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let user_id = account.map(|account| account.id.to_string());
+        let challenge = self
+            .store_challenge(AUTHENTICATION_CEREMONY, user_id.as_deref())
+            .await?;
+
+        Ok(RequestOptions {
+            public_key: PublicKeyCredentialRequestOptions {
+                challenge,
+                rp_id: webauthn::rp_id(),
+                timeout: CHALLENGE_TTL * 1000,
+                user_verification: "required",
+                allow_credentials,
+            },
+        })
+    }
+
+    /// Finishes a passkey login, verifying the assertion and starting a session.
+    /// A signature counter that did not increase points to a cloned authenticator and fails the login.
+    ///
+    /// # Arguments
+    ///
+    /// * `credential` - The credential returned by `navigator.credentials.get()`.
+    /// * `device` - The user agent and IP address the login comes from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Token)` - Struct containing the generated access and refresh tokens.
+    /// * `Err(ServiceError)` - `BadRequest` for malformed responses, `UnAuthorizedError`
+    ///   if the assertion does not verify, or a storage error.
+    pub async fn finish_authentication(
+        &self,
+        credential: AuthenticationCredential,
+        device: DeviceInfo,
+    ) -> Result<Token, ServiceError> {
+        let client_data_json =
+            utils::webauthn::decode(&credential.response.client_data_json).map_err(bad_request)?;
+        let challenge = utils::webauthn::verify_client_data(
+            &client_data_json,
+            AUTHENTICATION_CEREMONY,
+            &webauthn::origins(),
+        )
+        .map_err(bad_request)?;
+
+        let record = self.consume_challenge(&challenge).await?;
+        if record.ceremony != AUTHENTICATION_CEREMONY {
+            return Err(bad_request("Unknown or expired challenge"));
+        }
+
+        let credential_id =
+            URL_SAFE_NO_PAD.encode(utils::webauthn::decode(&credential.id).map_err(bad_request)?);
+        let stored = match self.credential_repo.get_credential(&credential_id).await {
+            Ok(stored) => stored,
+            Err(sqlx::Error::RowNotFound) => return Err(ServiceError::UnAuthorizedError),
+            Err(e) => return Err(ServiceError::DatabaseError(e)),
+        };
+        let user_id = stored.account_id.to_string();
+
+        // The credential must belong to the user the login was started for, and to the user handle.
+        if record
+            .user_id
+            .as_deref()
+            .is_some_and(|expected| expected != user_id)
+        {
+            return Err(ServiceError::UnAuthorizedError);
+        }
+        if let Some(user_handle) = &credential.response.user_handle {
+            let user_handle = utils::webauthn::decode(user_handle).map_err(bad_request)?;
+            if user_handle != user_id.as_bytes() {
+                return Err(ServiceError::UnAuthorizedError);
+            }
+        }
+
+        let authenticator_data = utils::webauthn::decode(&credential.response.authenticator_data)
+            .map_err(bad_request)?;
+        let signature =
+            utils::webauthn::decode(&credential.response.signature).map_err(bad_request)?;
+        let parsed =
+            utils::webauthn::parse_authenticator_data(&authenticator_data, &webauthn::rp_id())
+                .map_err(|e| {
+                    error!("WebAuthn assertion rejected: {}", e);
+                    ServiceError::UnAuthorizedError
+                })?;
+        utils::webauthn::verify_assertion(
+            &stored.public_key,
+            &authenticator_data,
+            &client_data_json,
+            &signature,
+        )
+        .map_err(|e| {
+            error!("WebAuthn assertion rejected: {}", e);
+            ServiceError::UnAuthorizedError
+        })?;
+
+        // Authenticators without a counter always report 0, any other counter must increase.
+        let updated = self
+            .credential_repo
+            .update_sign_count(
+                &credential_id,
+                parsed.sign_count as i64,
+                Utc::now().timestamp(),
+            )
+            .await
+            .map_err(ServiceError::DatabaseError)?;
+        if updated == 0 {
+            error!(
+                "Signature counter of credential {} did not increase, the authenticator may be cloned",
+                credential_id
+            );
+            return Err(ServiceError::UnAuthorizedError);
+        }
+
+        let account = self.account(&user_id).await?;
+        auth_service::check_login_policy(&account)?;
+
+        // Passkey logins hold every scope of their roles, like password logins.
+        let scope = scope::default_scope(&self.auth_service.roles_scopes(&account.roles).await?);
+        self.auth_service
+            .start_session(
+                &user_id,
+                &account.roles,
+                device,
+                None,
+                scope.as_deref(),
+                &Authentication::now(&["webauthn"]),
+            )
+            .await
+    }
+
+    /// Lists the credentials of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose credentials to list.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<WebAuthnCredential>)` - The credentials, most recent first.
+    /// * `Err(ServiceError)` - If a database error occurs.
+    pub async fn list_credentials(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<WebAuthnCredential>, ServiceError> {
+        self.credential_repo
+            .list_credentials(parse_account_id(user_id)?)
+            .await
+            .map_err(ServiceError::DatabaseError)
+    }
+
+    /// Removes a credential of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user owning the credential.
+    /// * `credential_id` - The credential to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the credential was removed.
+    /// * `Err(ServiceError)` - `NotFound` if the user has no such credential, or a database error.
+    pub async fn delete_credential(
+        &self,
+        user_id: &str,
+        credential_id: &str,
+    ) -> Result<(), ServiceError> {
+        match self
+            .credential_repo
+            .delete_credential(credential_id, parse_account_id(user_id)?)
+            .await
+        {
+            Ok(0) => Err(ServiceError::NotFound),
+            Ok(_) => Ok(()),
+            Err(e) => Err(ServiceError::DatabaseError(e)),
+        }
+    }
+
+    /// Reads the account of a user.
+    async fn account(&self, user_id: &str) -> Result<Account, ServiceError> {
+        match self
+            .account_repo
+            .get_account_by_id(parse_account_id(user_id)?)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(sqlx::Error::RowNotFound) => Err(ServiceError::NotFound),
+            Err(e) => Err(ServiceError::DatabaseError(e)),
+        }
+    }
+
+    /// Generates and stores the challenge of a new ceremony.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The base64url encoded challenge.
+    /// * `Err(ServiceError)` - If Redis storage fails.
+    async fn store_challenge(
+        &self,
+        ceremony: &str,
+        user_id: Option<&str>,
+    ) -> Result<String, ServiceError> {
+        let challenge = utils::random::random_token(32);
+        let record = WebAuthnChallenge {
+            ceremony: ceremony.to_string(),
+            user_id: user_id.map(String::from),
+        };
+
+        self.redis_repo
+            .store_webauthn_challenge(&challenge, &record, CHALLENGE_TTL)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?;
+
+        Ok(challenge)
+    }
+
+    /// Consumes the challenge of a ceremony, so every challenge is answered once.
+    async fn consume_challenge(&self, challenge: &str) -> Result<WebAuthnChallenge, ServiceError> {
+        self.redis_repo
+            .consume_webauthn_challenge(challenge)
+            .await
+            .map_err(|e| {
+                error!("Redis error: {}", e);
+                ServiceError::RedisError
+            })?
+            .ok_or_else(|| bad_request("Unknown or expired challenge"))
+    }
+}
+
+/// Parses the account id of a user token.
+fn parse_account_id(user_id: &str) -> Result<i32, ServiceError> {
+    user_id.parse().map_err(ServiceError::InvalidIdFormat)
+}
+
+/// Turns a verification failure into a `BadRequest` error.
+fn bad_request(message: impl Into<String>) -> ServiceError {
+    ServiceError::BadRequest(message.into())
+}
@@ -1,8 +1,40 @@
-use crate::model::account::Account;
+use crate::model::{account::Account, mfa::TotpSecret};
 
 pub trait AccountRepository: Send + Sync {
-    async fn insert_account(&self, username: String, password: String) -> Result<u64, sqlx::Error>;
+    async fn insert_account(
+        &self,
+        username: String,
+        password: String,
+        email: String,
+    ) -> Result<u64, sqlx::Error>;
     async fn get_account_by_username(&self, username: &str) -> Result<Account, sqlx::Error>;
     async fn get_account_by_id(&self, id: i32) -> Result<Account, sqlx::Error>;
+    async fn get_account_by_email(&self, email: &str) -> Result<Account, sqlx::Error>;
+    async fn get_roles_scopes(&self, roles: &[String]) -> Result<Vec<String>, sqlx::Error>;
     async fn is_account_exist(&self, username: &str) -> Result<(), sqlx::Error>;
+    async fn set_email_verified(&self, id: i32, email: &str) -> Result<u64, sqlx::Error>;
+    async fn update_password(&self, id: i32, password: &str) -> Result<u64, sqlx::Error>;
+    async fn update_account(
+        &self,
+        id: i32,
+        username: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<u64, sqlx::Error>;
+    async fn get_totp(&self, account_id: i32) -> Result<TotpSecret, sqlx::Error>;
+    async fn upsert_totp(&self, account_id: i32, secret: &str) -> Result<u64, sqlx::Error>;
+    async fn confirm_totp(
+        &self,
+        account_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error>;
+    async fn use_totp_step(&self, account_id: i32, step: i64) -> Result<u64, sqlx::Error>;
+    async fn use_recovery_code(&self, account_id: i32, code_hash: &str)
+        -> Result<u64, sqlx::Error>;
+    async fn replace_recovery_codes(
+        &self,
+        account_id: i32,
+        recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error>;
+    async fn delete_totp(&self, account_id: i32) -> Result<u64, sqlx::Error>;
 }
@@ -0,0 +1,22 @@
+use crate::model::client::Client;
+
+pub trait ClientRepository: Send + Sync {
+    async fn get_client_by_id(&self, client_id: &str) -> Result<Client, sqlx::Error>;
+    async fn list_clients(&self) -> Result<Vec<Client>, sqlx::Error>;
+    async fn insert_client(&self, client: &Client) -> Result<u64, sqlx::Error>;
+    async fn update_client_secret(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<u64, sqlx::Error>;
+    async fn update_redirect_uris(
+        &self,
+        client_id: &str,
+        redirect_uris: &[String],
+    ) -> Result<u64, sqlx::Error>;
+    async fn set_client_disabled(
+        &self,
+        client_id: &str,
+        disabled: bool,
+    ) -> Result<u64, sqlx::Error>;
+}
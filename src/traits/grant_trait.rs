@@ -0,0 +1,14 @@
+use crate::model::grant::Grant;
+
+pub trait GrantRepository: Send + Sync {
+    async fn get_grant(&self, account_id: i32, client_id: &str) -> Result<Grant, sqlx::Error>;
+    async fn list_grants(&self, account_id: i32) -> Result<Vec<Grant>, sqlx::Error>;
+    async fn upsert_grant(
+        &self,
+        account_id: i32,
+        client_id: &str,
+        scopes: &[String],
+        granted_at: i64,
+    ) -> Result<u64, sqlx::Error>;
+    async fn delete_grant(&self, account_id: i32, client_id: &str) -> Result<u64, sqlx::Error>;
+}
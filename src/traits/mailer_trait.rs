@@ -0,0 +1,5 @@
+use crate::{error::mail_error::MailError, model::mail::Mail};
+
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError>;
+}
@@ -1,2 +1,7 @@
 pub mod account_trait;
+pub mod client_trait;
+pub mod grant_trait;
+pub mod mailer_trait;
+pub mod rbac_trait;
 pub mod redis_traits;
+pub mod webauthn_trait;
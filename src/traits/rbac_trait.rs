@@ -0,0 +1,20 @@
+use crate::model::rbac::{NewRole, Permission, Role, RoleInheritance, RolePermission};
+
+pub trait RbacRepository: Send + Sync {
+    async fn list_roles(&self) -> Result<Vec<Role>, sqlx::Error>;
+    async fn insert_role(&self, role: &NewRole) -> Result<u64, sqlx::Error>;
+    async fn delete_role(&self, name: &str) -> Result<u64, sqlx::Error>;
+    async fn list_permissions(&self) -> Result<Vec<Permission>, sqlx::Error>;
+    async fn insert_permission(&self, permission: &Permission) -> Result<u64, sqlx::Error>;
+    async fn delete_permission(&self, name: &str) -> Result<u64, sqlx::Error>;
+    async fn grant_permission(&self, role: &str, permission: &str) -> Result<u64, sqlx::Error>;
+    async fn revoke_permission(&self, role: &str, permission: &str) -> Result<u64, sqlx::Error>;
+    async fn list_role_permissions(&self) -> Result<Vec<RolePermission>, sqlx::Error>;
+    async fn list_role_inheritance(&self) -> Result<Vec<RoleInheritance>, sqlx::Error>;
+    async fn insert_role_inheritance(&self, role: &str, inherits: &str)
+        -> Result<u64, sqlx::Error>;
+    async fn delete_role_inheritance(&self, role: &str, inherits: &str)
+        -> Result<u64, sqlx::Error>;
+    async fn assign_account_role(&self, account_id: i32, role: &str) -> Result<u64, sqlx::Error>;
+    async fn unassign_account_role(&self, account_id: i32, role: &str) -> Result<u64, sqlx::Error>;
+}
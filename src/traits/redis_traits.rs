@@ -1,12 +1,160 @@
-use crate::error::redis_error::RedisError;
+use crate::{
+    error::redis_error::RedisError,
+    model::{
+        mfa::MfaChallenge,
+        oauth::{AuthorizationCode, ConsentRequest, DeviceAuthorization},
+        session::{LoginSession, Session},
+        token::RefreshTokenRecord,
+        webauthn::WebAuthnChallenge,
+    },
+};
 
 pub trait TokenRedisRepository: Send + Sync {
     async fn store_refresh_token(
         &self,
         user_id: &str,
+        family_id: &str,
+        client_id: Option<&str>,
         token: &str,
         ttl: i64,
     ) -> Result<(), RedisError>;
-    async fn is_refresh_token_valid(&self, token: &str) -> Result<bool, RedisError>;
+    async fn get_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError>;
+    async fn rotate_refresh_token(
+        &self,
+        token: &str,
+        used_ttl: i64,
+        new_token: &str,
+        ttl: i64,
+        access_jti: &str,
+        access_exp: i64,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError>;
+    async fn get_used_token_family(&self, token: &str) -> Result<Option<String>, RedisError>;
+    async fn revoke_token_family(&self, family_id: &str) -> Result<(), RedisError>;
     async fn delete_refresh_token(&self, token: &str) -> Result<(), RedisError>;
+    async fn delete_family_tokens(&self, family_id: &str) -> Result<(), RedisError>;
+    async fn create_session(&self, session: &Session, ttl: i64) -> Result<(), RedisError>;
+    async fn touch_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        last_used_at: i64,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, RedisError>;
+    async fn revoke_user_sessions(&self, user_id: &str) -> Result<(), RedisError>;
+    async fn revoke_client_sessions(&self, client_id: &str) -> Result<(), RedisError>;
+    async fn set_tokens_valid_after(
+        &self,
+        user_id: &str,
+        timestamp: i64,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn get_tokens_valid_after(&self, user_id: &str) -> Result<Option<i64>, RedisError>;
+    async fn get_session_tokens_valid_after(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<i64>, RedisError>;
+    async fn set_session_access_token(
+        &self,
+        session_id: &str,
+        jti: &str,
+        exp: i64,
+    ) -> Result<(), RedisError>;
+    async fn deny_access_token(&self, jti: &str, ttl: i64) -> Result<(), RedisError>;
+    async fn is_access_token_denied(&self, jti: &str) -> Result<bool, RedisError>;
+    async fn store_authorization_code(
+        &self,
+        code: &str,
+        authorization: &AuthorizationCode,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn consume_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, RedisError>;
+    async fn store_device_authorization(
+        &self,
+        device_code: &str,
+        authorization: &DeviceAuthorization,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn find_device_code(&self, user_code: &str) -> Result<Option<String>, RedisError>;
+    async fn get_device_authorization(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError>;
+    async fn update_device_authorization(
+        &self,
+        device_code: &str,
+        authorization: &DeviceAuthorization,
+    ) -> Result<bool, RedisError>;
+    async fn consume_device_authorization(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError>;
+    async fn store_consent_request(
+        &self,
+        consent_id: &str,
+        request: &ConsentRequest,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn get_consent_request(
+        &self,
+        consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError>;
+    async fn consume_consent_request(
+        &self,
+        consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError>;
+    async fn throttle_device_poll(
+        &self,
+        device_code: &str,
+        interval: i64,
+    ) -> Result<bool, RedisError>;
+    async fn store_mfa_challenge(
+        &self,
+        token: &str,
+        challenge: &MfaChallenge,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn get_mfa_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, RedisError>;
+    async fn consume_mfa_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, RedisError>;
+    async fn revoke_mfa_challenges(&self, user_id: &str) -> Result<(), RedisError>;
+    async fn record_mfa_failure(&self, token: &str, ttl: i64) -> Result<i64, RedisError>;
+    async fn record_account_mfa_failure(&self, user_id: &str, ttl: i64) -> Result<i64, RedisError>;
+    async fn get_account_mfa_failures(&self, user_id: &str) -> Result<i64, RedisError>;
+    async fn clear_account_mfa_failures(&self, user_id: &str) -> Result<(), RedisError>;
+    async fn store_webauthn_challenge(
+        &self,
+        challenge: &str,
+        record: &WebAuthnChallenge,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn consume_webauthn_challenge(
+        &self,
+        challenge: &str,
+    ) -> Result<Option<WebAuthnChallenge>, RedisError>;
+    async fn store_password_reset(
+        &self,
+        token: &str,
+        user_id: &str,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn throttle_verification_mail(
+        &self,
+        email: &str,
+        interval: i64,
+    ) -> Result<bool, RedisError>;
+    async fn consume_password_reset(&self, token: &str) -> Result<Option<String>, RedisError>;
+    async fn revoke_password_resets(&self, user_id: &str) -> Result<(), RedisError>;
+    async fn store_login_session(
+        &self,
+        token: &str,
+        login: &LoginSession,
+        ttl: i64,
+    ) -> Result<(), RedisError>;
+    async fn get_login_session(&self, token: &str) -> Result<Option<LoginSession>, RedisError>;
 }
@@ -0,0 +1,21 @@
+use crate::model::webauthn::WebAuthnCredential;
+
+pub trait WebAuthnRepository: Send + Sync {
+    async fn insert_credential(&self, credential: &WebAuthnCredential) -> Result<u64, sqlx::Error>;
+    async fn get_credential(&self, credential_id: &str) -> Result<WebAuthnCredential, sqlx::Error>;
+    async fn list_credentials(
+        &self,
+        account_id: i32,
+    ) -> Result<Vec<WebAuthnCredential>, sqlx::Error>;
+    async fn update_sign_count(
+        &self,
+        credential_id: &str,
+        sign_count: i64,
+        used_at: i64,
+    ) -> Result<u64, sqlx::Error>;
+    async fn delete_credential(
+        &self,
+        credential_id: &str,
+        account_id: i32,
+    ) -> Result<u64, sqlx::Error>;
+}
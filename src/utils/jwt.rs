@@ -1,21 +1,185 @@
-use std::env;
+use std::{
+    env,
+    sync::{Arc, RwLock},
+};
 
 use chrono::{Duration, Utc};
-use jsonwebtoken::{EncodingKey, Header};
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use lazy_static::lazy_static;
 use log::info;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{
+    jwt::{self, KeyRing, SigningKey},
+    oidc,
+};
+
+/// `acr` of single factor authentications.
+pub const ACR_SINGLE_FACTOR: &str = "aal1";
+
+/// `acr` of multi-factor or passkey authentications.
+pub const ACR_MFA: &str = "aal2";
+
+/// Kind of principal a token was issued to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SubjectType {
+    /// A user account, `id` is the account id.
+    #[default]
+    User,
+    /// An OAuth client acting on its own behalf, `id` is the client id.
+    Client,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
+    /// Account id for user tokens, client id for client tokens.
     pub id: String,
-    pub role: String,
+    /// Roles assigned to the account, or the role registered for the client.
+    /// Inherited roles are resolved when checking permissions.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub exp: usize,
+    #[serde(default)]
+    pub iat: usize,
+    /// Issue time in milliseconds, compared against the user's revocation watermark.
+    /// `iat` only has second precision, too coarse to tell a logout from a login in the same second.
+    #[serde(default)]
+    pub iat_ms: i64,
+    /// Unique token identifier, so two tokens issued in the same second never collide.
+    pub jti: String,
+    /// Id of the login session the token was issued for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// Whether `id` identifies a user or a client.
+    #[serde(default)]
+    pub sub_type: SubjectType,
+    /// Service the token is restricted to, set on tokens minted by token exchange.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// Space separated scopes the token is restricted to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Party acting on behalf of the subject, set on tokens minted by token exchange.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    /// OAuth client the token was issued to (RFC 9068), absent on tokens of direct logins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Time the user last authenticated, kept when the tokens are refreshed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    /// Authentication methods the user last authenticated with (RFC 8176), such as `pwd`, `otp` or `webauthn`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    /// Authentication context class reached by `amr`, see `Authentication::acr`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+}
+
+/// How and when the user behind a session last authenticated.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Authentication {
+    /// Unix timestamp of the authentication.
+    pub auth_time: i64,
+    /// Authentication methods used (RFC 8176).
+    pub amr: Vec<String>,
+}
+
+impl Authentication {
+    /// Records an authentication happening now.
+    pub fn now(amr: &[&str]) -> Self {
+        Self {
+            auth_time: Utc::now().timestamp(),
+            amr: amr.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    /// Authentication context class reached: `aal2` with a second factor or a passkey, `aal1` otherwise.
+    pub fn acr(&self) -> &'static str {
+        if self.amr.iter().any(|m| m == "otp" || m == "webauthn") {
+            ACR_MFA
+        } else {
+            ACR_SINGLE_FACTOR
+        }
+    }
+}
+
+/// The `act` claim of a delegated token (RFC 8693 section 4.1).
+/// A nested `act` records the previous actors of a delegation chain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+impl Claims {
+    /// Returns true if the token was issued to a client through the `client_credentials` grant.
+    pub fn is_client(&self) -> bool {
+        self.sub_type == SubjectType::Client
+    }
+
+    /// Returns the OAuth client the token was issued to: the actor of an exchanged token,
+    /// the `client_id` claim otherwise, `None` for tokens of direct logins.
+    pub fn issued_to(&self) -> Option<&str> {
+        match &self.act {
+            Some(actor) => Some(&actor.sub),
+            None => self.client_id.as_deref(),
+        }
+    }
+
+    /// Returns how the user behind the token authenticated.
+    /// Tokens issued before authentications were recorded report a time of 0.
+    pub fn authentication(&self) -> Authentication {
+        Authentication {
+            auth_time: self.auth_time.unwrap_or_default() as i64,
+            amr: self.amr.clone(),
+        }
+    }
+}
+
+/// Claims of an OpenID Connect ID token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    /// Account id of the authenticated user.
+    pub sub: String,
+    /// Client id of the relying party.
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    /// Time the user authenticated.
+    pub auth_time: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// Claims of an email verification token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailVerificationClaims {
+    /// Account id of the address owner.
+    pub sub: String,
+    /// Address being verified, the token is void once the account's address changes.
+    pub email: String,
     pub exp: usize,
+    pub iat: usize,
 }
 
 lazy_static! {
     pub static ref ACCESS_TOKEN_EXPIRY: Duration = Duration::seconds(20);
     pub static ref REFRESH_TOKEN_EXPIRY: Duration = Duration::minutes(1);
+    pub static ref EMAIL_VERIFICATION_EXPIRY: Duration = Duration::hours(24);
+    /// Keys used to sign and verify access tokens, loaded from the environment.
+    pub static ref ACCESS_KEYS: RwLock<KeyRing> =
+        RwLock::new(jwt::load_key_ring(ACCESS_TOKEN_EXPIRY.num_seconds()));
+    /// Asymmetric key signing ID tokens when access tokens use the private HS256 secret,
+    /// `None` when ID tokens are signed with the access token keys.
+    pub static ref ID_TOKEN_KEY: Option<Arc<SigningKey>> =
+        (ACCESS_KEYS.read().unwrap().current().algorithm == Algorithm::HS256).then(|| {
+            Arc::new(jwt::load_id_token_key().unwrap_or_else(|e| panic!("{e}")))
+        });
 }
 
 pub struct JwtUtils;
@@ -25,35 +189,193 @@ impl JwtUtils {
         REFRESH_TOKEN_EXPIRY.num_seconds()
     }
 
+    pub fn get_access_exp() -> i64 {
+        ACCESS_TOKEN_EXPIRY.num_seconds()
+    }
+
+    /// Generates a signed access token restricted to `scope`, recording how the user authenticated
+    /// and the OAuth client the session belongs to, `None` for direct logins.
+    /// The claims are returned alongside the token so callers can track its `jti` and `exp`.
     pub fn generate_access_token(
         user_id: &str,
-        role: &str,
-    ) -> Result<String, jsonwebtoken::errors::Error> {
-        let secret_key = env::var("JWT_ACCESS_SECRET").expect("JWT_ACCESS_SECRET must be set");
+        roles: &[String],
+        session_id: &str,
+        client_id: Option<&str>,
+        scope: Option<&str>,
+        authentication: &Authentication,
+    ) -> Result<(String, Claims), jsonwebtoken::errors::Error> {
+        let key = ACCESS_KEYS.read().unwrap().current();
         let claims = Claims {
             id: user_id.to_string(),
-            role: role.to_string(),
+            roles: roles.to_vec(),
             exp: (Utc::now() + *ACCESS_TOKEN_EXPIRY).timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+            iat_ms: Utc::now().timestamp_millis(),
+            jti: Uuid::new_v4().to_string(),
+            sid: Some(session_id.to_string()),
+            sub_type: SubjectType::User,
+            aud: None,
+            scope: scope.map(String::from),
+            act: None,
+            client_id: client_id.map(String::from),
+            auth_time: Some(authentication.auth_time as usize),
+            amr: authentication.amr.clone(),
+            acr: Some(authentication.acr().to_string()),
         };
 
         info!("Access token generated");
 
-        jsonwebtoken::encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret_key.as_ref()),
-        )
+        // Publish the key id so verifiers can pick the matching public key.
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        let token = jsonwebtoken::encode(&header, &claims, &key.encoding_key)?;
+        Ok((token, claims))
     }
 
+    /// Generates a signed access token for a client acting on its own behalf.
+    /// Client tokens belong to no session and no refresh token is issued with them.
+    pub fn generate_client_token(
+        client_id: &str,
+        role: &str,
+        scope: Option<&str>,
+    ) -> Result<(String, Claims), jsonwebtoken::errors::Error> {
+        let key = ACCESS_KEYS.read().unwrap().current();
+        let claims = Claims {
+            id: client_id.to_string(),
+            roles: vec![role.to_string()],
+            exp: (Utc::now() + *ACCESS_TOKEN_EXPIRY).timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+            iat_ms: Utc::now().timestamp_millis(),
+            jti: Uuid::new_v4().to_string(),
+            sid: None,
+            sub_type: SubjectType::Client,
+            aud: None,
+            scope: scope.map(String::from),
+            act: None,
+            client_id: Some(client_id.to_string()),
+            auth_time: None,
+            amr: Vec::new(),
+            acr: None,
+        };
+
+        info!("Client access token generated");
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        let token = jsonwebtoken::encode(&header, &claims, &key.encoding_key)?;
+        Ok((token, claims))
+    }
+
+    /// Generates a delegated access token from a subject token (RFC 8693).
+    /// The new token keeps the subject, is restricted to `roles`, `audience` and `scope`,
+    /// records `actor` in its `act` claim and never outlives the subject token.
+    /// It belongs to no session and carries no authentication of the user, so it can neither
+    /// end the subject's session nor pass for a recent login.
+    pub fn generate_exchanged_token(
+        subject: &Claims,
+        roles: Vec<String>,
+        audience: Option<String>,
+        scope: Option<String>,
+        actor: Actor,
+    ) -> Result<(String, Claims), jsonwebtoken::errors::Error> {
+        let key = ACCESS_KEYS.read().unwrap().current();
+        let exp = (Utc::now() + *ACCESS_TOKEN_EXPIRY).timestamp() as usize;
+        let claims = Claims {
+            id: subject.id.clone(),
+            roles,
+            exp: exp.min(subject.exp),
+            iat: Utc::now().timestamp() as usize,
+            iat_ms: Utc::now().timestamp_millis(),
+            jti: Uuid::new_v4().to_string(),
+            sid: None,
+            sub_type: subject.sub_type,
+            aud: audience,
+            scope,
+            act: Some(actor),
+            client_id: subject.client_id.clone(),
+            auth_time: None,
+            amr: Vec::new(),
+            acr: None,
+        };
+
+        info!("Exchanged access token generated");
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        let token = jsonwebtoken::encode(&header, &claims, &key.encoding_key)?;
+        Ok((token, claims))
+    }
+
+    /// Generates an OpenID Connect ID token, signed with an asymmetric key
+    /// so relying parties can verify it with the published JWKS.
+    pub fn generate_id_token(
+        user_id: &str,
+        client_id: &str,
+        nonce: Option<String>,
+        auth_time: i64,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let key = Self::id_token_key();
+        let claims = IdTokenClaims {
+            iss: oidc::issuer(),
+            sub: user_id.to_string(),
+            aud: client_id.to_string(),
+            exp: (Utc::now() + *ACCESS_TOKEN_EXPIRY).timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+            auth_time: auth_time as usize,
+            nonce,
+        };
+
+        info!("ID token generated");
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        jsonwebtoken::encode(&header, &claims, &key.encoding_key)
+    }
+
+    /// Returns the algorithm ID tokens are currently signed with.
+    pub fn id_token_signing_algorithm() -> Algorithm {
+        Self::id_token_key().algorithm
+    }
+
+    /// Returns the key signing ID tokens: the dedicated ID token key under HS256,
+    /// the current access token key otherwise.
+    fn id_token_key() -> Arc<SigningKey> {
+        match &*ID_TOKEN_KEY {
+            Some(key) => key.clone(),
+            None => ACCESS_KEYS.read().unwrap().current(),
+        }
+    }
+
+    /// Generates a refresh token. It carries the scope and the authentication of the session,
+    /// so rotated access tokens keep those of the first one.
     pub fn generate_refresh_token(
         user_id: &str,
-        role: &str,
+        roles: &[String],
+        session_id: &str,
+        scope: Option<&str>,
+        authentication: &Authentication,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let secret_key = env::var("JWT_REFRESH_SECRET").expect("JWT_REFRESH_SECRET must be set");
         let claims = Claims {
             id: user_id.to_string(),
-            role: role.to_string(),
+            roles: roles.to_vec(),
             exp: (Utc::now() + *REFRESH_TOKEN_EXPIRY).timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+            iat_ms: Utc::now().timestamp_millis(),
+            jti: Uuid::new_v4().to_string(),
+            sid: Some(session_id.to_string()),
+            sub_type: SubjectType::User,
+            aud: None,
+            scope: scope.map(String::from),
+            act: None,
+            client_id: None,
+            auth_time: Some(authentication.auth_time as usize),
+            amr: authentication.amr.clone(),
+            acr: Some(authentication.acr().to_string()),
         };
 
         info!("Refresh token generated");
@@ -64,4 +386,64 @@ impl JwtUtils {
             &EncodingKey::from_secret(secret_key.as_ref()),
         )
     }
+
+    /// Generates a token proving the owner of an account receives mail at `email`,
+    /// signed with the `EMAIL_VERIFICATION_SECRET`.
+    pub fn generate_email_verification_token(
+        user_id: &str,
+        email: &str,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let secret_key =
+            env::var("EMAIL_VERIFICATION_SECRET").expect("EMAIL_VERIFICATION_SECRET must be set");
+        let claims = EmailVerificationClaims {
+            sub: user_id.to_string(),
+            email: email.to_string(),
+            exp: (Utc::now() + *EMAIL_VERIFICATION_EXPIRY).timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+        };
+
+        info!("Email verification token generated");
+
+        jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret_key.as_ref()),
+        )
+    }
+
+    /// Decodes an email verification token, validating its signature and expiration.
+    pub fn decode_email_verification_token(
+        token: &str,
+    ) -> Result<EmailVerificationClaims, jsonwebtoken::errors::Error> {
+        let secret_key =
+            env::var("EMAIL_VERIFICATION_SECRET").expect("EMAIL_VERIFICATION_SECRET must be set");
+
+        jsonwebtoken::decode(
+            token,
+            &DecodingKey::from_secret(secret_key.as_ref()),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+    }
+
+    /// Returns the public keys that verify access and ID tokens, as a JWK set.
+    pub fn jwks() -> JwkSet {
+        let mut jwks = ACCESS_KEYS.read().unwrap().jwks();
+        if let Some(jwk) = ID_TOKEN_KEY.as_ref().and_then(|key| key.jwk.clone()) {
+            jwks.keys.push(jwk);
+        }
+        jwks
+    }
+
+    /// Reloads the signing keys, the newest key signs from now on.
+    /// Returns the key id of the signing key, or the reason the keys could not be loaded.
+    pub fn rotate_signing_key() -> Result<String, String> {
+        // Read the keys from disk before taking the write lock.
+        let keys = jwt::load_keys()?;
+
+        Ok(ACCESS_KEYS
+            .write()
+            .unwrap()
+            .reload(keys, ACCESS_TOKEN_EXPIRY.num_seconds()))
+    }
 }
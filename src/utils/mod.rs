@@ -1,2 +1,7 @@
 pub mod jwt;
 pub mod password;
+pub mod path_pattern;
+pub mod policy;
+pub mod random;
+pub mod totp;
+pub mod webauthn;
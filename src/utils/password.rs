@@ -18,7 +18,16 @@ impl Hasher {
     }
 
     pub fn verify_password(password: &str, hash: &str) -> Result<(), password_hash::errors::Error> {
-        let parsed_hash = PasswordHash::new(&hash)?;
+        let parsed_hash = PasswordHash::new(hash)?;
         Argon2::default().verify_password(password.as_bytes(), &parsed_hash)
     }
 }
+
+/// Compares two secrets in time independent of where they differ.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
@@ -0,0 +1,168 @@
+/// One segment of a path pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Matches exactly this segment.
+    Literal(String),
+    /// `{name}`, matches any single segment.
+    Param,
+    /// A trailing `*`, matches the rest of the path, possibly empty.
+    Rest,
+}
+
+/// A path pattern such as `/api/admin/users/{id}` or `/api/admin/*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+impl PathPattern {
+    /// Parses a path pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern, starting with `/`. A `{name}` segment matches one segment
+    ///   and a `*` last segment matches the rest of the path.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(PathPattern)` - The parsed pattern.
+    /// * `Err(String)` - A description of why the pattern is invalid.
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let path = pattern
+            .strip_prefix('/')
+            .ok_or_else(|| String::from("Path pattern must start with /"))?;
+
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            let segment = if *part == "*" {
+                if i + 1 != parts.len() {
+                    return Err(String::from("* is only allowed as the last segment"));
+                }
+                Segment::Rest
+            } else if part.starts_with('{') && part.ends_with('}') && part.len() > 2 {
+                Segment::Param
+            } else if part.contains(['{', '}', '*']) {
+                return Err(format!("Invalid path segment {}", part));
+            } else {
+                Segment::Literal(part.to_string())
+            };
+            segments.push(segment);
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Checks whether a request path matches the pattern.
+    pub fn matches(&self, path: &str) -> bool {
+        let mut parts = path.split('/').filter(|s| !s.is_empty());
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest => return true,
+                Segment::Param => {
+                    if parts.next().is_none() {
+                        return false;
+                    }
+                }
+                Segment::Literal(literal) => {
+                    if parts.next() != Some(literal.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        parts.next().is_none()
+    }
+
+    /// Ranks how specific the pattern is, so the longest match can be picked among
+    /// overlapping patterns: more segments first, then more literal segments,
+    /// then exact patterns over `*` patterns.
+    pub fn specificity(&self) -> (usize, usize, bool) {
+        let rest = self.segments.last() == Some(&Segment::Rest);
+        let literals = self
+            .segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Literal(_)))
+            .count();
+
+        (self.segments.len() - rest as usize, literals, !rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> PathPattern {
+        PathPattern::parse(pattern).unwrap()
+    }
+
+    #[test]
+    fn parse_rejects_invalid_patterns() {
+        assert!(PathPattern::parse("api/users").is_err());
+        assert!(PathPattern::parse("/api/*/users").is_err());
+        assert!(PathPattern::parse("/api/{}").is_err());
+        assert!(PathPattern::parse("/api/user{id}").is_err());
+        assert!(PathPattern::parse("/api/users*").is_err());
+    }
+
+    #[test]
+    fn parse_ignores_empty_segments() {
+        assert_eq!(pattern("/api//users/"), pattern("/api/users"));
+        assert_eq!(pattern("/"), PathPattern { segments: vec![] });
+    }
+
+    #[test]
+    fn literal_matches_exact_path() {
+        let p = pattern("/api/users");
+        assert!(p.matches("/api/users"));
+        assert!(p.matches("/api/users/"));
+        assert!(!p.matches("/api/users/1"));
+        assert!(!p.matches("/api"));
+        assert!(!p.matches("/api/accounts"));
+    }
+
+    #[test]
+    fn parameter_matches_one_segment() {
+        let p = pattern("/api/users/{id}");
+        assert!(p.matches("/api/users/42"));
+        assert!(!p.matches("/api/users"));
+        assert!(!p.matches("/api/users/42/sessions"));
+
+        let p = pattern("/api/users/{id}/sessions/{sid}");
+        assert!(p.matches("/api/users/42/sessions/abc"));
+        assert!(!p.matches("/api/users/42/grants/abc"));
+    }
+
+    #[test]
+    fn wildcard_matches_rest_of_path() {
+        let p = pattern("/api/admin/*");
+        assert!(p.matches("/api/admin"));
+        assert!(p.matches("/api/admin/users"));
+        assert!(p.matches("/api/admin/users/42/disable"));
+        assert!(!p.matches("/api/administrators"));
+        assert!(!p.matches("/api/users"));
+
+        assert!(pattern("/*").matches("/anything/at/all"));
+    }
+
+    #[test]
+    fn specificity_prefers_longer_patterns() {
+        assert!(pattern("/api/admin/users").specificity() > pattern("/api/admin").specificity());
+        assert!(pattern("/api/users/{id}").specificity() > pattern("/api/*").specificity());
+    }
+
+    #[test]
+    fn specificity_prefers_literals_over_parameters() {
+        assert!(pattern("/api/users/me").specificity() > pattern("/api/users/{id}").specificity());
+        assert!(pattern("/api/{a}/me").specificity() > pattern("/api/{a}/{b}").specificity());
+    }
+
+    #[test]
+    fn specificity_prefers_exact_over_wildcard() {
+        assert!(pattern("/api/admin").specificity() > pattern("/api/admin/*").specificity());
+        assert!(pattern("/api/admin/*").specificity() > pattern("/api/*").specificity());
+    }
+}
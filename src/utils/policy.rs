@@ -0,0 +1,158 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
+
+use actix_web::HttpRequest;
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::utils::jwt::Claims;
+
+/// Attributes of a request a policy is evaluated against.
+pub struct PolicyContext {
+    /// Claims of the verified access token.
+    pub claims: Claims,
+    /// Roles of the subject, inherited roles included.
+    pub roles: HashSet<String>,
+    /// Path parameters of the matched route.
+    pub params: HashMap<String, String>,
+    /// Address of the direct peer. Forwarded headers are ignored, they are set by the client.
+    pub ip: Option<IpAddr>,
+    /// Time the request is evaluated at.
+    pub time: DateTime<Utc>,
+}
+
+impl PolicyContext {
+    /// Collects the attributes of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The request, matched against its resource so path parameters are known.
+    /// * `claims` - The claims of the verified access token.
+    /// * `roles` - The roles of the subject, inherited roles included.
+    ///
+    /// # Returns
+    ///
+    /// * The context to evaluate policies against.
+    pub fn from_request(req: &HttpRequest, claims: Claims, roles: HashSet<String>) -> Self {
+        Self {
+            claims,
+            roles,
+            params: req
+                .match_info()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ip: req.peer_addr().map(|addr| addr.ip()),
+            time: Utc::now(),
+        }
+    }
+}
+
+/// An IP network in CIDR notation, such as `10.0.0.0/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    /// Parses a network in CIDR notation. A bare address is a network of one address.
+    pub fn parse(network: &str) -> Result<Self, String> {
+        let (addr, prefix) = network.split_once('/').unwrap_or((network, ""));
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("Invalid IP address {}", addr))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() {
+            max
+        } else {
+            prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("Invalid prefix length {}", prefix))?
+        };
+
+        Ok(Self { addr, prefix })
+    }
+
+    /// Checks whether an address belongs to the network.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A condition over the attributes of a request, combining ownership, role and
+/// request attribute checks, such as "the `id` path parameter is the user, or the user is an admin".
+#[derive(Clone)]
+pub enum Condition {
+    /// The path parameter is the id of the requesting user. Never true for client tokens.
+    Owner(&'static str),
+    /// The subject has the role, directly or through inheritance.
+    Role(&'static str),
+    /// The request comes from the network.
+    IpIn(IpNet),
+    /// The request is made between two times of day, UTC. The window may span midnight.
+    TimeBetween(NaiveTime, NaiveTime),
+    /// Any of the conditions holds.
+    Any(Vec<Condition>),
+    /// All of the conditions hold.
+    All(Vec<Condition>),
+}
+
+impl Condition {
+    /// Combines two conditions, either of which must hold.
+    pub fn or(self, other: Condition) -> Self {
+        match self {
+            Condition::Any(mut conditions) => {
+                conditions.push(other);
+                Condition::Any(conditions)
+            }
+            condition => Condition::Any(vec![condition, other]),
+        }
+    }
+
+    /// Combines two conditions, both of which must hold.
+    pub fn and(self, other: Condition) -> Self {
+        match self {
+            Condition::All(mut conditions) => {
+                conditions.push(other);
+                Condition::All(conditions)
+            }
+            condition => Condition::All(vec![condition, other]),
+        }
+    }
+
+    /// Evaluates the condition against the attributes of a request.
+    /// Handlers can evaluate conditions themselves with a context built by `PolicyContext::from_request`.
+    pub fn evaluate(&self, ctx: &PolicyContext) -> bool {
+        match self {
+            Condition::Owner(param) => {
+                !ctx.claims.is_client() && ctx.params.get(*param) == Some(&ctx.claims.id)
+            }
+            Condition::Role(role) => ctx.roles.contains(*role),
+            Condition::IpIn(network) => ctx.ip.is_some_and(|ip| network.contains(&ip)),
+            Condition::TimeBetween(from, to) => {
+                let now = ctx.time.time();
+                if from <= to {
+                    *from <= now && now < *to
+                } else {
+                    *from <= now || now < *to
+                }
+            }
+            Condition::Any(conditions) => conditions.iter().any(|c| c.evaluate(ctx)),
+            Condition::All(conditions) => conditions.iter().all(|c| c.evaluate(ctx)),
+        }
+    }
+}
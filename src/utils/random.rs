@@ -0,0 +1,36 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// Generates `len` random bytes.
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; len];
+    OsRng.fill_bytes(&mut buf);
+    buf
+}
+
+/// Generates an unguessable URL-safe token from `bytes` random bytes.
+pub fn random_token(bytes: usize) -> String {
+    URL_SAFE_NO_PAD.encode(random_bytes(bytes))
+}
+
+/// Characters of user codes: consonants only, so codes never spell words
+/// and are easy to type (RFC 8628 section 6.1).
+pub const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Generates an 8 character user code, formatted as `XXXX-XXXX`.
+pub fn random_user_code() -> String {
+    let mut code = String::with_capacity(9);
+    let mut buf = [0u8; 1];
+    while code.len() < 9 {
+        if code.len() == 4 {
+            code.push('-');
+        }
+        OsRng.fill_bytes(&mut buf);
+        // Reject bytes past the last full multiple of the alphabet size to avoid bias.
+        let limit = 256 - 256 % USER_CODE_ALPHABET.len();
+        if (buf[0] as usize) < limit {
+            code.push(USER_CODE_ALPHABET[buf[0] as usize % USER_CODE_ALPHABET.len()] as char);
+        }
+    }
+    code
+}
@@ -0,0 +1,171 @@
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use url::form_urlencoded;
+
+use super::{password::constant_time_eq, random::random_bytes};
+
+/// Length of a time step in seconds (RFC 6238 section 4.1).
+const TIME_STEP: i64 = 30;
+
+/// Number of digits of a code.
+const DIGITS: u32 = 6;
+
+/// Steps accepted before and after the current one, tolerating clock drift.
+const SKEW: i64 = 1;
+
+/// Generates a 160 bit secret, base32 encoded as authenticator apps expect.
+pub fn generate_secret() -> String {
+    BASE32_NOPAD.encode(&random_bytes(20))
+}
+
+/// Builds the `otpauth://` URI authenticator apps enroll from, usually shown as a QR code.
+///
+/// # Arguments
+///
+/// * `issuer` - The name of the service shown by the authenticator.
+/// * `account` - The name of the account shown by the authenticator.
+/// * `secret` - The base32 encoded secret.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let label: String =
+        form_urlencoded::byte_serialize(format!("{}:{}", issuer, account).as_bytes()).collect();
+    let query = form_urlencoded::Serializer::new(String::new())
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &TIME_STEP.to_string())
+        .finish();
+
+    // Authenticators do not read `+` as a space.
+    format!("otpauth://totp/{}?{}", label, query).replace('+', "%20")
+}
+
+/// Computes the HOTP value of a counter (RFC 4226 section 5.3).
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation.
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    value % 10u32.pow(DIGITS)
+}
+
+/// Verifies a code against a secret at the given time.
+/// Every accepted step is compared in constant time, so timing reveals neither the code
+/// nor which step it matched.
+///
+/// # Arguments
+///
+/// * `secret` - The base32 encoded secret.
+/// * `code` - The code entered by the user.
+/// * `now` - Unix timestamp to verify the code at.
+///
+/// # Returns
+///
+/// * `Some(u64)` - The time step the code belongs to, so it can be marked as used.
+/// * `None` - If the code or the secret is invalid.
+pub fn verify(secret: &str, code: &str, now: i64) -> Option<u64> {
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+
+    let current = now / TIME_STEP;
+    (current - SKEW..=current + SKEW)
+        .filter(|step| *step >= 0)
+        .map(|step| step as u64)
+        .fold(None, |found, step| {
+            let expected = format!("{:0width$}", hotp(&key, step), width = DIGITS as usize);
+            let matches = constant_time_eq(&expected, code);
+            found.or(matches.then_some(step))
+        })
+}
+
+/// Generates a recovery code of 80 random bits, formatted as `XXXX-XXXX-XXXX-XXXX`.
+pub fn generate_recovery_code() -> String {
+    let code = BASE32_NOPAD.encode(&random_bytes(10));
+    code.as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Hashes a recovery code for storage. Dashes, spaces and case are ignored.
+/// Recovery codes are random, a fast hash is enough.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    HEXLOWER.encode(&Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ASCII secret of the RFC 4226 and RFC 6238 test vectors.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn hotp_matches_rfc4226_vectors() {
+        // RFC 4226 appendix D.
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(
+                hotp(RFC_SECRET, counter as u64),
+                *code,
+                "counter {}",
+                counter
+            );
+        }
+    }
+
+    #[test]
+    fn verify_matches_rfc6238_vectors() {
+        // RFC 6238 appendix B, SHA1, truncated to six digits.
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let vectors = [
+            (59, "287082", 1),
+            (1111111109, "081804", 37037036),
+            (1111111111, "050471", 37037037),
+            (1234567890, "005924", 41152263),
+            (2000000000, "279037", 66666666),
+            (20000000000, "353130", 666666666),
+        ];
+        for (time, code, step) in vectors {
+            assert_eq!(verify(&secret, code, time), Some(step), "time {}", time);
+        }
+    }
+
+    #[test]
+    fn verify_tolerates_one_step_of_drift() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        assert_eq!(verify(&secret, "287082", 59 + TIME_STEP), Some(1));
+        assert_eq!(verify(&secret, "287082", 59 - TIME_STEP), Some(1));
+        assert_eq!(verify(&secret, "287082", 59 + 2 * TIME_STEP), None);
+    }
+
+    #[test]
+    fn verify_rejects_malformed_codes() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        assert_eq!(verify(&secret, "28708", 59), None);
+        assert_eq!(verify(&secret, "2870822", 59), None);
+        assert_eq!(verify(&secret, "28708a", 59), None);
+        assert_eq!(verify("not base32!", "287082", 59), None);
+    }
+}
@@ -0,0 +1,245 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// COSE algorithm identifiers of the accepted credential keys (RFC 9053), in order of preference.
+pub const ES256: i64 = -7;
+pub const EDDSA: i64 = -8;
+pub const RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: &[i64] = &[ES256, EDDSA, RS256];
+
+/// Authenticator data flags (WebAuthn section 6.1).
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Decodes a base64url value, with or without padding.
+pub fn decode(value: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| String::from("Invalid base64url value"))
+}
+
+/// The fields of the client data checked by the relying party.
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Checks the client data of a ceremony.
+///
+/// # Arguments
+///
+/// * `client_data_json` - The raw `clientDataJSON`.
+/// * `ceremony` - The expected type, `webauthn.create` or `webauthn.get`.
+/// * `origins` - The origins the ceremony may come from.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The challenge the client data is bound to.
+/// * `Err(String)` - If the client data is malformed or comes from another ceremony or origin.
+pub fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony: &str,
+    origins: &[String],
+) -> Result<String, String> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|_| String::from("Invalid client data"))?;
+
+    if client_data.kind != ceremony {
+        return Err(format!("Expected a {} ceremony", ceremony));
+    }
+    if !origins.contains(&client_data.origin) {
+        return Err(format!("Origin {} is not allowed", client_data.origin));
+    }
+
+    Ok(client_data.challenge)
+}
+
+/// The credential created by a registration ceremony.
+pub struct AttestedCredential {
+    pub id: Vec<u8>,
+    /// COSE encoded public key.
+    pub public_key: Vec<u8>,
+}
+
+/// Parsed authenticator data (WebAuthn section 6.1).
+pub struct AuthenticatorData {
+    pub sign_count: u32,
+    /// Present for registration ceremonies.
+    pub credential: Option<AttestedCredential>,
+}
+
+/// Parses authenticator data, checking it is scoped to the relying party and that the
+/// user was present and verified.
+///
+/// # Arguments
+///
+/// * `data` - The raw authenticator data.
+/// * `rp_id` - The relying party id the credential must be scoped to.
+///
+/// # Returns
+///
+/// * `Ok(AuthenticatorData)` - The signature counter and the attested credential, if any.
+/// * `Err(String)` - If the data is malformed or the checks fail.
+pub fn parse_authenticator_data(data: &[u8], rp_id: &str) -> Result<AuthenticatorData, String> {
+    if data.len() < 37 {
+        return Err(String::from("Authenticator data is too short"));
+    }
+    if data[..32] != Sha256::digest(rp_id.as_bytes())[..] {
+        return Err(String::from(
+            "Credential is scoped to another relying party",
+        ));
+    }
+
+    let flags = data[32];
+    if flags & FLAG_USER_PRESENT == 0 || flags & FLAG_USER_VERIFIED == 0 {
+        return Err(String::from("User was not verified"));
+    }
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    if flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+        return Ok(AuthenticatorData {
+            sign_count,
+            credential: None,
+        });
+    }
+
+    // AAGUID (16 bytes), credential id length (2 bytes), credential id, COSE public key.
+    let attested = &data[37..];
+    if attested.len() < 18 {
+        return Err(String::from("Attested credential data is too short"));
+    }
+    let id_len = u16::from_be_bytes([attested[16], attested[17]]) as usize;
+    let id = attested
+        .get(18..18 + id_len)
+        .ok_or_else(|| String::from("Credential id is truncated"))?
+        .to_vec();
+
+    // The public key is followed by optional extensions, only decoding tells its length.
+    let key_start = &attested[18 + id_len..];
+    let mut rest = key_start;
+    ciborium::from_reader::<Value, _>(&mut rest).map_err(|_| String::from("Invalid public key"))?;
+    let public_key = key_start[..key_start.len() - rest.len()].to_vec();
+    validate_public_key(&public_key)?;
+
+    Ok(AuthenticatorData {
+        sign_count,
+        credential: Some(AttestedCredential { id, public_key }),
+    })
+}
+
+/// Extracts the authenticator data from an attestation object.
+/// The attestation statement is not verified, registrations request `none` attestation
+/// so any authenticator, software ones included, can register.
+pub fn attested_authenticator_data(attestation_object: &[u8]) -> Result<Vec<u8>, String> {
+    let value: Value = ciborium::from_reader(attestation_object)
+        .map_err(|_| String::from("Invalid attestation object"))?;
+
+    value
+        .as_map()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|(key, _)| key.as_text() == Some("authData"))
+        })
+        .and_then(|(_, value)| value.as_bytes())
+        .cloned()
+        .ok_or_else(|| String::from("Attestation object has no authenticator data"))
+}
+
+/// A credential public key in one of the supported algorithms.
+enum PublicKey {
+    Es256(Vec<u8>),
+    Ed25519(Vec<u8>),
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+/// Decodes a COSE key (RFC 9052 section 7).
+fn public_key(cose_key: &[u8]) -> Result<PublicKey, String> {
+    let value: Value =
+        ciborium::from_reader(cose_key).map_err(|_| String::from("Invalid public key"))?;
+    let entries = value
+        .as_map()
+        .ok_or_else(|| String::from("Invalid public key"))?;
+    let get = |label: i64| {
+        entries
+            .iter()
+            .find(|(key, _)| key.as_integer() == Some(label.into()))
+            .map(|(_, value)| value)
+    };
+    let integer = |label: i64| get(label).and_then(Value::as_integer).map(i128::from);
+    let bytes = |label: i64| {
+        get(label)
+            .and_then(Value::as_bytes)
+            .cloned()
+            .ok_or_else(|| String::from("Incomplete public key"))
+    };
+
+    match integer(3).map(|alg| alg as i64) {
+        // EC2 key on P-256.
+        Some(ES256) if integer(-1) == Some(1) => {
+            let (x, y) = (bytes(-2)?, bytes(-3)?);
+            if x.len() != 32 || y.len() != 32 {
+                return Err(String::from("Invalid P-256 point"));
+            }
+            Ok(PublicKey::Es256([&[0x04], &x[..], &y[..]].concat()))
+        }
+        // OKP key on Ed25519.
+        Some(EDDSA) if integer(-1) == Some(6) => Ok(PublicKey::Ed25519(bytes(-2)?)),
+        Some(RS256) => Ok(PublicKey::Rs256 {
+            n: bytes(-1)?,
+            e: bytes(-2)?,
+        }),
+        _ => Err(String::from("Unsupported public key algorithm")),
+    }
+}
+
+/// Checks a COSE key uses one of the supported algorithms.
+pub fn validate_public_key(cose_key: &[u8]) -> Result<(), String> {
+    public_key(cose_key).map(|_| ())
+}
+
+/// Verifies an assertion signature over `authenticatorData || SHA-256(clientDataJSON)`.
+///
+/// # Arguments
+///
+/// * `cose_key` - The COSE encoded public key of the credential.
+/// * `authenticator_data` - The raw authenticator data of the assertion.
+/// * `client_data_json` - The raw client data of the assertion.
+/// * `signature` - The assertion signature.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the signature is valid.
+/// * `Err(String)` - If the key is unusable or the signature is invalid.
+pub fn verify_assertion(
+    cose_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), String> {
+    let message = [authenticator_data, &Sha256::digest(client_data_json)[..]].concat();
+
+    let verified = match public_key(cose_key)? {
+        PublicKey::Es256(point) => {
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                .verify(&message, signature)
+        }
+        PublicKey::Ed25519(key) => {
+            UnparsedPublicKey::new(&signature::ED25519, key).verify(&message, signature)
+        }
+        PublicKey::Rs256 { n, e } => RsaPublicKeyComponents { n, e }.verify(
+            &signature::RSA_PKCS1_2048_8192_SHA256,
+            &message,
+            signature,
+        ),
+    };
+
+    verified.map_err(|_| String::from("Invalid signature"))
+}
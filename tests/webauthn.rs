@@ -0,0 +1,836 @@
+//! Drives WebAuthn registration and passkey login end to end with a software authenticator,
+//! against in-memory repositories.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Once},
+};
+
+use auth_service::{
+    error::{redis_error::RedisError, service_error::ServiceError},
+    model::{
+        account::Account,
+        mfa::{MfaChallenge, TotpSecret},
+        oauth::{AuthorizationCode, ConsentRequest, DeviceAuthorization},
+        session::{DeviceInfo, LoginSession, Session},
+        token::RefreshTokenRecord,
+        webauthn::{
+            AssertionResponse, AttestationResponse, AuthenticationCredential,
+            RegistrationCredential, WebAuthnChallenge, WebAuthnCredential,
+        },
+    },
+    service::{auth_service::AuthService, webauthn_service::WebAuthnService},
+    traits::{
+        account_trait::AccountRepository, redis_traits::TokenRedisRepository,
+        webauthn_trait::WebAuthnRepository,
+    },
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+use sha2::{Digest, Sha256};
+
+const RP_ID: &str = "auth.example.com";
+const ORIGIN: &str = "https://auth.example.com";
+const USER_ID: &str = "1";
+const USERNAME: &str = "alice";
+
+/// Authenticator data flags: user present, user verified, attested credential data.
+const FLAG_UP: u8 = 0x01;
+const FLAG_UV: u8 = 0x04;
+const FLAG_AT: u8 = 0x40;
+
+static ENV: Once = Once::new();
+
+/// Configures the relying party and the token secrets once for every test.
+fn setup() {
+    ENV.call_once(|| {
+        std::env::set_var("WEBAUTHN_RP_ID", RP_ID);
+        std::env::set_var("WEBAUTHN_ORIGINS", ORIGIN);
+        std::env::set_var("JWT_ALGORITHM", "HS256");
+        std::env::set_var("JWT_ACCESS_SECRET", "access-secret");
+        std::env::set_var("JWT_REFRESH_SECRET", "refresh-secret");
+    });
+}
+
+/// An account repository holding the single test account, without TOTP.
+struct FakeAccountRepo;
+
+fn account() -> Account {
+    Account {
+        id: 1,
+        username: String::from(USERNAME),
+        password: None,
+        email: Some(String::from("alice@example.com")),
+        email_verified: true,
+        roles: vec![String::from("user")],
+    }
+}
+
+impl AccountRepository for FakeAccountRepo {
+    async fn insert_account(
+        &self,
+        _username: String,
+        _password: String,
+        _email: String,
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn get_account_by_username(&self, username: &str) -> Result<Account, sqlx::Error> {
+        match username {
+            USERNAME => Ok(account()),
+            _ => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, sqlx::Error> {
+        match id {
+            1 => Ok(account()),
+            _ => Err(sqlx::Error::RowNotFound),
+        }
+    }
+
+    async fn get_account_by_email(&self, _email: &str) -> Result<Account, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn get_roles_scopes(&self, _roles: &[String]) -> Result<Vec<String>, sqlx::Error> {
+        Ok(vec![String::from("accounts:read")])
+    }
+
+    async fn is_account_exist(&self, _username: &str) -> Result<(), sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn set_email_verified(&self, _id: i32, _email: &str) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn update_password(&self, _id: i32, _password: &str) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn update_account(
+        &self,
+        _id: i32,
+        _username: Option<&str>,
+        _email: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn get_totp(&self, _account_id: i32) -> Result<TotpSecret, sqlx::Error> {
+        Err(sqlx::Error::RowNotFound)
+    }
+
+    async fn upsert_totp(&self, _account_id: i32, _secret: &str) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn confirm_totp(
+        &self,
+        _account_id: i32,
+        _step: i64,
+        _recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn use_totp_step(&self, _account_id: i32, _step: i64) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn use_recovery_code(
+        &self,
+        _account_id: i32,
+        _code_hash: &str,
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        _account_id: i32,
+        _recovery_code_hashes: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+
+    async fn delete_totp(&self, _account_id: i32) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+}
+
+/// A Redis repository keeping ceremony challenges in memory and accepting new sessions.
+#[derive(Default)]
+struct FakeRedisRepo {
+    challenges: Mutex<HashMap<String, String>>,
+}
+
+impl TokenRedisRepository for FakeRedisRepo {
+    async fn store_refresh_token(
+        &self,
+        _user_id: &str,
+        _family_id: &str,
+        _client_id: Option<&str>,
+        _token: &str,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        Ok(())
+    }
+
+    async fn get_refresh_token(
+        &self,
+        _token: &str,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        _token: &str,
+        _used_ttl: i64,
+        _new_token: &str,
+        _ttl: i64,
+        _access_jti: &str,
+        _access_exp: i64,
+    ) -> Result<Option<RefreshTokenRecord>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_used_token_family(&self, _token: &str) -> Result<Option<String>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn revoke_token_family(&self, _family_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn delete_refresh_token(&self, _token: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn delete_family_tokens(&self, _family_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn create_session(&self, _session: &Session, _ttl: i64) -> Result<(), RedisError> {
+        Ok(())
+    }
+
+    async fn touch_session(
+        &self,
+        _user_id: &str,
+        _session_id: &str,
+        _last_used_at: i64,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn list_sessions(&self, _user_id: &str) -> Result<Vec<Session>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn revoke_user_sessions(&self, _user_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn revoke_client_sessions(&self, _client_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn set_tokens_valid_after(
+        &self,
+        _user_id: &str,
+        _timestamp: i64,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_tokens_valid_after(&self, _user_id: &str) -> Result<Option<i64>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_session_tokens_valid_after(
+        &self,
+        _session_id: &str,
+    ) -> Result<Option<i64>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn set_session_access_token(
+        &self,
+        _session_id: &str,
+        _jti: &str,
+        _exp: i64,
+    ) -> Result<(), RedisError> {
+        Ok(())
+    }
+
+    async fn deny_access_token(&self, _jti: &str, _ttl: i64) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn is_access_token_denied(&self, _jti: &str) -> Result<bool, RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_authorization_code(
+        &self,
+        _code: &str,
+        _authorization: &AuthorizationCode,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn consume_authorization_code(
+        &self,
+        _code: &str,
+    ) -> Result<Option<AuthorizationCode>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_device_authorization(
+        &self,
+        _device_code: &str,
+        _authorization: &DeviceAuthorization,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn find_device_code(&self, _user_code: &str) -> Result<Option<String>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_device_authorization(
+        &self,
+        _device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn update_device_authorization(
+        &self,
+        _device_code: &str,
+        _authorization: &DeviceAuthorization,
+    ) -> Result<bool, RedisError> {
+        unimplemented!()
+    }
+
+    async fn consume_device_authorization(
+        &self,
+        _device_code: &str,
+    ) -> Result<Option<DeviceAuthorization>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_consent_request(
+        &self,
+        _consent_id: &str,
+        _request: &ConsentRequest,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_consent_request(
+        &self,
+        _consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn consume_consent_request(
+        &self,
+        _consent_id: &str,
+    ) -> Result<Option<ConsentRequest>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn throttle_device_poll(
+        &self,
+        _device_code: &str,
+        _interval: i64,
+    ) -> Result<bool, RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_mfa_challenge(
+        &self,
+        _token: &str,
+        _challenge: &MfaChallenge,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_mfa_challenge(&self, _token: &str) -> Result<Option<MfaChallenge>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn consume_mfa_challenge(
+        &self,
+        _token: &str,
+    ) -> Result<Option<MfaChallenge>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn revoke_mfa_challenges(&self, _user_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn record_mfa_failure(&self, _token: &str, _ttl: i64) -> Result<i64, RedisError> {
+        unimplemented!()
+    }
+
+    async fn record_account_mfa_failure(
+        &self,
+        _user_id: &str,
+        _ttl: i64,
+    ) -> Result<i64, RedisError> {
+        unimplemented!()
+    }
+
+    async fn get_account_mfa_failures(&self, _user_id: &str) -> Result<i64, RedisError> {
+        unimplemented!()
+    }
+
+    async fn clear_account_mfa_failures(&self, _user_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_webauthn_challenge(
+        &self,
+        challenge: &str,
+        record: &WebAuthnChallenge,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        let record = serde_json::to_string(record).map_err(|_| RedisError::RedisError)?;
+        self.challenges
+            .lock()
+            .unwrap()
+            .insert(challenge.to_string(), record);
+        Ok(())
+    }
+
+    async fn consume_webauthn_challenge(
+        &self,
+        challenge: &str,
+    ) -> Result<Option<WebAuthnChallenge>, RedisError> {
+        self.challenges
+            .lock()
+            .unwrap()
+            .remove(challenge)
+            .map(|record| serde_json::from_str(&record).map_err(|_| RedisError::RedisError))
+            .transpose()
+    }
+
+    async fn store_password_reset(
+        &self,
+        _token: &str,
+        _user_id: &str,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn throttle_verification_mail(
+        &self,
+        _email: &str,
+        _interval: i64,
+    ) -> Result<bool, RedisError> {
+        unimplemented!()
+    }
+
+    async fn consume_password_reset(&self, _token: &str) -> Result<Option<String>, RedisError> {
+        unimplemented!()
+    }
+
+    async fn revoke_password_resets(&self, _user_id: &str) -> Result<(), RedisError> {
+        unimplemented!()
+    }
+
+    async fn store_login_session(
+        &self,
+        _token: &str,
+        _login: &LoginSession,
+        _ttl: i64,
+    ) -> Result<(), RedisError> {
+        Ok(())
+    }
+
+    async fn get_login_session(&self, _token: &str) -> Result<Option<LoginSession>, RedisError> {
+        unimplemented!()
+    }
+}
+
+/// A credential repository behaving like `sql/update_webauthn_sign_count.sql`.
+#[derive(Default)]
+struct FakeCredentialRepo {
+    credentials: Mutex<Vec<WebAuthnCredential>>,
+}
+
+fn copy(credential: &WebAuthnCredential) -> WebAuthnCredential {
+    WebAuthnCredential {
+        credential_id: credential.credential_id.clone(),
+        account_id: credential.account_id,
+        public_key: credential.public_key.clone(),
+        sign_count: credential.sign_count,
+        name: credential.name.clone(),
+        created_at: credential.created_at,
+        last_used_at: credential.last_used_at,
+    }
+}
+
+impl WebAuthnRepository for FakeCredentialRepo {
+    async fn insert_credential(&self, credential: &WebAuthnCredential) -> Result<u64, sqlx::Error> {
+        self.credentials.lock().unwrap().push(copy(credential));
+        Ok(1)
+    }
+
+    async fn get_credential(&self, credential_id: &str) -> Result<WebAuthnCredential, sqlx::Error> {
+        self.credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|c| c.credential_id == credential_id)
+            .map(copy)
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    async fn list_credentials(
+        &self,
+        account_id: i32,
+    ) -> Result<Vec<WebAuthnCredential>, sqlx::Error> {
+        Ok(self
+            .credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| c.account_id == account_id)
+            .map(copy)
+            .collect())
+    }
+
+    async fn update_sign_count(
+        &self,
+        credential_id: &str,
+        sign_count: i64,
+        used_at: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let mut credentials = self.credentials.lock().unwrap();
+        match credentials.iter_mut().find(|c| {
+            c.credential_id == credential_id
+                && (c.sign_count < sign_count || (c.sign_count == 0 && sign_count == 0))
+        }) {
+            Some(credential) => {
+                credential.sign_count = sign_count;
+                credential.last_used_at = Some(used_at);
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    async fn delete_credential(
+        &self,
+        _credential_id: &str,
+        _account_id: i32,
+    ) -> Result<u64, sqlx::Error> {
+        unimplemented!()
+    }
+}
+
+type TestWebAuthnService = WebAuthnService<FakeAccountRepo, FakeRedisRepo, FakeCredentialRepo>;
+
+fn webauthn_service() -> TestWebAuthnService {
+    setup();
+    let account_repo = Arc::new(FakeAccountRepo);
+    let redis_repo = Arc::new(FakeRedisRepo::default());
+    let auth_service = Arc::new(AuthService::new(account_repo.clone(), redis_repo.clone()));
+    WebAuthnService::new(
+        auth_service,
+        account_repo,
+        redis_repo,
+        Arc::new(FakeCredentialRepo::default()),
+    )
+}
+
+/// A software authenticator holding one P-256 credential.
+struct SoftwareAuthenticator {
+    rng: SystemRandom,
+    key_pair: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+        Self {
+            rng,
+            key_pair,
+            credential_id: b"software-credential".to_vec(),
+        }
+    }
+
+    /// The credential id, base64url encoded.
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    /// The COSE encoding of the public key (RFC 9053 EC2 key).
+    fn cose_key(&self) -> Vec<u8> {
+        let point = self.key_pair.public_key().as_ref();
+        let key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point[1..33].to_vec())),
+            (Value::from(-3), Value::Bytes(point[33..65].to_vec())),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&key, &mut encoded).unwrap();
+        encoded
+    }
+
+    fn client_data(ceremony: &str, challenge: &str, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony,
+            "challenge": challenge,
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        [
+            &Sha256::digest(rp_id.as_bytes())[..],
+            &[flags],
+            &sign_count.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Answers `navigator.credentials.create()`.
+    fn register(&self, challenge: &str, origin: &str, rp_id: &str) -> RegistrationCredential {
+        let client_data_json = Self::client_data("webauthn.create", challenge, origin);
+        let auth_data = [
+            Self::authenticator_data(rp_id, FLAG_UP | FLAG_UV | FLAG_AT, 0),
+            vec![0; 16],
+            (self.credential_id.len() as u16).to_be_bytes().to_vec(),
+            self.credential_id.clone(),
+            self.cose_key(),
+        ]
+        .concat();
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(Vec::new())),
+            (Value::from("authData"), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        RegistrationCredential {
+            id: self.id(),
+            response: AttestationResponse {
+                client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                attestation_object: URL_SAFE_NO_PAD.encode(attestation_object),
+            },
+            name: Some(String::from("Software key")),
+        }
+    }
+
+    /// Answers `navigator.credentials.get()`.
+    fn assert(
+        &self,
+        challenge: &str,
+        origin: &str,
+        rp_id: &str,
+        sign_count: u32,
+    ) -> AuthenticationCredential {
+        let client_data_json = Self::client_data("webauthn.get", challenge, origin);
+        let auth_data = Self::authenticator_data(rp_id, FLAG_UP | FLAG_UV, sign_count);
+        let message = [&auth_data[..], &Sha256::digest(&client_data_json)[..]].concat();
+        let signature = self.key_pair.sign(&self.rng, &message).unwrap();
+
+        AuthenticationCredential {
+            id: self.id(),
+            response: AssertionResponse {
+                client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                signature: URL_SAFE_NO_PAD.encode(signature.as_ref()),
+                user_handle: Some(URL_SAFE_NO_PAD.encode(USER_ID)),
+            },
+        }
+    }
+}
+
+/// Registers the authenticator's credential for the test account.
+async fn register(service: &TestWebAuthnService, authenticator: &SoftwareAuthenticator) {
+    let options = service.start_registration(USER_ID).await.unwrap();
+    let credential = authenticator.register(&options.public_key.challenge, ORIGIN, RP_ID);
+    service
+        .finish_registration(USER_ID, credential)
+        .await
+        .unwrap();
+}
+
+/// Runs a passkey login with the given signature counter.
+async fn login(
+    service: &TestWebAuthnService,
+    authenticator: &SoftwareAuthenticator,
+    sign_count: u32,
+) -> Result<(), ServiceError> {
+    let options = service.start_authentication(Some(USERNAME)).await?;
+    let assertion = authenticator.assert(&options.public_key.challenge, ORIGIN, RP_ID, sign_count);
+    service
+        .finish_authentication(assertion, DeviceInfo::default())
+        .await
+        .map(|_| ())
+}
+
+#[actix_web::test]
+async fn registers_and_logs_in_with_a_passkey() {
+    let service = webauthn_service();
+    let authenticator = SoftwareAuthenticator::new();
+
+    register(&service, &authenticator).await;
+    let credentials = service.list_credentials(USER_ID).await.unwrap();
+    assert_eq!(credentials.len(), 1);
+    assert_eq!(credentials[0].credential_id, authenticator.id());
+
+    let options = service.start_authentication(Some(USERNAME)).await.unwrap();
+    assert_eq!(
+        options.public_key.allow_credentials[0].id,
+        authenticator.id()
+    );
+    let assertion = authenticator.assert(&options.public_key.challenge, ORIGIN, RP_ID, 1);
+    let token = service
+        .finish_authentication(assertion, DeviceInfo::default())
+        .await
+        .unwrap();
+    assert!(!token.access_token.is_empty());
+    assert!(token.login_session.is_some());
+}
+
+#[actix_web::test]
+async fn rejects_a_signature_counter_that_did_not_increase() {
+    let service = webauthn_service();
+    let authenticator = SoftwareAuthenticator::new();
+    register(&service, &authenticator).await;
+
+    login(&service, &authenticator, 5).await.unwrap();
+    assert!(matches!(
+        login(&service, &authenticator, 5).await,
+        Err(ServiceError::UnAuthorizedError)
+    ));
+    assert!(matches!(
+        login(&service, &authenticator, 3).await,
+        Err(ServiceError::UnAuthorizedError)
+    ));
+    login(&service, &authenticator, 6).await.unwrap();
+}
+
+#[actix_web::test]
+async fn rejects_registrations_from_another_origin_or_relying_party() {
+    let service = webauthn_service();
+    let authenticator = SoftwareAuthenticator::new();
+
+    let options = service.start_registration(USER_ID).await.unwrap();
+    let credential =
+        authenticator.register(&options.public_key.challenge, "https://evil.example", RP_ID);
+    assert!(matches!(
+        service.finish_registration(USER_ID, credential).await,
+        Err(ServiceError::BadRequest(_))
+    ));
+
+    let options = service.start_registration(USER_ID).await.unwrap();
+    let credential = authenticator.register(&options.public_key.challenge, ORIGIN, "evil.example");
+    assert!(matches!(
+        service.finish_registration(USER_ID, credential).await,
+        Err(ServiceError::BadRequest(_))
+    ));
+
+    assert!(service.list_credentials(USER_ID).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn rejects_assertions_from_another_origin_or_relying_party() {
+    let service = webauthn_service();
+    let authenticator = SoftwareAuthenticator::new();
+    register(&service, &authenticator).await;
+
+    let options = service.start_authentication(Some(USERNAME)).await.unwrap();
+    let assertion = authenticator.assert(
+        &options.public_key.challenge,
+        "https://evil.example",
+        RP_ID,
+        1,
+    );
+    assert!(matches!(
+        service
+            .finish_authentication(assertion, DeviceInfo::default())
+            .await,
+        Err(ServiceError::BadRequest(_))
+    ));
+
+    let options = service.start_authentication(Some(USERNAME)).await.unwrap();
+    let assertion = authenticator.assert(&options.public_key.challenge, ORIGIN, "evil.example", 1);
+    assert!(matches!(
+        service
+            .finish_authentication(assertion, DeviceInfo::default())
+            .await,
+        Err(ServiceError::UnAuthorizedError)
+    ));
+}
+
+#[actix_web::test]
+async fn rejects_a_replayed_challenge() {
+    let service = webauthn_service();
+    let authenticator = SoftwareAuthenticator::new();
+
+    let options = service.start_registration(USER_ID).await.unwrap();
+    let challenge = options.public_key.challenge;
+    service
+        .finish_registration(USER_ID, authenticator.register(&challenge, ORIGIN, RP_ID))
+        .await
+        .unwrap();
+    assert!(matches!(
+        service
+            .finish_registration(USER_ID, authenticator.register(&challenge, ORIGIN, RP_ID))
+            .await,
+        Err(ServiceError::BadRequest(_))
+    ));
+
+    let options = service.start_authentication(Some(USERNAME)).await.unwrap();
+    let challenge = options.public_key.challenge;
+    service
+        .finish_authentication(
+            authenticator.assert(&challenge, ORIGIN, RP_ID, 1),
+            DeviceInfo::default(),
+        )
+        .await
+        .unwrap();
+    // A fresh counter does not make up for a consumed challenge.
+    assert!(matches!(
+        service
+            .finish_authentication(
+                authenticator.assert(&challenge, ORIGIN, RP_ID, 2),
+                DeviceInfo::default(),
+            )
+            .await,
+        Err(ServiceError::BadRequest(_))
+    ));
+}